@@ -0,0 +1,510 @@
+use crate::lexer::{is_reserved, is_word_char, tokenize, Token, KEYWORDS};
+use crate::schema::{quote_name, Relation, Schema};
+use crate::script::{statement_ranges, Dialect};
+
+/// What a completion inserts, which decides where it is listed.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum CompletionKind {
+    Column,
+    Table,
+    Function,
+    Keyword,
+}
+
+impl CompletionKind {
+    pub fn label(self) -> &'static str {
+        match self {
+            CompletionKind::Column => "column",
+            CompletionKind::Table => "table",
+            CompletionKind::Function => "function",
+            CompletionKind::Keyword => "keyword",
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Completion {
+    /// What is shown in the list.
+    pub name: String,
+    /// What replaces the word being completed.
+    pub text: String,
+    pub kind: CompletionKind,
+}
+
+const FUNCTIONS: &[&str] = &[
+    "abs", "avg", "cast", "coalesce", "count", "lower", "max", "min", "nullif", "round", "substr",
+    "sum", "trim", "upper",
+];
+
+/// The functions only some backends have.
+fn dialect_functions(dialect: Dialect) -> &'static [&'static str] {
+    match dialect {
+        Dialect::Sqlite => &[
+            "date",
+            "datetime",
+            "group_concat",
+            "ifnull",
+            "instr",
+            "json_extract",
+            "length",
+            "printf",
+            "random",
+            "replace",
+            "strftime",
+            "time",
+            "typeof",
+        ],
+        Dialect::Postgres => &[
+            "array_agg",
+            "date_trunc",
+            "extract",
+            "format",
+            "generate_series",
+            "json_agg",
+            "jsonb_build_object",
+            "length",
+            "now",
+            "regexp_replace",
+            "string_agg",
+            "to_char",
+        ],
+        Dialect::MySql => &[
+            "concat",
+            "date_format",
+            "group_concat",
+            "ifnull",
+            "json_extract",
+            "length",
+            "now",
+            "replace",
+            "str_to_date",
+        ],
+        Dialect::DuckDb => &[
+            "date_trunc",
+            "list",
+            "list_value",
+            "now",
+            "read_csv",
+            "read_parquet",
+            "regexp_matches",
+            "string_agg",
+            "strftime",
+        ],
+    }
+}
+
+/// The completions for the word before `cursor`, a byte offset into
+/// `script`, and how many characters of that word they replace. Columns
+/// are those of the tables the statement under the cursor reads, or of the
+/// one named by the alias or table before a `.`.
+pub fn complete(
+    script: &str,
+    cursor: usize,
+    dialect: Dialect,
+    schema: Option<&Schema>,
+) -> (usize, Vec<Completion>) {
+    let statement = statement_ranges(script, dialect)
+        .into_iter()
+        .find(|range| range.start <= cursor && cursor <= range.end)
+        .unwrap_or(cursor..cursor);
+    let before = &script[statement.start..cursor];
+    let word_start = before.rfind(|c: char| !is_word_char(c)).map_or(0, |i| {
+        i + before[i..].chars().next().map_or(1, char::len_utf8)
+    });
+    let prefix = &before[word_start..];
+    let preceding = tokenize(&before[..word_start], dialect);
+    let relations = referenced_relations(&tokenize(&script[statement], dialect), dialect);
+    let all = || {
+        schema
+            .into_iter()
+            .flat_map(|schema| schema.tables.iter().chain(&schema.views))
+    };
+    let lookup =
+        |name: &str| all().find(|relation| relation.qualified_name().eq_ignore_ascii_case(name));
+
+    // Each with how a table or column is written, quoted where it has to be.
+    let mut candidates: Vec<(String, CompletionKind, String)> = Vec::new();
+    let tables = |candidates: &mut Vec<_>| {
+        candidates.extend(all().map(|relation| {
+            let written = match &relation.schema {
+                Some(schema) => format!(
+                    "{}.{}",
+                    identifier(schema, dialect),
+                    identifier(&relation.name, dialect)
+                ),
+                None => identifier(&relation.name, dialect),
+            };
+            (relation.qualified_name(), CompletionKind::Table, written)
+        }));
+    };
+    let columns = |candidates: &mut Vec<_>, relation: &Relation| {
+        candidates.extend(relation.columns.iter().map(|column| {
+            let written = identifier(&column.name, dialect);
+            (column.name.clone(), CompletionKind::Column, written)
+        }));
+    };
+
+    match preceding.as_slice() {
+        [.., Token::Word(qualifier) | Token::Quoted(qualifier), Token::Punct('.')] => {
+            let named = relations
+                .iter()
+                .find(|(name, alias)| {
+                    alias
+                        .as_ref()
+                        .is_some_and(|alias| alias.eq_ignore_ascii_case(qualifier))
+                        || unqualified(name).eq_ignore_ascii_case(qualifier)
+                })
+                .map_or(qualifier.as_str(), |(name, _)| name.as_str());
+            match lookup(named) {
+                Some(relation) => columns(&mut candidates, relation),
+                // Otherwise the tables in a schema of that name.
+                None => candidates.extend(
+                    all()
+                        .filter(|relation| {
+                            relation
+                                .schema
+                                .as_ref()
+                                .is_some_and(|schema| schema.eq_ignore_ascii_case(qualifier))
+                        })
+                        .map(|relation| {
+                            let written = identifier(&relation.name, dialect);
+                            (relation.name.clone(), CompletionKind::Table, written)
+                        }),
+                ),
+            }
+        }
+        [.., Token::Word(word)] if is_table_clause(word) => tables(&mut candidates),
+        [.., Token::Punct(',')] if in_from_list(&preceding, dialect) => tables(&mut candidates),
+        _ => {
+            for relation in relations.iter().filter_map(|(name, _)| lookup(name)) {
+                columns(&mut candidates, relation);
+            }
+            candidates.extend(
+                FUNCTIONS
+                    .iter()
+                    .chain(dialect_functions(dialect))
+                    .map(|name| (name.to_string(), CompletionKind::Function, String::new())),
+            );
+            candidates.extend(
+                KEYWORDS
+                    .iter()
+                    .map(|name| (name.to_string(), CompletionKind::Keyword, String::new())),
+            );
+            tables(&mut candidates);
+        }
+    }
+
+    let lowercase =
+        prefix.chars().any(char::is_alphabetic) && !prefix.chars().any(char::is_uppercase);
+    let folded = prefix.to_lowercase();
+    let mut completions: Vec<Completion> = Vec::new();
+    for (name, kind, written) in candidates {
+        let matched = name.to_lowercase();
+        if !matched.starts_with(&folded)
+            || matched == folded
+            || completions.iter().any(|completion| completion.name == name)
+        {
+            continue;
+        }
+        let text = match kind {
+            CompletionKind::Keyword if lowercase => name.to_lowercase(),
+            CompletionKind::Keyword => name.clone(),
+            CompletionKind::Function if !lowercase && !prefix.is_empty() => {
+                format!("{}(", name.to_uppercase())
+            }
+            CompletionKind::Function => format!("{}(", name),
+            CompletionKind::Column | CompletionKind::Table => written,
+        };
+        completions.push(Completion { name, text, kind });
+    }
+    (prefix.chars().count(), completions)
+}
+
+/// Whether a table name comes next after this keyword.
+fn is_table_clause(word: &str) -> bool {
+    ["FROM", "JOIN", "INTO", "UPDATE", "TABLE"]
+        .iter()
+        .any(|keyword| keyword.eq_ignore_ascii_case(word))
+}
+
+/// Whether the last keyword is a `FROM`, so that after a comma another
+/// table is named.
+fn in_from_list(tokens: &[Token], dialect: Dialect) -> bool {
+    tokens
+        .iter()
+        .rev()
+        .find_map(|token| match token {
+            Token::Word(word) if is_reserved(word, dialect) => {
+                Some(word.eq_ignore_ascii_case("FROM"))
+            }
+            _ => None,
+        })
+        .unwrap_or(false)
+}
+
+fn unqualified(name: &str) -> &str {
+    name.rsplit_once('.').map_or(name, |(_, name)| name)
+}
+
+/// A name as it has to be written, quoted if it would not be read back as
+/// it is.
+fn identifier(name: &str, dialect: Dialect) -> String {
+    let plain = name.starts_with(|c: char| c.is_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_alphanumeric() || c == '_')
+        && !is_reserved(name, dialect);
+    let folds_case = matches!(dialect, Dialect::Postgres | Dialect::DuckDb);
+    if plain && !(folds_case && name.chars().any(char::is_uppercase)) {
+        name.to_string()
+    } else {
+        quote_name(name, dialect)
+    }
+}
+
+/// The tables a statement names after `FROM`, `JOIN`, `UPDATE` and `INTO`,
+/// each with the alias it is given.
+pub fn referenced_relations(tokens: &[Token], dialect: Dialect) -> Vec<(String, Option<String>)> {
+    let mut relations = Vec::new();
+    let mut i = 0;
+    while i < tokens.len() {
+        let Token::Word(word) = &tokens[i] else {
+            i += 1;
+            continue;
+        };
+        i += 1;
+        if !["FROM", "JOIN", "UPDATE", "INTO"]
+            .iter()
+            .any(|keyword| keyword.eq_ignore_ascii_case(word))
+        {
+            continue;
+        }
+        loop {
+            let mut parts = Vec::new();
+            while let Some(part) = tokens.get(i).and_then(|token| name(token, dialect)) {
+                parts.push(part);
+                i += 1;
+                if tokens.get(i) != Some(&Token::Punct('.')) {
+                    break;
+                }
+                i += 1;
+            }
+            if parts.is_empty() {
+                break;
+            }
+            if matches!(tokens.get(i), Some(Token::Word(word)) if word.eq_ignore_ascii_case("AS")) {
+                i += 1;
+            }
+            let alias = tokens.get(i).and_then(|token| alias(token, dialect));
+            if alias.is_some() {
+                i += 1;
+            }
+            relations.push((parts.join("."), alias.map(str::to_string)));
+            if tokens.get(i) != Some(&Token::Punct(',')) {
+                break;
+            }
+            i += 1;
+        }
+    }
+    relations
+}
+
+/// The words that can follow a table to start the next clause. Not every
+/// dialect reserves them, but none of them is read as an alias.
+const CLAUSE_KEYWORDS: &[&str] = &[
+    "CROSS",
+    "EXCEPT",
+    "FETCH",
+    "FOR",
+    "FULL",
+    "GROUP",
+    "HAVING",
+    "INNER",
+    "INTERSECT",
+    "JOIN",
+    "LATERAL",
+    "LEFT",
+    "LIMIT",
+    "NATURAL",
+    "OFFSET",
+    "ON",
+    "ORDER",
+    "QUALIFY",
+    "RETURNING",
+    "RIGHT",
+    "SET",
+    "UNION",
+    "USING",
+    "VALUES",
+    "WHERE",
+    "WINDOW",
+];
+
+/// The alias a token gives a table: a name that is not a clause keyword.
+fn alias(token: &Token, dialect: Dialect) -> Option<&str> {
+    match token {
+        Token::Word(word) if CLAUSE_KEYWORDS.iter().any(|k| k.eq_ignore_ascii_case(word)) => None,
+        _ => name(token, dialect),
+    }
+}
+
+/// The name a token gives: a quoted name, or a word the dialect does not
+/// reserve.
+pub fn name(token: &Token, dialect: Dialect) -> Option<&str> {
+    match token {
+        Token::Word(word) if !is_reserved(word, dialect) => Some(word),
+        Token::Quoted(name) => Some(name),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::ColumnInfo;
+
+    fn relation(name: &str, columns: &[&str]) -> Relation {
+        let (schema, name) = match name.split_once('.') {
+            Some((schema, name)) => (Some(schema.to_string()), name),
+            None => (None, name),
+        };
+        Relation {
+            schema,
+            name: name.to_string(),
+            columns: columns
+                .iter()
+                .map(|column| ColumnInfo {
+                    name: column.to_string(),
+                    data_type: String::new(),
+                    not_null: false,
+                    primary_key: false,
+                    default: None,
+                    references: None,
+                })
+                .collect(),
+        }
+    }
+
+    fn schema() -> Schema {
+        Schema {
+            tables: vec![
+                relation("orders", &["id", "total"]),
+                relation("users", &["id", "email"]),
+                relation("sales.invoices", &["number", "due"]),
+            ],
+            ..Schema::default()
+        }
+    }
+
+    /// The names offered with the cursor at the `|` in `script`.
+    fn names(script: &str, dialect: Dialect) -> Vec<String> {
+        let cursor = script.find('|').unwrap();
+        let script = script.replace('|', "");
+        let schema = schema();
+        let (_, completions) = complete(&script, cursor, dialect, Some(&schema));
+        completions.into_iter().map(|c| c.name).collect()
+    }
+
+    fn relations(sql: &str, dialect: Dialect) -> Vec<(String, Option<String>)> {
+        referenced_relations(&tokenize(sql, dialect), dialect)
+    }
+
+    fn named(name: &str, alias: Option<&str>) -> (String, Option<String>) {
+        (name.to_string(), alias.map(str::to_string))
+    }
+
+    #[test]
+    fn reads_tables_and_aliases_from_a_comma_list() {
+        assert_eq!(
+            relations(
+                "SELECT * FROM users u, orders AS o, sales.invoices",
+                Dialect::Postgres
+            ),
+            [
+                named("users", Some("u")),
+                named("orders", Some("o")),
+                named("sales.invoices", None),
+            ]
+        );
+    }
+
+    #[test]
+    fn clause_keywords_are_not_aliases() {
+        assert_eq!(
+            relations("UPDATE users SET email = NULL", Dialect::Postgres),
+            [named("users", None)]
+        );
+        assert_eq!(
+            relations("SELECT * FROM users QUALIFY true", Dialect::DuckDb),
+            [named("users", None)]
+        );
+        assert_eq!(
+            relations(
+                "INSERT INTO users DEFAULT VALUES RETURNING id",
+                Dialect::Sqlite
+            ),
+            [named("users", None)]
+        );
+        assert_eq!(
+            relations(
+                "SELECT sum(id) OVER w FROM users WINDOW w AS (ORDER BY id)",
+                Dialect::Sqlite
+            ),
+            [named("users", None)]
+        );
+    }
+
+    #[test]
+    fn an_alias_names_the_columns_of_its_table() {
+        assert_eq!(
+            names("SELECT u.| FROM users u", Dialect::Sqlite),
+            ["id", "email"]
+        );
+        assert_eq!(
+            names(
+                "SELECT o.t| FROM users u JOIN orders o ON true",
+                Dialect::Postgres
+            ),
+            ["total"]
+        );
+    }
+
+    #[test]
+    fn an_alias_belongs_to_its_own_statement() {
+        let script = "SELECT * FROM orders u;\nSELECT u.| FROM users u";
+        assert_eq!(names(script, Dialect::Sqlite), ["id", "email"]);
+    }
+
+    #[test]
+    fn a_table_name_qualifies_its_columns() {
+        assert_eq!(
+            names("SELECT users.e| FROM users", Dialect::Sqlite),
+            ["email"]
+        );
+        assert_eq!(
+            names("SELECT invoices.| FROM sales.invoices", Dialect::Postgres),
+            ["number", "due"]
+        );
+    }
+
+    #[test]
+    fn a_schema_qualifies_its_tables() {
+        assert_eq!(
+            names("SELECT * FROM sales.|", Dialect::Postgres),
+            ["invoices"]
+        );
+    }
+
+    #[test]
+    fn a_comma_in_the_from_list_offers_tables() {
+        assert_eq!(
+            names("SELECT * FROM users, o|", Dialect::Sqlite),
+            ["orders"]
+        );
+    }
+
+    #[test]
+    fn columns_come_from_every_table_read() {
+        let offered = names("SELECT | FROM users, orders LIMIT 1", Dialect::Sqlite);
+        assert!(offered.contains(&"email".to_string()));
+        assert!(offered.contains(&"total".to_string()));
+    }
+}
@@ -1,7 +1,1179 @@
-use sqlx::Sqlite;
+use futures::{Stream, StreamExt};
+use sqlx::database::HasArguments;
+use sqlx::mysql::{MySqlArguments, MySqlTypeInfo};
+use sqlx::mysql::{MySqlConnectOptions, MySqlDatabaseError, MySqlQueryResult, MySqlRow};
+use sqlx::pool::{PoolConnection, PoolOptions};
+use sqlx::postgres::{PgArguments, PgConnectOptions, PgQueryResult, PgRow, PgTypeInfo, PgTypeKind};
+use sqlx::sqlite::{
+    SqliteArguments, SqliteConnectOptions, SqliteQueryResult, SqliteRow, SqliteTypeInfo,
+};
+use sqlx::types::chrono::{DateTime, NaiveDate, NaiveDateTime, NaiveTime, Utc};
+use sqlx::types::{BigDecimal, JsonValue, Uuid};
+use sqlx::{
+    Arguments, Column, ColumnIndex, Decode, Either, Executor, IntoArguments, MySql, Pool, Postgres,
+    Row, Sqlite, Statement, TypeInfo, ValueRef,
+};
 use std::error::Error;
+use std::future::Future;
+use std::ops::{Deref, DerefMut};
+use std::ptr::NonNull;
+use std::str::FromStr;
+use std::sync::Arc;
+#[cfg(feature = "duckdb")]
+use std::sync::{Mutex, OnceLock, PoisonError};
+use tokio::sync::mpsc;
 
-use crate::{config::Config, config::StartingDb, App};
+use crate::config::{Config, Profile, StartingDb};
+use crate::model::{CellValue, Results};
+use crate::script::Dialect;
+
+/// A live connection pool to one of the supported database backends.
+#[derive(Clone, Debug)]
+pub enum Connection {
+    Sqlite(Pool<Sqlite>),
+    Postgres(Pool<Postgres>),
+    MySql(Pool<MySql>),
+    /// DuckDB is embedded and synchronous, so queries against it are run on
+    /// the blocking thread pool.
+    #[cfg(feature = "duckdb")]
+    DuckDb(Arc<Mutex<duckdb::Connection>>),
+}
+
+/// Settings applied to every connection a pool opens.
+#[derive(Clone, Default)]
+pub struct ConnectOptions {
+    pub read_only: bool,
+    pub init_sql: Option<String>,
+}
+
+impl Connection {
+    /// Connect to the database described by a sqlx style URL, picking the
+    /// backend from the URL scheme.
+    pub async fn connect(url: &str) -> Result<Self, Box<dyn Error>> {
+        Self::connect_with(url, &ConnectOptions::default()).await
+    }
+
+    pub async fn connect_with(url: &str, options: &ConnectOptions) -> Result<Self, Box<dyn Error>> {
+        let scheme = url.split(':').next().unwrap_or_default();
+        let mut init_sql = Vec::new();
+        match scheme {
+            "sqlite" => {
+                let mut connect_options = SqliteConnectOptions::from_str(url)?;
+                if options.read_only {
+                    connect_options = connect_options.read_only(true);
+                }
+                init_sql.extend(options.init_sql.clone());
+                let pool = pool_options(init_sql).connect_with(connect_options).await?;
+                Ok(Connection::Sqlite(pool))
+            }
+            "postgres" | "postgresql" => {
+                if options.read_only {
+                    init_sql.push("SET default_transaction_read_only = on".to_string());
+                }
+                init_sql.extend(options.init_sql.clone());
+                Ok(Connection::Postgres(
+                    pool_options(init_sql).connect(url).await?,
+                ))
+            }
+            "mysql" | "mariadb" => {
+                let url = url.replacen("mariadb:", "mysql:", 1);
+                if options.read_only {
+                    init_sql.push("SET SESSION TRANSACTION READ ONLY".to_string());
+                }
+                init_sql.extend(options.init_sql.clone());
+                Ok(Connection::MySql(
+                    pool_options(init_sql).connect(&url).await?,
+                ))
+            }
+            #[cfg(feature = "duckdb")]
+            "duckdb" => {
+                let path = url.trim_start_matches("duckdb:").trim_start_matches("//");
+                let connection = match path {
+                    "" | ":memory:" => duckdb::Connection::open_in_memory()?,
+                    path if options.read_only => {
+                        let config =
+                            duckdb::Config::default().access_mode(duckdb::AccessMode::ReadOnly)?;
+                        duckdb::Connection::open_with_flags(path, config)?
+                    }
+                    path => duckdb::Connection::open(path)?,
+                };
+                if let Some(init_sql) = &options.init_sql {
+                    connection.execute_batch(init_sql)?;
+                }
+                Ok(Connection::DuckDb(Arc::new(Mutex::new(connection))))
+            }
+            #[cfg(not(feature = "duckdb"))]
+            "duckdb" => Err("squealer was built without the duckdb feature".into()),
+            _ => Err(format!("Unsupported database URL scheme: {}", scheme).into()),
+        }
+    }
+
+    /// The dialect scripts for this connection are split in.
+    pub fn dialect(&self) -> Dialect {
+        match self {
+            Connection::Sqlite(_) => Dialect::Sqlite,
+            Connection::Postgres(_) => Dialect::Postgres,
+            Connection::MySql(_) => Dialect::MySql,
+            #[cfg(feature = "duckdb")]
+            Connection::DuckDb(_) => Dialect::DuckDb,
+        }
+    }
+
+    /// Run the statements of a script in order on one connection, sending
+    /// what each produces to `on_output` as it goes and stopping at the
+    /// first error. Rows come back a page at a time. Only the last
+    /// statement's rows stay open for more pages, fetched for each request
+    /// received on `requests`; earlier statements keep just their first page.
+    ///
+    /// Closing `requests` stops the script, interrupting a running statement
+    /// with the backend's own mechanism (`sqlite3_interrupt`,
+    /// `pg_cancel_backend`, `KILL QUERY` or a DuckDB interrupt).
+    ///
+    /// The script runs on the transaction's connection when one is given,
+    /// and otherwise on a connection of its own in autocommit.
+    pub async fn run_script(
+        &self,
+        transaction: Option<&Transaction>,
+        statements: &[BoundStatement],
+        mut requests: mpsc::UnboundedReceiver<()>,
+        mut on_output: impl FnMut(usize, Output) + Send,
+    ) -> Result<(), Box<dyn Error>> {
+        match self {
+            Connection::Sqlite(pool) => {
+                let pinned = match transaction {
+                    Some(Transaction(Pinned::Sqlite(pinned))) => Some(pinned),
+                    _ => None,
+                };
+                let mut connection = checkout(pool, pinned).await?;
+                let handle = SqliteInterrupt(connection.lock_handle().await?.as_raw_handle());
+                let result = run_statements::<Sqlite, _, _>(
+                    &mut *connection,
+                    statements,
+                    &mut requests,
+                    || async move { handle.interrupt() },
+                    SqliteQueryResult::rows_affected,
+                    sqlite_cell,
+                    &mut on_output,
+                )
+                .await;
+                // SQLite gives up on a statement's remaining rows as soon as
+                // it is reset, so the connection can always go back, which
+                // keeps an in-memory database alive.
+                connection.finish(true);
+                result?;
+            }
+            Connection::Postgres(pool) => {
+                let pinned = match transaction {
+                    Some(Transaction(Pinned::Postgres(pinned))) => Some(pinned),
+                    _ => None,
+                };
+                let mut connection = checkout(pool, pinned).await?;
+                let pid: i32 = sqlx::query_scalar("SELECT pg_backend_pid()")
+                    .fetch_one(&mut *connection)
+                    .await?;
+                let finished = run_statements::<Postgres, _, _>(
+                    &mut *connection,
+                    statements,
+                    &mut requests,
+                    || async move {
+                        let cancel = sqlx::query("SELECT pg_cancel_backend($1)").bind(pid);
+                        let _ = cancel.execute(pool).await;
+                    },
+                    PgQueryResult::rows_affected,
+                    postgres_cell,
+                    &mut on_output,
+                )
+                .await?;
+                connection.finish(finished);
+            }
+            Connection::MySql(pool) => {
+                let pinned = match transaction {
+                    Some(Transaction(Pinned::MySql(pinned))) => Some(pinned),
+                    _ => None,
+                };
+                let mut connection = checkout(pool, pinned).await?;
+                let id: u64 = sqlx::query_scalar("SELECT CONNECTION_ID()")
+                    .fetch_one(&mut *connection)
+                    .await?;
+                let finished = run_statements::<MySql, _, _>(
+                    &mut *connection,
+                    statements,
+                    &mut requests,
+                    || async move {
+                        let kill = format!("KILL QUERY {}", id);
+                        let _ = sqlx::query(&kill).execute(pool).await;
+                    },
+                    MySqlQueryResult::rows_affected,
+                    mysql_cell,
+                    &mut on_output,
+                )
+                .await?;
+                connection.finish(finished);
+            }
+            #[cfg(feature = "duckdb")]
+            Connection::DuckDb(connection) => {
+                for (index, statement) in statements.iter().enumerate() {
+                    if requests.is_closed() {
+                        break;
+                    }
+                    let handle = connection
+                        .lock()
+                        .unwrap_or_else(PoisonError::into_inner)
+                        .interrupt_handle();
+                    // The rows are read on a blocking thread and handed over
+                    // one at a time, so DuckDB only runs ahead of the pages
+                    // asked for by the size of the channel.
+                    let (sender, mut receiver) = mpsc::channel(PAGE_SIZE);
+                    let columns = Arc::new(OnceLock::new());
+                    tokio::task::spawn_blocking({
+                        let (connection, columns) = (connection.clone(), columns.clone());
+                        let statement = statement.clone();
+                        move || duckdb_stream(&connection, &statement, &columns, sender)
+                    });
+                    stream_pages(
+                        futures::stream::poll_fn(|context| receiver.poll_recv(context)),
+                        &mut requests,
+                        index + 1 == statements.len(),
+                        || async move { handle.interrupt() },
+                        |rows, more| {
+                            let columns = columns.get().cloned().unwrap_or_default();
+                            on_output(index, Output::rows(columns, rows, more));
+                        },
+                    )
+                    .await?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Connection {
+    /// Run one of the app's own queries, such as a catalog lookup, and
+    /// decode all of its rows.
+    pub async fn fetch_all(&self, query: &str) -> Result<Vec<Vec<CellValue>>, Box<dyn Error>> {
+        Ok(match self {
+            Connection::Sqlite(pool) => {
+                decode_rows(&sqlx::query(query).fetch_all(pool).await?, sqlite_cell)
+            }
+            Connection::Postgres(pool) => {
+                decode_rows(&sqlx::query(query).fetch_all(pool).await?, postgres_cell)
+            }
+            Connection::MySql(pool) => {
+                decode_rows(&sqlx::query(query).fetch_all(pool).await?, mysql_cell)
+            }
+            #[cfg(feature = "duckdb")]
+            Connection::DuckDb(connection) => {
+                let (connection, query) = (connection.clone(), query.to_string());
+                tokio::task::spawn_blocking(move || duckdb_fetch_all(&connection, &query, &[]))
+                    .await??
+            }
+        })
+    }
+
+    /// Run a statement with its parameters bound and decode all of its
+    /// rows, on the transaction's connection when one is given.
+    pub async fn fetch_bound(
+        &self,
+        transaction: Option<&Transaction>,
+        statement: &BoundStatement,
+    ) -> Result<Vec<Vec<CellValue>>, Box<dyn Error>> {
+        match self {
+            Connection::Sqlite(pool) => {
+                let pinned = match transaction {
+                    Some(Transaction(Pinned::Sqlite(pinned))) => Some(pinned),
+                    _ => None,
+                };
+                let mut connection = checkout(pool, pinned).await?;
+                let rows =
+                    fetch_statement::<Sqlite>(&mut *connection, statement, sqlite_cell).await;
+                connection.finish(true);
+                rows
+            }
+            Connection::Postgres(pool) => {
+                let pinned = match transaction {
+                    Some(Transaction(Pinned::Postgres(pinned))) => Some(pinned),
+                    _ => None,
+                };
+                let mut connection = checkout(pool, pinned).await?;
+                let rows =
+                    fetch_statement::<Postgres>(&mut *connection, statement, postgres_cell).await;
+                connection.finish(rows.is_ok());
+                rows
+            }
+            Connection::MySql(pool) => {
+                let pinned = match transaction {
+                    Some(Transaction(Pinned::MySql(pinned))) => Some(pinned),
+                    _ => None,
+                };
+                let mut connection = checkout(pool, pinned).await?;
+                let rows = fetch_statement::<MySql>(&mut *connection, statement, mysql_cell).await;
+                connection.finish(rows.is_ok());
+                rows
+            }
+            #[cfg(feature = "duckdb")]
+            Connection::DuckDb(connection) => {
+                let (connection, statement) = (connection.clone(), statement.clone());
+                Ok(tokio::task::spawn_blocking(move || {
+                    duckdb_fetch_all(&connection, &statement.sql, &statement.parameters)
+                })
+                .await??)
+            }
+        }
+    }
+}
+
+fn decode_rows<R: Row>(rows: &[R], decode_cell: fn(&R, usize) -> CellValue) -> Vec<Vec<CellValue>> {
+    rows.iter()
+        .map(|row| (0..row.len()).map(|i| decode_cell(row, i)).collect())
+        .collect()
+}
+
+/// A connection taken out of the pool with a transaction open on it, so the
+/// scripts run in it can be committed or rolled back together. Dropping the
+/// last clone without ending it closes the connection, which rolls it back.
+#[derive(Clone, Debug)]
+pub struct Transaction(Pinned);
+
+#[derive(Clone, Debug)]
+enum Pinned {
+    Sqlite(Shared<Sqlite>),
+    Postgres(Shared<Postgres>),
+    MySql(Shared<MySql>),
+    /// DuckDB only has the one connection, so the transaction is open on it.
+    #[cfg(feature = "duckdb")]
+    DuckDb(Arc<Mutex<duckdb::Connection>>),
+}
+
+type Shared<DB> = Arc<tokio::sync::Mutex<StreamingConnection<DB>>>;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TransactionEnd {
+    Commit,
+    Rollback,
+}
+
+impl Connection {
+    /// Take a connection out of the pool and open a transaction on it.
+    pub async fn begin(&self) -> Result<Transaction, Box<dyn Error>> {
+        Ok(Transaction(match self {
+            Connection::Sqlite(pool) => Pinned::Sqlite(pin(pool).await?),
+            Connection::Postgres(pool) => Pinned::Postgres(pin(pool).await?),
+            Connection::MySql(pool) => Pinned::MySql(pin(pool).await?),
+            #[cfg(feature = "duckdb")]
+            Connection::DuckDb(connection) => {
+                let connection = connection.clone();
+                tokio::task::spawn_blocking({
+                    let connection = connection.clone();
+                    move || {
+                        let connection = connection.lock().unwrap_or_else(PoisonError::into_inner);
+                        connection.execute_batch("BEGIN TRANSACTION")
+                    }
+                })
+                .await??;
+                Pinned::DuckDb(connection)
+            }
+        }))
+    }
+}
+
+impl Transaction {
+    /// Commit or roll back the transaction, returning its connection to the
+    /// pool. Waits for any script still running in it to stop first.
+    pub async fn end(self, end: TransactionEnd) -> Result<(), Box<dyn Error>> {
+        let statement = match end {
+            TransactionEnd::Commit => "COMMIT",
+            TransactionEnd::Rollback => "ROLLBACK",
+        };
+        match self.0 {
+            Pinned::Sqlite(pinned) => unpin(pinned, statement).await?,
+            Pinned::Postgres(pinned) => unpin(pinned, statement).await?,
+            Pinned::MySql(pinned) => unpin(pinned, statement).await?,
+            #[cfg(feature = "duckdb")]
+            Pinned::DuckDb(connection) => {
+                tokio::task::spawn_blocking(move || {
+                    let connection = connection.lock().unwrap_or_else(PoisonError::into_inner);
+                    connection.execute_batch(statement)
+                })
+                .await??
+            }
+        }
+        Ok(())
+    }
+}
+
+async fn pin<DB>(pool: &Pool<DB>) -> Result<Shared<DB>, sqlx::Error>
+where
+    DB: sqlx::Database,
+    for<'c> &'c mut DB::Connection: Executor<'c, Database = DB>,
+{
+    let mut connection = StreamingConnection(Some(pool.acquire().await?));
+    (&mut **connection).execute("BEGIN").await?;
+    Ok(Arc::new(tokio::sync::Mutex::new(connection)))
+}
+
+async fn unpin<DB>(pinned: Shared<DB>, statement: &str) -> Result<(), sqlx::Error>
+where
+    DB: sqlx::Database,
+    for<'c> &'c mut DB::Connection: Executor<'c, Database = DB>,
+{
+    let mut connection = pinned.lock().await;
+    (&mut ***connection).execute(statement).await?;
+    drop(connection);
+    // A script that was stopped may still hold a clone for a moment, in
+    // which case the connection is closed rather than returned.
+    if let Some(connection) = Arc::into_inner(pinned) {
+        connection.into_inner().release();
+    }
+    Ok(())
+}
+
+/// The connection a script runs on: one of its own from the pool, or the
+/// one pinned by a transaction.
+enum Checkout<'t, DB: sqlx::Database> {
+    Pooled(StreamingConnection<DB>),
+    Pinned(tokio::sync::MutexGuard<'t, StreamingConnection<DB>>),
+}
+
+async fn checkout<'t, DB: sqlx::Database>(
+    pool: &Pool<DB>,
+    pinned: Option<&'t Shared<DB>>,
+) -> Result<Checkout<'t, DB>, sqlx::Error> {
+    Ok(match pinned {
+        Some(pinned) => Checkout::Pinned(pinned.lock().await),
+        None => Checkout::Pooled(StreamingConnection(Some(pool.acquire().await?))),
+    })
+}
+
+impl<DB: sqlx::Database> Checkout<'_, DB> {
+    /// Give back the connection, returning one of its own to the pool if the
+    /// script read all its rows.
+    fn finish(self, finished: bool) {
+        if let (Checkout::Pooled(connection), true) = (self, finished) {
+            connection.release();
+        }
+    }
+}
+
+impl<DB: sqlx::Database> Deref for Checkout<'_, DB> {
+    type Target = DB::Connection;
+
+    fn deref(&self) -> &Self::Target {
+        match self {
+            Checkout::Pooled(connection) => connection,
+            Checkout::Pinned(connection) => connection,
+        }
+    }
+}
+
+impl<DB: sqlx::Database> DerefMut for Checkout<'_, DB> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        match self {
+            Checkout::Pooled(connection) => connection,
+            Checkout::Pinned(connection) => connection,
+        }
+    }
+}
+
+/// The number of rows fetched each time more are asked for.
+pub const PAGE_SIZE: usize = 200;
+
+/// What a statement of a script produced.
+#[derive(Debug)]
+pub enum Output {
+    /// A page of the rows returned by a statement.
+    Rows {
+        results: Results,
+        /// Whether the statement may have more rows after this page.
+        more: bool,
+    },
+    /// A statement that returns no rows finished.
+    Done { rows_affected: u64 },
+}
+
+impl Output {
+    fn rows(headers: Vec<String>, data: Vec<Vec<CellValue>>, more: bool) -> Self {
+        Output::Rows {
+            results: Results::new(headers, data),
+            more,
+        }
+    }
+}
+
+/// Run the statements of a script on a sqlx connection, see
+/// `Connection::run_script`. Returns whether the last statement's rows ran
+/// out, leaving the connection ready for another query.
+async fn run_statements<DB, I, F>(
+    connection: &mut DB::Connection,
+    statements: &[BoundStatement],
+    requests: &mut mpsc::UnboundedReceiver<()>,
+    interrupt: I,
+    rows_affected: fn(&DB::QueryResult) -> u64,
+    decode_cell: fn(&DB::Row, usize) -> CellValue,
+    on_output: &mut impl FnMut(usize, Output),
+) -> Result<bool, Box<dyn Error>>
+where
+    DB: BindParameters,
+    for<'c> &'c mut DB::Connection: Executor<'c, Database = DB>,
+    for<'q> <DB as HasArguments<'q>>::Arguments: IntoArguments<'q, DB>,
+    I: Fn() -> F,
+    F: Future<Output = ()>,
+{
+    let mut finished = true;
+    for (index, statement) in statements.iter().enumerate() {
+        if requests.is_closed() {
+            break;
+        }
+        // Preparing the statement first tells whether it returns rows, and
+        // gives the column names even when it returns none. One that cannot
+        // be prepared is sent as it is, and returns none.
+        let (columns, arguments) = match prepare::<DB>(connection, statement).await? {
+            Some((columns, arguments)) => (columns, Some(arguments)),
+            None => (Vec::new(), None),
+        };
+        if columns.is_empty() {
+            let result = match arguments {
+                Some(arguments) => {
+                    (&mut *connection).execute(sqlx::query_with(&statement.sql, arguments))
+                }
+                None => (&mut *connection).execute(statement.sql.as_str()),
+            };
+            // Page requests mean nothing to a statement without rows, so
+            // only the requests closing interrupts it.
+            let closed = async { while requests.recv().await.is_some() {} };
+            let result = tokio::select! {
+                result = result => result?,
+                () = closed => {
+                    interrupt().await;
+                    return Ok(false);
+                }
+            };
+            let rows_affected = rows_affected(&result);
+            on_output(index, Output::Done { rows_affected });
+            continue;
+        }
+        let arguments = arguments.expect("a prepared statement returns the rows");
+        let rows = sqlx::query_with(&statement.sql, arguments).fetch(&mut *connection);
+        finished = stream_pages(
+            rows,
+            requests,
+            index + 1 == statements.len(),
+            &interrupt,
+            |rows, more| {
+                let data = decode_rows(&rows, decode_cell);
+                on_output(index, Output::rows(columns.clone(), data, more));
+            },
+        )
+        .await?;
+    }
+    Ok(finished)
+}
+
+/// Prepare a statement, returning the names of the columns it returns and
+/// its parameters' values as arguments of the types the backend works out
+/// for them, or `None` for a statement without parameters that the backend
+/// will only run unprepared.
+async fn prepare<'q, DB>(
+    connection: &mut DB::Connection,
+    statement: &'q BoundStatement,
+) -> Result<Option<(Vec<String>, <DB as HasArguments<'q>>::Arguments)>, Box<dyn Error>>
+where
+    DB: BindParameters,
+    for<'c> &'c mut DB::Connection: Executor<'c, Database = DB>,
+{
+    let prepared = match (&mut *connection).prepare(&statement.sql).await {
+        Ok(prepared) => prepared,
+        Err(error) if statement.parameters.is_empty() && DB::unpreparable(&error) => {
+            return Ok(None)
+        }
+        Err(error) => return Err(error.into()),
+    };
+    let columns = prepared
+        .columns()
+        .iter()
+        .map(|column| column.name().to_string())
+        .collect();
+    let types = match prepared.parameters() {
+        Some(Either::Left(types)) => Some(types),
+        _ => None,
+    };
+    let arguments = DB::arguments(&statement.parameters, types)?;
+    Ok(Some((columns, arguments)))
+}
+
+/// Run a statement on a sqlx connection and decode all of its rows, see
+/// `Connection::fetch_bound`.
+async fn fetch_statement<DB>(
+    connection: &mut DB::Connection,
+    statement: &BoundStatement,
+    decode_cell: fn(&DB::Row, usize) -> CellValue,
+) -> Result<Vec<Vec<CellValue>>, Box<dyn Error>>
+where
+    DB: BindParameters,
+    for<'c> &'c mut DB::Connection: Executor<'c, Database = DB>,
+    for<'q> <DB as HasArguments<'q>>::Arguments: IntoArguments<'q, DB>,
+{
+    let Some((_, arguments)) = prepare::<DB>(connection, statement).await? else {
+        (&mut *connection).execute(statement.sql.as_str()).await?;
+        return Ok(Vec::new());
+    };
+    let rows = sqlx::query_with(&statement.sql, arguments)
+        .fetch_all(&mut *connection)
+        .await?;
+    Ok(decode_rows(&rows, decode_cell))
+}
+
+/// Pull pages of up to `PAGE_SIZE` rows from `rows`. The first page is
+/// fetched straight away; when `keep_open` is set each further page waits
+/// for a request. If the requests channel is closed while a page is being
+/// fetched, `interrupt` is run and the page is finished off with whatever
+/// the query does once interrupted. Returns whether the rows ran out.
+async fn stream_pages<R, E, I, F>(
+    rows: impl Stream<Item = Result<R, E>>,
+    requests: &mut mpsc::UnboundedReceiver<()>,
+    keep_open: bool,
+    interrupt: I,
+    mut on_page: impl FnMut(Vec<R>, bool),
+) -> Result<bool, E>
+where
+    I: FnOnce() -> F,
+    F: Future<Output = ()>,
+{
+    tokio::pin!(rows);
+    let mut interrupt = Some(interrupt);
+    loop {
+        let mut page = Vec::with_capacity(PAGE_SIZE);
+        let more = loop {
+            if page.len() == PAGE_SIZE {
+                break true;
+            }
+            tokio::select! {
+                row = rows.next() => match row {
+                    Some(row) => page.push(row?),
+                    None => break false,
+                },
+                None = requests.recv(), if interrupt.is_some() => {
+                    if let Some(interrupt) = interrupt.take() {
+                        interrupt().await;
+                    }
+                }
+            }
+        };
+        on_page(page, more);
+        if !more {
+            return Ok(true);
+        }
+        if !keep_open || requests.recv().await.is_none() {
+            return Ok(false);
+        }
+    }
+}
+
+/// A pooled connection used to stream a query's rows, or pinned by a
+/// transaction. Returning it to the pool part way through makes the pool
+/// read every remaining row before reusing it, so unless it is released once
+/// the rows run out it is closed when dropped instead. Closing it also rolls
+/// back a transaction left open on it.
+#[derive(Debug)]
+struct StreamingConnection<DB: sqlx::Database>(Option<PoolConnection<DB>>);
+
+impl<DB: sqlx::Database> StreamingConnection<DB> {
+    fn release(mut self) {
+        self.0.take();
+    }
+}
+
+impl<DB: sqlx::Database> Deref for StreamingConnection<DB> {
+    type Target = PoolConnection<DB>;
+
+    fn deref(&self) -> &Self::Target {
+        self.0
+            .as_ref()
+            .expect("connection is only taken on release")
+    }
+}
+
+impl<DB: sqlx::Database> DerefMut for StreamingConnection<DB> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.0
+            .as_mut()
+            .expect("connection is only taken on release")
+    }
+}
+
+impl<DB: sqlx::Database> Drop for StreamingConnection<DB> {
+    fn drop(&mut self) {
+        if let Some(connection) = self.0.take() {
+            drop(connection.detach());
+        }
+    }
+}
+
+/// The raw handle of a pooled SQLite connection, used to interrupt the
+/// statement running on it.
+#[derive(Clone, Copy)]
+struct SqliteInterrupt(NonNull<libsqlite3_sys::sqlite3>);
+
+// SAFETY: `sqlite3_interrupt` may be called from any thread, and the handle is
+// only used while the pooled connection it came from is checked out.
+unsafe impl Send for SqliteInterrupt {}
+unsafe impl Sync for SqliteInterrupt {}
+
+impl SqliteInterrupt {
+    fn interrupt(self) {
+        // SAFETY: see above, the connection is still open.
+        unsafe { libsqlite3_sys::sqlite3_interrupt(self.0.as_ptr()) }
+    }
+}
+
+/// Pool options that run `init_sql` on every new connection.
+fn pool_options<DB: sqlx::Database>(init_sql: Vec<String>) -> PoolOptions<DB>
+where
+    for<'c> &'c mut DB::Connection: Executor<'c, Database = DB>,
+{
+    PoolOptions::new().after_connect(move |connection: &mut DB::Connection, _| {
+        let init_sql = init_sql.clone();
+        Box::pin(async move {
+            for statement in &init_sql {
+                connection.execute(statement.as_str()).await?;
+            }
+            Ok(())
+        })
+    })
+}
+
+/// Check that a connection URL is well formed for its backend without
+/// connecting to it, returning the URL unchanged if it is.
+pub fn validate_url(url: &str) -> Result<String, String> {
+    let scheme = match url.split_once(':') {
+        Some((scheme, _)) => scheme,
+        None => return Err(format!("`{}` is missing a scheme such as sqlite://", url)),
+    };
+    let options = match scheme {
+        "sqlite" => SqliteConnectOptions::from_str(url).map(drop),
+        "postgres" | "postgresql" => PgConnectOptions::from_str(url).map(drop),
+        "mysql" | "mariadb" => {
+            MySqlConnectOptions::from_str(&url.replacen("mariadb:", "mysql:", 1)).map(drop)
+        }
+        "duckdb" => Ok(()),
+        _ => return Err(format!("unsupported database URL scheme `{}`", scheme)),
+    };
+    options
+        .map(|_| url.to_string())
+        .map_err(|error| error.to_string())
+}
+
+/// A URL with any password removed, for showing on screen.
+pub fn redact_url(url: &str) -> String {
+    let Some((scheme, rest)) = url.split_once("://") else {
+        return url.to_string();
+    };
+    match rest.split_once('@') {
+        Some((user_info, host)) => {
+            let user = user_info.split(':').next().unwrap_or_default();
+            format!("{}://{}@{}", scheme, user, host)
+        }
+        None => url.to_string(),
+    }
+}
+
+/// Decode a cell as `T` without sqlx's type compatibility check, for when
+/// the type has already been picked from the column's type name.
+fn get<'r, R, T>(row: &'r R, index: usize) -> Option<T>
+where
+    R: Row,
+    T: Decode<'r, R::Database>,
+    usize: ColumnIndex<R>,
+{
+    row.try_get_unchecked(index).ok()
+}
+
+fn is_null<R: Row>(row: &R, index: usize) -> bool
+where
+    usize: ColumnIndex<R>,
+{
+    row.try_get_raw(index).map_or(true, |value| value.is_null())
+}
+
+/// SQLite values are typed by their storage class, with the declared column
+/// type used to pick out booleans, dates and times stored as integers or
+/// text.
+fn sqlite_cell(row: &SqliteRow, index: usize) -> CellValue {
+    let Ok(value) = row.try_get_raw(index) else {
+        return CellValue::Null;
+    };
+    if value.is_null() {
+        return CellValue::Null;
+    }
+    let storage = value.type_info();
+    let declared = row.column(index).type_info().name();
+    let decoded = match (declared, storage.name()) {
+        ("BOOLEAN", "INTEGER") => get(row, index).map(CellValue::Bool),
+        ("DATE", "TEXT") => get(row, index).map(CellValue::Date),
+        ("TIME", "TEXT") => get(row, index).map(CellValue::Time),
+        ("DATETIME", "TEXT" | "INTEGER") => get(row, index).map(CellValue::DateTime),
+        (_, "INTEGER") => get(row, index).map(CellValue::Integer),
+        (_, "REAL") => get(row, index).map(CellValue::Real),
+        (_, "BLOB") => get(row, index).map(CellValue::Blob),
+        _ => None,
+    };
+    decoded
+        .or_else(|| get(row, index).map(CellValue::Text))
+        .or_else(|| get(row, index).map(CellValue::Blob))
+        .unwrap_or(CellValue::Null)
+}
+
+fn postgres_cell(row: &PgRow, index: usize) -> CellValue {
+    if is_null(row, index) {
+        return CellValue::Null;
+    }
+    let type_info = row.column(index).type_info();
+    let decoded = match type_info.name() {
+        "BOOL" => get(row, index).map(CellValue::Bool),
+        "INT2" => get::<_, i16>(row, index).map(|v| CellValue::Integer(v.into())),
+        "INT4" => get::<_, i32>(row, index).map(|v| CellValue::Integer(v.into())),
+        "INT8" => get(row, index).map(CellValue::Integer),
+        "FLOAT4" => get::<_, f32>(row, index).map(|v| CellValue::Real(v.into())),
+        "FLOAT8" => get(row, index).map(CellValue::Real),
+        "NUMERIC" => get(row, index).map(CellValue::Decimal),
+        "TEXT" | "VARCHAR" | "BPCHAR" | "NAME" | "CITEXT" | "UNKNOWN" => {
+            get(row, index).map(CellValue::Text)
+        }
+        "BYTEA" => get(row, index).map(CellValue::Blob),
+        "DATE" => get(row, index).map(CellValue::Date),
+        "TIME" => get(row, index).map(CellValue::Time),
+        "TIMESTAMP" => get(row, index).map(CellValue::DateTime),
+        "TIMESTAMPTZ" => get(row, index).map(CellValue::Timestamp),
+        "JSON" | "JSONB" => get(row, index).map(CellValue::Json),
+        "UUID" => get(row, index).map(CellValue::Uuid),
+        // Enums are sent as their label, so they can be read as text.
+        _ if matches!(type_info.kind(), PgTypeKind::Enum(_)) => {
+            get(row, index).map(CellValue::Text)
+        }
+        _ => None,
+    };
+    decoded.unwrap_or_else(|| CellValue::Other(format!("<{}>", type_info.name())))
+}
+
+/// MySQL only decodes text columns in a handful of collations with its type
+/// checks, so anything without a variant of its own (ENUM, SET, text in any
+/// charset) is read from its raw form.
+fn mysql_cell(row: &MySqlRow, index: usize) -> CellValue {
+    if is_null(row, index) {
+        return CellValue::Null;
+    }
+    let decoded = match row.column(index).type_info().name() {
+        "DATETIME" => get(row, index).map(CellValue::DateTime),
+        "TIMESTAMP" => get(row, index).map(CellValue::Timestamp),
+        "DATE" => get(row, index).map(CellValue::Date),
+        "TIME" => get(row, index).map(CellValue::Time),
+        "BOOLEAN" => get(row, index).map(CellValue::Bool),
+        "TINYINT" => get::<_, i8>(row, index).map(|v| CellValue::Integer(v.into())),
+        "SMALLINT" => get::<_, i16>(row, index).map(|v| CellValue::Integer(v.into())),
+        "INT" | "MEDIUMINT" => get::<_, i32>(row, index).map(|v| CellValue::Integer(v.into())),
+        "BIGINT" => get(row, index).map(CellValue::Integer),
+        "TINYINT UNSIGNED" | "SMALLINT UNSIGNED" | "INT UNSIGNED" | "MEDIUMINT UNSIGNED"
+        | "BIGINT UNSIGNED" | "YEAR" => get(row, index).map(CellValue::Unsigned),
+        "FLOAT" => get::<_, f32>(row, index).map(|v| CellValue::Real(v.into())),
+        "DOUBLE" => get(row, index).map(CellValue::Real),
+        "DECIMAL" => get(row, index).map(CellValue::Decimal),
+        "JSON" => get(row, index).map(CellValue::Json),
+        "BINARY" | "VARBINARY" | "TINYBLOB" | "BLOB" | "MEDIUMBLOB" | "LONGBLOB" => {
+            get(row, index).map(CellValue::Blob)
+        }
+        _ => None,
+    };
+    decoded
+        .or_else(|| get(row, index).map(CellValue::Text))
+        .or_else(|| get(row, index).map(CellValue::Blob))
+        .unwrap_or(CellValue::Null)
+}
+
+/// A statement of a script with its placeholders rewritten into the form
+/// the backend binds, and the values bound to them in order, see
+/// `script::bind_placeholders`.
+#[derive(Clone, Debug)]
+pub struct BoundStatement {
+    pub sql: String,
+    pub parameters: Vec<CellValue>,
+}
+
+/// Turning the values given for a statement's parameters into the arguments
+/// it runs with, given the types the backend worked out for the parameters
+/// when it prepared the statement, if it says.
+trait BindParameters: sqlx::Database {
+    fn arguments<'q>(
+        values: &[CellValue],
+        types: Option<&[Self::TypeInfo]>,
+    ) -> Result<<Self as HasArguments<'q>>::Arguments, String>;
+
+    /// Whether the backend refused to prepare a statement that it runs
+    /// when it is sent unprepared.
+    fn unpreparable(_error: &sqlx::Error) -> bool {
+        false
+    }
+}
+
+/// SQLite stores whatever it is given, so values are bound as they are.
+impl BindParameters for Sqlite {
+    fn arguments<'q>(
+        values: &[CellValue],
+        _types: Option<&[SqliteTypeInfo]>,
+    ) -> Result<SqliteArguments<'q>, String> {
+        let mut arguments = SqliteArguments::default();
+        for value in values {
+            match value {
+                CellValue::Null => arguments.add(None::<String>),
+                CellValue::Bool(value) => arguments.add(*value),
+                CellValue::Integer(value) => arguments.add(*value),
+                CellValue::Real(value) => arguments.add(*value),
+                CellValue::Blob(value) => arguments.add(value.clone()),
+                value => arguments.add(value.to_string()),
+            }
+        }
+        Ok(arguments)
+    }
+}
+
+/// MySQL converts between strings and numbers itself, so values are bound
+/// as they are.
+impl BindParameters for MySql {
+    fn arguments<'q>(
+        values: &[CellValue],
+        _types: Option<&[MySqlTypeInfo]>,
+    ) -> Result<<Self as HasArguments<'q>>::Arguments, String> {
+        let mut arguments = MySqlArguments::default();
+        for value in values {
+            match value {
+                CellValue::Null => arguments.add(None::<String>),
+                CellValue::Bool(value) => arguments.add(*value),
+                CellValue::Integer(value) => arguments.add(*value),
+                CellValue::Unsigned(value) => arguments.add(*value),
+                CellValue::Real(value) => arguments.add(*value),
+                CellValue::Blob(value) => arguments.add(value.clone()),
+                value => arguments.add(value.to_string()),
+            }
+        }
+        Ok(arguments)
+    }
+
+    /// MySQL has no prepared form of statements such as `CREATE PROCEDURE`,
+    /// `USE` and `LOCK TABLES`, and says so with error 1295.
+    fn unpreparable(error: &sqlx::Error) -> bool {
+        let sqlx::Error::Database(error) = error else {
+            return false;
+        };
+        error
+            .try_downcast_ref::<MySqlDatabaseError>()
+            .is_some_and(|error| error.number() == 1295)
+    }
+}
+
+/// Postgres reads each value in the binary form of the type it worked out
+/// for the parameter, so values are converted to that type first.
+impl BindParameters for Postgres {
+    fn arguments<'q>(
+        values: &[CellValue],
+        types: Option<&[PgTypeInfo]>,
+    ) -> Result<<Self as HasArguments<'q>>::Arguments, String> {
+        let mut arguments = PgArguments::default();
+        for (index, value) in values.iter().enumerate() {
+            let type_name = types
+                .and_then(|types| types.get(index))
+                .map_or("TEXT", |type_info| type_info.name());
+            add_postgres_argument(&mut arguments, value, type_name)
+                .ok_or_else(|| format!("{} is not a valid {}", value, type_name.to_lowercase()))?;
+        }
+        Ok(arguments)
+    }
+}
+
+/// Add a value as the Postgres type named, or `None` if it is not one.
+/// Types without a conversion of their own are sent as text, which is also
+/// how enums and JSON are sent.
+fn add_postgres_argument(
+    arguments: &mut PgArguments,
+    value: &CellValue,
+    type_name: &str,
+) -> Option<()> {
+    fn parsed<T: FromStr>(text: &str) -> Option<T> {
+        text.parse().ok()
+    }
+    fn convert<T>(text: Option<&str>, parse: impl FnOnce(&str) -> Option<T>) -> Option<Option<T>> {
+        match text {
+            Some(text) => parse(text).map(Some),
+            None => Some(None),
+        }
+    }
+    let text = (!value.is_null()).then(|| value.to_string());
+    let text = text.as_deref();
+    match type_name {
+        "BOOL" => arguments.add(convert(text, parsed::<bool>)?),
+        "INT2" => arguments.add(convert(text, parsed::<i16>)?),
+        "INT4" => arguments.add(convert(text, parsed::<i32>)?),
+        "INT8" => arguments.add(convert(text, parsed::<i64>)?),
+        "FLOAT4" => arguments.add(convert(text, parsed::<f32>)?),
+        "FLOAT8" => arguments.add(convert(text, parsed::<f64>)?),
+        "NUMERIC" => arguments.add(convert(text, parsed::<BigDecimal>)?),
+        "UUID" => arguments.add(convert(text, parsed::<Uuid>)?),
+        "DATE" => arguments.add(convert(text, parsed::<NaiveDate>)?),
+        "TIME" => arguments.add(convert(text, parsed::<NaiveTime>)?),
+        "TIMESTAMP" => arguments.add(convert(text, parse_datetime)?),
+        "TIMESTAMPTZ" => arguments.add(convert(text, |text| {
+            parsed::<DateTime<Utc>>(text).or_else(|| parse_datetime(text).map(|at| at.and_utc()))
+        })?),
+        "JSONB" => arguments.add(convert(text, parsed::<JsonValue>)?),
+        _ => arguments.add(text.map(str::to_string)),
+    }
+    Some(())
+}
+
+/// A date and time written with a space or a `T` between them, or just a
+/// date for midnight.
+fn parse_datetime(text: &str) -> Option<NaiveDateTime> {
+    NaiveDateTime::parse_from_str(text, "%Y-%m-%d %H:%M:%S%.f")
+        .or_else(|_| text.parse())
+        .ok()
+        .or_else(|| Some(text.parse::<NaiveDate>().ok()?.and_time(NaiveTime::MIN)))
+}
+
+#[cfg(feature = "duckdb")]
+fn duckdb_fetch_all(
+    connection: &Mutex<duckdb::Connection>,
+    query: &str,
+    parameters: &[CellValue],
+) -> duckdb::Result<Vec<Vec<CellValue>>> {
+    let connection = connection.lock().unwrap_or_else(PoisonError::into_inner);
+    let mut statement = connection.prepare(query)?;
+    let mut rows = statement.query(duckdb::params_from_iter(
+        parameters.iter().map(duckdb_value),
+    ))?;
+    let count = rows
+        .as_ref()
+        .map(|statement| statement.column_count())
+        .unwrap_or_default();
+    let mut data = Vec::new();
+    while let Some(row) = rows.next()? {
+        data.push(duckdb_row(row, count));
+    }
+    Ok(data)
+}
+
+#[cfg(feature = "duckdb")]
+fn duckdb_row(row: &duckdb::Row, count: usize) -> Vec<CellValue> {
+    (0..count)
+        .map(|i| {
+            row.get::<_, duckdb::types::Value>(i)
+                .map(duckdb_cell)
+                .unwrap_or(CellValue::Null)
+        })
+        .collect()
+}
+
+/// Run a statement on the blocking thread pool, setting `columns` once it
+/// has run and then sending its rows until they run out or `sender` is
+/// closed.
+#[cfg(feature = "duckdb")]
+fn duckdb_stream(
+    connection: &Mutex<duckdb::Connection>,
+    statement: &BoundStatement,
+    columns: &OnceLock<Vec<String>>,
+    sender: mpsc::Sender<duckdb::Result<Vec<CellValue>>>,
+) {
+    let connection = connection.lock().unwrap_or_else(PoisonError::into_inner);
+    let parameters = statement.parameters.iter().map(duckdb_value);
+    let rows = connection
+        .prepare(&statement.sql)
+        .and_then(|mut statement| {
+            let mut rows = statement.query(duckdb::params_from_iter(parameters))?;
+            let names = rows
+                .as_ref()
+                .map(|statement| statement.column_names())
+                .unwrap_or_default();
+            let count = names.len();
+            let _ = columns.set(names);
+            while let Some(row) = rows.next()? {
+                if sender.blocking_send(Ok(duckdb_row(row, count))).is_err() {
+                    break;
+                }
+            }
+            Ok(())
+        });
+    if let Err(error) = rows {
+        let _ = sender.blocking_send(Err(error));
+    }
+}
+
+#[cfg(feature = "duckdb")]
+fn duckdb_value(value: &CellValue) -> duckdb::types::Value {
+    use duckdb::types::Value;
+
+    match value {
+        CellValue::Null => Value::Null,
+        CellValue::Bool(value) => Value::Boolean(*value),
+        CellValue::Integer(value) => Value::BigInt(*value),
+        CellValue::Unsigned(value) => Value::UBigInt(*value),
+        CellValue::Real(value) => Value::Double(*value),
+        CellValue::Blob(value) => Value::Blob(value.clone()),
+        value => Value::Text(value.to_string()),
+    }
+}
+
+#[cfg(feature = "duckdb")]
+fn duckdb_cell(value: duckdb::types::Value) -> CellValue {
+    use duckdb::types::Value;
+
+    match value {
+        Value::Null => CellValue::Null,
+        Value::Boolean(v) => CellValue::Bool(v),
+        Value::TinyInt(v) => CellValue::Integer(v.into()),
+        Value::SmallInt(v) => CellValue::Integer(v.into()),
+        Value::Int(v) => CellValue::Integer(v.into()),
+        Value::BigInt(v) => CellValue::Integer(v),
+        Value::UTinyInt(v) => CellValue::Unsigned(v.into()),
+        Value::USmallInt(v) => CellValue::Unsigned(v.into()),
+        Value::UInt(v) => CellValue::Unsigned(v.into()),
+        Value::UBigInt(v) => CellValue::Unsigned(v),
+        Value::HugeInt(v) => BigDecimal::from_str(&v.to_string())
+            .map(CellValue::Decimal)
+            .unwrap_or(CellValue::Null),
+        Value::UHugeInt(v) => BigDecimal::from_str(&v.to_string())
+            .map(CellValue::Decimal)
+            .unwrap_or(CellValue::Null),
+        Value::Float(v) => CellValue::Real(v.into()),
+        Value::Double(v) => CellValue::Real(v),
+        Value::Decimal(v) => BigDecimal::from_str(&v.to_string())
+            .map(CellValue::Decimal)
+            .unwrap_or_else(|_| CellValue::Other(v.to_string())),
+        Value::Text(v) | Value::Enum(v) => CellValue::Text(v),
+        Value::Blob(v) => CellValue::Blob(v),
+        Value::Timestamp(unit, v) => DateTime::from_timestamp_micros(unit.to_micros(v))
+            .map(|v| CellValue::DateTime(v.naive_utc()))
+            .unwrap_or(CellValue::Null),
+        // Days from 1970-01-01, which is day 719163 of the common era.
+        Value::Date32(days) => NaiveDate::from_num_days_from_ce_opt(days + 719_163)
+            .map(CellValue::Date)
+            .unwrap_or(CellValue::Null),
+        Value::Time64(unit, v) => {
+            let micros = unit.to_micros(v);
+            NaiveTime::from_num_seconds_from_midnight_opt(
+                (micros / 1_000_000) as u32,
+                (micros % 1_000_000 * 1_000) as u32,
+            )
+            .map(CellValue::Time)
+            .unwrap_or(CellValue::Null)
+        }
+        Value::List(values) | Value::Array(values) => CellValue::Other(format!(
+            "[{}]",
+            values
+                .into_iter()
+                .map(|value| duckdb_cell(value).to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        )),
+        other => CellValue::Other(format!("{:?}", other)),
+    }
+}
 
 pub async fn setup_test_database(pool: &sqlx::Pool<Sqlite>) {
     let init_query = "
@@ -16,36 +1188,201 @@ pub async fn setup_test_database(pool: &sqlx::Pool<Sqlite>) {
     let _ = sqlx::query(init_query).execute(pool).await;
 }
 
-pub async fn execute_query(app: &mut App<'static>) -> Result<(), Box<dyn Error>> {
-    let query = app.query_input.lines().join(" ");
-    // if let Some(ref pool) = app.pool {
-    match &app.pool {
-        Some(pool) => {
-            app.results = Some(sqlx::query(&query).fetch_all(pool).await?);
-            Ok(())
+/// Open the connection asked for on the command line or in the config.
+pub async fn initial_database_conection(config: Config) -> Result<Connection, Box<dyn Error>> {
+    match config.starting_db {
+        StartingDb::File(file) => connect_to_database_file(&file).await,
+        StartingDb::InMemory => {
+            let pool = sqlx::SqlitePool::connect("sqlite::memory:").await?;
+            setup_test_database(&pool).await;
+            Ok(Connection::Sqlite(pool))
         }
-        //None => Err("No database connection".into()),
-        None => Ok(()),
+        StartingDb::Url(url) => Connection::connect(&url).await,
+        StartingDb::Profile(name) => match config.profiles.get(&name) {
+            Some(profile) => connect_to_profile(profile).await,
+            None => Err(format!("there is no profile named {}", name).into()),
+        },
+        StartingDb::DuckDb(path) => Connection::connect(&format!("duckdb://{}", path)).await,
+        StartingDb::None => Err("no database was given".into()),
     }
 }
 
-pub async fn initial_database_conection(config: Config) -> Option<sqlx::Pool<Sqlite>> {
-    match config.starting_db {
-        StartingDb::File(file) => connect_to_database_file(&file).await,
-        StartingDb::InMemory => match sqlx::SqlitePool::connect("sqlite::memory:").await {
-            Ok(pool) => {
-                setup_test_database(&pool).await;
-                Some(pool)
+pub async fn connect_to_database_file(file: &str) -> Result<Connection, Box<dyn Error>> {
+    Connection::connect(&file_url(file)).await
+}
+
+pub async fn connect_to_profile(profile: &Profile) -> Result<Connection, Box<dyn Error>> {
+    let options = ConnectOptions {
+        read_only: profile.read_only,
+        init_sql: profile.init_sql.clone(),
+    };
+    Connection::connect_with(&profile.url()?, &options).await
+}
+
+/// The URL for a database file, using DuckDB for `.duckdb` and `.ddb` files
+/// and SQLite for everything else.
+pub fn file_url(file: &str) -> String {
+    let scheme = match std::path::Path::new(file).extension() {
+        Some(extension) if extension == "duckdb" || extension == "ddb" => "duckdb",
+        _ => "sqlite",
+    };
+    format!("{}://{}", scheme, file)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::script::split_statements;
+
+    /// Run a script and return the rows its statements gave, in order.
+    async fn run(connection: &Connection, script: &str) -> Vec<Vec<CellValue>> {
+        let statements = split_statements(script, connection.dialect())
+            .into_iter()
+            .map(|sql| BoundStatement {
+                sql,
+                parameters: Vec::new(),
+            })
+            .collect::<Vec<_>>();
+        let (_requests, received) = mpsc::unbounded_channel();
+        let mut rows = Vec::new();
+        connection
+            .run_script(None, &statements, received, |_, output| {
+                if let Output::Rows {
+                    results: Results::Some { data, .. },
+                    ..
+                } = output
+                {
+                    rows.extend(data);
+                }
+            })
+            .await
+            .expect("the script runs");
+        rows
+    }
+
+    /// A connection to a server given by an environment variable, for tests
+    /// that are skipped without one.
+    async fn server(variable: &str) -> Option<Connection> {
+        let url = std::env::var(variable).ok()?;
+        Some(
+            Connection::connect(&url)
+                .await
+                .expect("the test server connects"),
+        )
+    }
+
+    #[tokio::test]
+    async fn runs_a_sqlite_trigger_script() {
+        let connection = Connection::connect("sqlite::memory:").await.unwrap();
+        let script = "CREATE TABLE t (x INTEGER, doubled INTEGER);
+            CREATE TRIGGER t_double AFTER INSERT ON t BEGIN
+                UPDATE t SET doubled = new.x * 2 WHERE rowid = new.rowid;
+            END;
+            INSERT INTO t (x) VALUES (21);
+            SELECT doubled FROM t;";
+        assert_eq!(run(&connection, script).await, [[CellValue::Integer(42)]]);
+    }
+
+    #[tokio::test]
+    async fn decodes_sqlite_values_by_storage_and_declared_type() {
+        let connection = Connection::connect("sqlite::memory:").await.unwrap();
+        let script = "CREATE TABLE t (n INTEGER, r REAL, s TEXT, b BLOB, flag BOOLEAN, x);
+            INSERT INTO t VALUES (42, 2.5, 'text', x'00ff', 1, 7);
+            INSERT INTO t VALUES (NULL, NULL, NULL, NULL, NULL, NULL);
+            INSERT INTO t VALUES ('not a number', 3, '12', 'text', 0, 1.5);
+            SELECT * FROM t;";
+        let text = |text: &str| CellValue::Text(text.to_string());
+        assert_eq!(
+            run(&connection, script).await,
+            [
+                vec![
+                    CellValue::Integer(42),
+                    CellValue::Real(2.5),
+                    text("text"),
+                    CellValue::Blob(vec![0, 255]),
+                    CellValue::Bool(true),
+                    CellValue::Integer(7),
+                ],
+                vec![CellValue::Null; 6],
+                vec![
+                    text("not a number"),
+                    CellValue::Real(3.0),
+                    text("12"),
+                    text("text"),
+                    CellValue::Bool(false),
+                    CellValue::Real(1.5),
+                ],
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn cancels_a_statement_without_rows_after_a_page_request() {
+        let connection = Connection::connect("sqlite::memory:").await.unwrap();
+        let endless = "CREATE TABLE t AS WITH RECURSIVE c(x) AS \
+            (SELECT 1 UNION ALL SELECT x + 1 FROM c) SELECT x FROM c";
+        let statements = [BoundStatement {
+            sql: endless.to_string(),
+            parameters: Vec::new(),
+        }];
+        let (requests, received) = mpsc::unbounded_channel();
+        requests.send(()).unwrap();
+        tokio::task::spawn_blocking(move || {
+            std::thread::sleep(std::time::Duration::from_millis(100));
+            drop(requests);
+        });
+        // Gives up after ten seconds, or as soon as `finished` is dropped.
+        let (finished, waiting) = std::sync::mpsc::channel::<()>();
+        let give_up = tokio::task::spawn_blocking(move || {
+            let _ = waiting.recv_timeout(std::time::Duration::from_secs(10));
+        });
+        tokio::select! {
+            result = connection.run_script(None, &statements, received, |_, _| {}) => {
+                assert!(result.is_ok());
             }
-            Err(_) => None,
-        },
-        StartingDb::None => None,
+            _ = give_up => panic!("the statement was not interrupted"),
+        }
+        drop(finished);
+    }
+
+    /// Set `SQUEALER_TEST_POSTGRES_URL` to run this.
+    #[tokio::test]
+    async fn runs_a_postgres_function_script() {
+        let Some(connection) = server("SQUEALER_TEST_POSTGRES_URL").await else {
+            return;
+        };
+        let script = "CREATE FUNCTION pg_temp.squealer_double(x int) RETURNS int AS $$
+            BEGIN
+                RETURN x * 2;
+            END;
+            $$ LANGUAGE plpgsql;
+            SELECT pg_temp.squealer_double(21);";
+        assert_eq!(run(&connection, script).await, [[CellValue::Integer(42)]]);
+    }
+
+    /// Set `SQUEALER_TEST_MYSQL_URL` to run this. MySQL cannot prepare
+    /// `CREATE FUNCTION`, so this also covers sending it unprepared.
+    #[tokio::test]
+    async fn runs_a_mysql_function_script() {
+        let Some(connection) = server("SQUEALER_TEST_MYSQL_URL").await else {
+            return;
+        };
+        let script = "DROP FUNCTION IF EXISTS squealer_double;
+            CREATE FUNCTION squealer_double(x INT) RETURNS INT DETERMINISTIC
+            BEGIN
+                RETURN x * 2;
+            END;
+            SELECT squealer_double(21);
+            DROP FUNCTION squealer_double;";
+        assert_eq!(run(&connection, script).await, [[CellValue::Integer(42)]]);
     }
-}
 
-pub async fn connect_to_database_file(file: &str) -> Option<sqlx::Pool<Sqlite>> {
-    match sqlx::SqlitePool::connect(format!("sqlite://{}", file).as_str()).await {
-        Ok(pool) => Some(pool),
-        Err(_) => None,
+    #[cfg(feature = "duckdb")]
+    #[tokio::test]
+    async fn runs_a_duckdb_macro_script() {
+        let connection = Connection::connect("duckdb://:memory:").await.unwrap();
+        let script = "CREATE MACRO squealer_double(x) AS x * 2;
+            SELECT squealer_double(21)::INTEGER;";
+        assert_eq!(run(&connection, script).await, [[CellValue::Integer(42)]]);
     }
 }
@@ -0,0 +1,540 @@
+use std::error::Error;
+
+use crate::completion::{name, referenced_relations};
+use crate::database::{BoundStatement, Connection, Transaction};
+use crate::lexer::{tokenize, Token};
+use crate::model::CellValue;
+use crate::schema::{quote_name, ColumnInfo, Relation, Schema};
+use crate::script::Dialect;
+
+/// The table the rows of a result were read from, and how to find each of
+/// them in it again.
+#[derive(Clone, Debug)]
+pub struct RowSource {
+    pub table: String,
+    /// The table as statements name it, quoted.
+    quoted_table: String,
+    /// For each column of the result, the column of the table it shows.
+    pub columns: Vec<Option<String>>,
+    /// The columns of the result that pick out a row: the table's primary
+    /// key, or SQLite's rowid.
+    pub key: Vec<usize>,
+    /// Every column of the table, as the schema describes them.
+    pub fields: Vec<ColumnInfo>,
+    dialect: Dialect,
+}
+
+/// Rows of a tab of the results that were changed, so that the tab can be
+/// brought up to date once the change has been made.
+#[derive(Clone, Debug)]
+pub struct ChangedRows {
+    pub source: RowSource,
+    /// The statement of the tab, in case the results were replaced since.
+    pub statement: String,
+    pub tab: usize,
+    pub change: RowChange,
+}
+
+#[derive(Clone, Debug)]
+pub enum RowChange {
+    /// A row was updated, and is read back to replace it.
+    Updated(usize),
+    /// A row was inserted, and goes in at this position if it is read back.
+    Inserted(usize),
+    Deleted(Vec<usize>),
+}
+
+/// The names SQLite answers to for a row's rowid.
+const ROWID: [&str; 3] = ["rowid", "_rowid_", "oid"];
+
+/// The table a query reads, when it only selects columns of a single table,
+/// without joins, grouping, `DISTINCT` or set operations, so that each row
+/// of its result is a row of the table.
+pub fn queried_table(statement: &str, dialect: Dialect) -> Option<String> {
+    let tokens = tokenize(statement, dialect);
+    let is = |token: &Token, keyword: &str| matches!(token, Token::Word(word) if word.eq_ignore_ascii_case(keyword));
+    if !tokens.first().is_some_and(|token| is(token, "SELECT")) {
+        return None;
+    }
+    let from = tokens.iter().position(|token| is(token, "FROM"))?;
+    // Names, `table.name` and stars separated by commas: no expressions,
+    // aliases or `DISTINCT`.
+    let mut after_name = false;
+    for token in &tokens[1..from] {
+        after_name = match token {
+            token if !after_name && name(token, dialect).is_some() => true,
+            Token::Punct('*') if !after_name => true,
+            Token::Punct('.' | ',') if after_name => false,
+            _ => return None,
+        };
+    }
+    let excluded = [
+        "SELECT",
+        "JOIN",
+        "GROUP",
+        "HAVING",
+        "UNION",
+        "INTERSECT",
+        "EXCEPT",
+        "WINDOW",
+    ];
+    let nested = tokens[1..]
+        .iter()
+        .any(|token| excluded.iter().any(|keyword| is(token, keyword)));
+    match referenced_relations(&tokens, dialect).as_slice() {
+        [(table, _)] if after_name && !nested => Some(table.clone()),
+        _ => None,
+    }
+}
+
+impl RowSource {
+    /// Find the table a result was read from in the schema and the columns
+    /// of the result that are its key, or say why its rows cannot be
+    /// changed.
+    pub fn find(
+        statement: &str,
+        headers: &[String],
+        schema: &Schema,
+        dialect: Dialect,
+    ) -> Result<RowSource, String> {
+        let table = queried_table(statement, dialect)
+            .ok_or("Only the rows of a plain SELECT from one table can be changed")?;
+        let unqualified = table
+            .rsplit_once('.')
+            .map_or(table.as_str(), |(_, name)| name);
+        let relation = [table.as_str(), unqualified]
+            .iter()
+            .find_map(|name| find_relation(schema, name))
+            .ok_or_else(|| format!("{} is not a table", table))?;
+        let columns = headers
+            .iter()
+            .map(|header| {
+                let column = relation
+                    .columns
+                    .iter()
+                    .find(|column| column.name.eq_ignore_ascii_case(header));
+                match column {
+                    Some(column) => Some(column.name.clone()),
+                    None if dialect == Dialect::Sqlite
+                        && ROWID.iter().any(|rowid| rowid.eq_ignore_ascii_case(header)) =>
+                    {
+                        Some(header.clone())
+                    }
+                    None => None,
+                }
+            })
+            .collect::<Vec<_>>();
+        let primary_key = relation
+            .columns
+            .iter()
+            .filter(|column| column.primary_key)
+            .map(|column| column.name.as_str())
+            .collect::<Vec<_>>();
+        let position = |name: &str| {
+            columns
+                .iter()
+                .position(|column| column.as_deref() == Some(name))
+        };
+        let key = if primary_key.is_empty() {
+            let rowid = ROWID.iter().find_map(|rowid| {
+                columns.iter().position(|column| {
+                    column
+                        .as_deref()
+                        .is_some_and(|column| column.eq_ignore_ascii_case(rowid))
+                })
+            });
+            match (rowid, dialect) {
+                (Some(rowid), _) => vec![rowid],
+                (None, Dialect::Sqlite) => {
+                    return Err(format!(
+                        "{} has no primary key, select its rowid to change its rows",
+                        relation.qualified_name()
+                    ))
+                }
+                (None, _) => {
+                    return Err(format!("{} has no primary key", relation.qualified_name()))
+                }
+            }
+        } else {
+            let missing = primary_key
+                .iter()
+                .filter(|name| position(name).is_none())
+                .copied()
+                .collect::<Vec<_>>();
+            if !missing.is_empty() {
+                return Err(format!(
+                    "Select {} to change the rows of {}",
+                    missing.join(", "),
+                    relation.qualified_name()
+                ));
+            }
+            primary_key
+                .iter()
+                .filter_map(|name| position(name))
+                .collect()
+        };
+        Ok(RowSource {
+            table: relation.qualified_name(),
+            quoted_table: relation.quoted_name(dialect),
+            columns,
+            key,
+            fields: relation.columns.clone(),
+            dialect,
+        })
+    }
+
+    /// The statement that sets one column of a row, with the same
+    /// statement written out with its values for showing.
+    pub fn update(
+        &self,
+        row: &[CellValue],
+        column: usize,
+        value: CellValue,
+    ) -> (BoundStatement, String) {
+        let mut sql = Sql::new(self.dialect);
+        sql.push(&format!("UPDATE {} SET ", self.quoted_table));
+        sql.push(&self.quoted(column));
+        sql.push(" = ");
+        sql.bind(value);
+        sql.push(" WHERE ");
+        self.key_condition(&mut sql, row);
+        sql.finish()
+    }
+
+    /// The statement that reads a row again, by the values of its key.
+    pub fn select(&self, row: &[CellValue]) -> BoundStatement {
+        let mut sql = Sql::new(self.dialect);
+        sql.push(&format!(
+            "SELECT {} FROM {} WHERE ",
+            self.selected_columns(),
+            self.quoted_table
+        ));
+        self.key_condition(&mut sql, row);
+        sql.finish().0
+    }
+
+    /// The statement that inserts a row with the values given for some of
+    /// the table's columns, leaving the rest to their defaults. Except on
+    /// MySQL, the row comes back from it in the columns of the results.
+    pub fn insert(&self, values: Vec<(String, CellValue)>) -> (BoundStatement, String) {
+        let mut sql = Sql::new(self.dialect);
+        sql.push(&format!("INSERT INTO {}", self.quoted_table));
+        match (values.is_empty(), self.dialect) {
+            (true, Dialect::MySql) => sql.push(" () VALUES ()"),
+            (true, _) => sql.push(" DEFAULT VALUES"),
+            (false, _) => {
+                let names = values
+                    .iter()
+                    .map(|(name, _)| quote_name(name, self.dialect))
+                    .collect::<Vec<_>>();
+                sql.push(&format!(" ({}) VALUES (", names.join(", ")));
+                for (i, (_, value)) in values.into_iter().enumerate() {
+                    if i > 0 {
+                        sql.push(", ");
+                    }
+                    sql.bind(value);
+                }
+                sql.push(")");
+            }
+        }
+        if self.dialect != Dialect::MySql {
+            sql.push(&format!(" RETURNING {}", self.selected_columns()));
+        }
+        sql.finish()
+    }
+
+    /// The statement that deletes rows by the values of their keys.
+    pub fn delete(&self, rows: &[&[CellValue]]) -> (BoundStatement, String) {
+        let mut sql = Sql::new(self.dialect);
+        sql.push(&format!("DELETE FROM {} WHERE ", self.quoted_table));
+        match (rows, self.key.as_slice()) {
+            ([row], _) => self.key_condition(&mut sql, row),
+            (rows, &[column]) => {
+                sql.push(&format!("{} IN (", self.quoted(column)));
+                for (i, row) in rows.iter().enumerate() {
+                    if i > 0 {
+                        sql.push(", ");
+                    }
+                    sql.bind(row[column].clone());
+                }
+                sql.push(")");
+            }
+            (rows, _) => {
+                for (i, row) in rows.iter().enumerate() {
+                    sql.push(if i == 0 { "(" } else { " OR (" });
+                    self.key_condition(&mut sql, row);
+                    sql.push(")");
+                }
+            }
+        }
+        sql.finish()
+    }
+
+    /// A row read by `select` or returned by `insert`, with the columns of
+    /// the results that are not the table's kept as they were in `old`.
+    pub fn refreshed(&self, old: &[CellValue], fetched: Vec<CellValue>) -> Vec<CellValue> {
+        let mut fetched = fetched.into_iter();
+        old.iter()
+            .zip(&self.columns)
+            .map(|(value, column)| match column {
+                Some(_) => fetched.next().unwrap_or(CellValue::Null),
+                None => value.clone(),
+            })
+            .collect()
+    }
+
+    pub fn is_key(&self, column: usize) -> bool {
+        self.key.contains(&column)
+    }
+
+    /// The table's column a column of the results shows, unless it is
+    /// SQLite's rowid.
+    pub fn field(&self, column: usize) -> Option<&ColumnInfo> {
+        let name = self.columns.get(column)?.as_deref()?;
+        self.fields.iter().find(|field| field.name == name)
+    }
+
+    fn quoted(&self, column: usize) -> String {
+        match &self.columns[column] {
+            Some(name) if ROWID.contains(&name.to_lowercase().as_str()) => name.clone(),
+            Some(name) => quote_name(name, self.dialect),
+            None => String::new(),
+        }
+    }
+
+    /// The table's columns among the results, to read rows back in the
+    /// same order.
+    fn selected_columns(&self) -> String {
+        let columns = (0..self.columns.len())
+            .filter(|&index| self.columns[index].is_some())
+            .map(|index| self.quoted(index))
+            .collect::<Vec<_>>();
+        columns.join(", ")
+    }
+
+    fn key_condition(&self, sql: &mut Sql, row: &[CellValue]) {
+        for (i, &column) in self.key.iter().enumerate() {
+            if i > 0 {
+                sql.push(" AND ");
+            }
+            sql.push(&self.quoted(column));
+            match &row[column] {
+                CellValue::Null => sql.push(" IS NULL"),
+                value => {
+                    sql.push(" = ");
+                    sql.bind(value.clone());
+                }
+            }
+        }
+    }
+}
+
+fn find_relation<'a>(schema: &'a Schema, name: &str) -> Option<&'a Relation> {
+    schema
+        .tables
+        .iter()
+        .find(|relation| relation.qualified_name().eq_ignore_ascii_case(name))
+}
+
+/// Run the statements that make a change to rows, on the transaction's
+/// connection when one is given, and return the rows the last of them
+/// gives back.
+pub async fn change_rows(
+    connection: &Connection,
+    transaction: Option<&Transaction>,
+    statements: &[BoundStatement],
+) -> Result<Vec<Vec<CellValue>>, Box<dyn Error>> {
+    let mut rows = Vec::new();
+    for statement in statements {
+        rows = connection.fetch_bound(transaction, statement).await?;
+    }
+    Ok(rows)
+}
+
+/// A statement being written with its values bound, and alongside it the
+/// same statement with the values written in, to show before it runs.
+struct Sql {
+    dialect: Dialect,
+    bound: BoundStatement,
+    shown: String,
+}
+
+impl Sql {
+    fn new(dialect: Dialect) -> Self {
+        Sql {
+            dialect,
+            bound: BoundStatement {
+                sql: String::new(),
+                parameters: Vec::new(),
+            },
+            shown: String::new(),
+        }
+    }
+
+    fn push(&mut self, text: &str) {
+        self.bound.sql.push_str(text);
+        self.shown.push_str(text);
+    }
+
+    fn bind(&mut self, value: CellValue) {
+        self.shown.push_str(&literal(&value));
+        self.bound.parameters.push(value);
+        match self.dialect {
+            Dialect::Postgres => {
+                let placeholder = format!("${}", self.bound.parameters.len());
+                self.bound.sql.push_str(&placeholder);
+            }
+            _ => self.bound.sql.push('?'),
+        }
+    }
+
+    fn finish(self) -> (BoundStatement, String) {
+        (self.bound, self.shown)
+    }
+}
+
+/// A value typed in for a column, read as `CellValue::from_input` reads
+/// it except that a textual column keeps what looks like a number or a
+/// boolean as the text it is, so `00501` is not stored as 501.
+pub fn input_value(input: &str, field: Option<&ColumnInfo>) -> CellValue {
+    match CellValue::from_input(input) {
+        CellValue::Bool(_) | CellValue::Integer(_) | CellValue::Real(_)
+            if field.is_some_and(|field| is_textual(&field.data_type)) =>
+        {
+            CellValue::Text(input.to_string())
+        }
+        value => value,
+    }
+}
+
+/// A value as it is typed in to change it, quoted where `input_value`
+/// would otherwise read it back as something else.
+pub fn input_text(value: &CellValue, field: Option<&ColumnInfo>) -> String {
+    match value {
+        CellValue::Null => "null".to_string(),
+        CellValue::Text(text) if input_value(text, field) != *value => literal(value),
+        value => value.to_string(),
+    }
+}
+
+/// Whether a column's declared type keeps what it is given as text, by
+/// SQLite's rules for a column's affinity, which also fit the names the
+/// other backends give their text types.
+fn is_textual(data_type: &str) -> bool {
+    let data_type = data_type.to_uppercase();
+    !data_type.contains("INT")
+        && ["CHAR", "CLOB", "TEXT"]
+            .iter()
+            .any(|name| data_type.contains(name))
+}
+
+/// A value written as an SQL literal.
+pub fn literal(value: &CellValue) -> String {
+    match value {
+        CellValue::Null => "NULL".to_string(),
+        CellValue::Bool(true) => "TRUE".to_string(),
+        CellValue::Bool(false) => "FALSE".to_string(),
+        CellValue::Integer(value) => value.to_string(),
+        CellValue::Unsigned(value) => value.to_string(),
+        CellValue::Real(value) => value.to_string(),
+        CellValue::Decimal(value) => value.to_string(),
+        CellValue::Blob(bytes) => {
+            let digits = bytes.iter().map(|byte| format!("{:02x}", byte));
+            format!("x'{}'", digits.collect::<String>())
+        }
+        value => format!("'{}'", value.to_string().replace('\'', "''")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn column(data_type: &str) -> ColumnInfo {
+        ColumnInfo {
+            name: "c".to_string(),
+            data_type: data_type.to_string(),
+            not_null: false,
+            primary_key: false,
+            default: None,
+            references: None,
+        }
+    }
+
+    #[test]
+    fn textual_columns_keep_numbers_as_typed() {
+        for data_type in ["TEXT", "varchar(20)", "character varying", "NCHAR(5)"] {
+            let field = column(data_type);
+            for input in ["00501", "+44 20 7946 0000", "1e3", "true"] {
+                assert_eq!(
+                    input_value(input, Some(&field)),
+                    CellValue::Text(input.to_string())
+                );
+            }
+            assert_eq!(input_value("null", Some(&field)), CellValue::Null);
+            assert_eq!(
+                input_value("'null'", Some(&field)),
+                CellValue::Text("null".to_string())
+            );
+        }
+        for field in [Some(column("INTEGER")), Some(column("")), None] {
+            assert_eq!(
+                input_value("00501", field.as_ref()),
+                CellValue::Integer(501)
+            );
+            assert_eq!(input_value("true", field.as_ref()), CellValue::Bool(true));
+        }
+    }
+
+    #[test]
+    fn values_typed_back_in_read_as_they_were() {
+        let text = |text: &str| CellValue::Text(text.to_string());
+        let cases = [
+            (text("00501"), column("TEXT"), "00501"),
+            (text("+44"), column("varchar"), "+44"),
+            (text("null"), column("TEXT"), "'null'"),
+            (text("'quoted'"), column("TEXT"), "'''quoted'''"),
+            (text("it's"), column("TEXT"), "it's"),
+            (text("00501"), column("INTEGER"), "'00501'"),
+            (text("true"), column(""), "'true'"),
+            (text(" padded "), column(""), " padded "),
+            (CellValue::Integer(501), column("INTEGER"), "501"),
+            (CellValue::Real(2.5), column("REAL"), "2.5"),
+            (CellValue::Bool(false), column("BOOLEAN"), "false"),
+            (CellValue::Null, column("TEXT"), "null"),
+        ];
+        for (value, field, typed) in cases {
+            assert_eq!(input_text(&value, Some(&field)), typed);
+            assert_eq!(input_value(typed, Some(&field)), value);
+        }
+    }
+
+    #[test]
+    fn keywords_that_are_not_reserved_can_be_columns() {
+        let statement = "select key, value from t";
+        assert_eq!(
+            queried_table(statement, Dialect::Sqlite),
+            Some("t".to_string())
+        );
+        assert_eq!(
+            queried_table(statement, Dialect::Postgres),
+            Some("t".to_string())
+        );
+        assert_eq!(queried_table(statement, Dialect::MySql), None);
+        let quoted = "select `key`, value from t";
+        assert_eq!(queried_table(quoted, Dialect::MySql), Some("t".to_string()));
+    }
+
+    #[test]
+    fn strings_and_comments_do_not_count() {
+        let statement = "select a -- , b from u\nfrom t where a = 'from v'";
+        assert_eq!(
+            queried_table(statement, Dialect::Sqlite),
+            Some("t".to_string())
+        );
+        assert_eq!(
+            queried_table("select a, count(*) from t", Dialect::Sqlite),
+            None
+        );
+    }
+}
@@ -0,0 +1,289 @@
+use sqlx::types::JsonValue;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use tokio::sync::mpsc::UnboundedReceiver;
+
+use crate::model::CellValue;
+
+/// The file formats results can be exported in.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ExportFormat {
+    Csv,
+    Tsv,
+    Json,
+    Ndjson,
+    Markdown,
+}
+
+impl ExportFormat {
+    pub const ALL: [ExportFormat; 5] = [
+        ExportFormat::Csv,
+        ExportFormat::Tsv,
+        ExportFormat::Json,
+        ExportFormat::Ndjson,
+        ExportFormat::Markdown,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "CSV",
+            ExportFormat::Tsv => "TSV",
+            ExportFormat::Json => "JSON",
+            ExportFormat::Ndjson => "NDJSON",
+            ExportFormat::Markdown => "Markdown",
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Tsv => "tsv",
+            ExportFormat::Json => "json",
+            ExportFormat::Ndjson => "ndjson",
+            ExportFormat::Markdown => "md",
+        }
+    }
+
+    /// The format a file name's extension asks for, if it is one of them.
+    pub fn from_path(path: &str) -> Option<Self> {
+        let extension = Path::new(path).extension()?.to_str()?.to_lowercase();
+        ExportFormat::ALL.into_iter().find(|format| {
+            format.extension() == extension
+                || (extension == "jsonl" && *format == ExportFormat::Ndjson)
+        })
+    }
+}
+
+/// What an export is sent as the rows are fetched.
+#[derive(Debug)]
+pub enum ExportPage {
+    Rows(Vec<Vec<CellValue>>),
+    /// There are no more rows.
+    End,
+    /// The query failed before all the rows came.
+    Failed(String),
+}
+
+/// Write a file of rows from pages as they arrive, until the end of them,
+/// and return how many rows were written. If the query fails or the pages
+/// stop before the end, because it was cancelled, the partial file is
+/// removed and this is an error. This blocks, so it belongs on a blocking
+/// task.
+pub fn export(
+    path: &Path,
+    format: ExportFormat,
+    headers: Vec<String>,
+    mut pages: UnboundedReceiver<ExportPage>,
+) -> Result<u64, String> {
+    let mut writer = ExportWriter::create(path, format, headers).map_err(|e| e.to_string())?;
+    let error = loop {
+        let error = match pages.blocking_recv() {
+            Some(ExportPage::Rows(rows)) => match writer.write_rows(&rows) {
+                Ok(()) => continue,
+                Err(error) => error.to_string(),
+            },
+            Some(ExportPage::End) => match writer.finish() {
+                Ok(rows) => return Ok(rows),
+                Err(error) => break error.to_string(),
+            },
+            Some(ExportPage::Failed(error)) => error,
+            None => "the query was stopped".to_string(),
+        };
+        break format!("{} after {} rows", error, writer.rows);
+    };
+    let _ = fs::remove_file(path);
+    Err(format!("{}, so the file was removed", error))
+}
+
+/// Writes rows to a file as they come, so an export never holds more than
+/// a page of them. NULL is an empty field in CSV, `\N` in TSV, `null` in
+/// JSON and `NULL` in Markdown.
+pub struct ExportWriter {
+    format: ExportFormat,
+    headers: Vec<String>,
+    out: BufWriter<File>,
+    rows: u64,
+}
+
+impl ExportWriter {
+    /// Create the file and write what comes before the rows.
+    pub fn create(path: &Path, format: ExportFormat, headers: Vec<String>) -> io::Result<Self> {
+        let mut writer = ExportWriter {
+            format,
+            headers,
+            out: BufWriter::new(File::create(path)?),
+            rows: 0,
+        };
+        match format {
+            ExportFormat::Csv => {
+                let fields = writer.headers.iter().map(|header| csv_field(header));
+                writeln!(writer.out, "{}", fields.collect::<Vec<_>>().join(","))?;
+            }
+            ExportFormat::Tsv => {
+                let fields = writer.headers.iter().map(|header| tsv_field(header));
+                writeln!(writer.out, "{}", fields.collect::<Vec<_>>().join("\t"))?;
+            }
+            ExportFormat::Json => write!(writer.out, "[")?,
+            ExportFormat::Ndjson => {}
+            ExportFormat::Markdown => {
+                let fields = writer.headers.iter().map(|header| markdown_field(header));
+                writeln!(writer.out, "| {} |", fields.collect::<Vec<_>>().join(" | "))?;
+                let rule = vec!["---"; writer.headers.len()].join(" | ");
+                writeln!(writer.out, "| {} |", rule)?;
+            }
+        }
+        Ok(writer)
+    }
+
+    pub fn write_rows(&mut self, rows: &[Vec<CellValue>]) -> io::Result<()> {
+        for row in rows {
+            match self.format {
+                ExportFormat::Csv => {
+                    let fields = row.iter().map(|value| match text(value) {
+                        Some(text) if text.is_empty() => "\"\"".to_string(),
+                        Some(text) => csv_field(&text),
+                        None => String::new(),
+                    });
+                    writeln!(self.out, "{}", fields.collect::<Vec<_>>().join(","))?;
+                }
+                ExportFormat::Tsv => {
+                    let fields = row.iter().map(|value| match text(value) {
+                        Some(text) => tsv_field(&text),
+                        None => "\\N".to_string(),
+                    });
+                    writeln!(self.out, "{}", fields.collect::<Vec<_>>().join("\t"))?;
+                }
+                ExportFormat::Json => {
+                    let separator = if self.rows == 0 { "" } else { "," };
+                    write!(self.out, "{}\n  {}", separator, self.json_object(row))?;
+                }
+                ExportFormat::Ndjson => writeln!(self.out, "{}", self.json_object(row))?,
+                ExportFormat::Markdown => {
+                    let fields = row.iter().map(|value| match text(value) {
+                        Some(text) => markdown_field(&text),
+                        None => "NULL".to_string(),
+                    });
+                    writeln!(self.out, "| {} |", fields.collect::<Vec<_>>().join(" | "))?;
+                }
+            }
+            self.rows += 1;
+        }
+        Ok(())
+    }
+
+    /// Write what comes after the rows and return how many there were.
+    pub fn finish(mut self) -> io::Result<u64> {
+        if self.format == ExportFormat::Json {
+            match self.rows {
+                0 => writeln!(self.out, "]")?,
+                _ => writeln!(self.out, "\n]")?,
+            }
+        }
+        self.out.flush()?;
+        Ok(self.rows)
+    }
+
+    /// A row as an object keyed by column, in the order of the columns.
+    fn json_object(&self, row: &[CellValue]) -> String {
+        let members = self.headers.iter().zip(row).map(|(header, value)| {
+            format!("{}:{}", JsonValue::String(header.clone()), json(value))
+        });
+        format!("{{{}}}", members.collect::<Vec<_>>().join(","))
+    }
+}
+
+/// A value as it is written in the text formats, or `None` for NULL. Blobs
+/// are written whole as `\x` and hex, as Postgres does.
+fn text(value: &CellValue) -> Option<String> {
+    match value {
+        CellValue::Null => None,
+        CellValue::Blob(bytes) => Some(hex(bytes)),
+        value => Some(value.to_string()),
+    }
+}
+
+/// A value as JSON. Numbers and booleans keep their type, decimals are
+/// written out in full and JSON columns are nested as they are.
+fn json(value: &CellValue) -> String {
+    match value {
+        CellValue::Null => "null".to_string(),
+        CellValue::Bool(value) => value.to_string(),
+        CellValue::Integer(value) => value.to_string(),
+        CellValue::Unsigned(value) => value.to_string(),
+        CellValue::Real(value) => JsonValue::from(*value).to_string(),
+        CellValue::Decimal(value) => value.to_string(),
+        CellValue::Json(value) => value.to_string(),
+        CellValue::Blob(bytes) => JsonValue::String(hex(bytes)).to_string(),
+        value => JsonValue::String(value.to_string()).to_string(),
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    let digits = bytes.iter().map(|byte| format!("{:02x}", byte));
+    format!("\\x{}", digits.collect::<String>())
+}
+
+/// Quote a field that has a comma, quote or line break in it, or space at
+/// either end, doubling its quotes.
+fn csv_field(text: &str) -> String {
+    let needs_quotes =
+        text.contains([',', '"', '\n', '\r']) || text.starts_with(' ') || text.ends_with(' ');
+    match needs_quotes {
+        true => format!("\"{}\"", text.replace('"', "\"\"")),
+        false => text.to_string(),
+    }
+}
+
+/// Escape the backslashes, tabs and line breaks in a field the way
+/// Postgres' `COPY` does.
+fn tsv_field(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('\t', "\\t")
+        .replace('\n', "\\n")
+        .replace('\r', "\\r")
+}
+
+fn markdown_field(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('|', "\\|")
+        .replace("\r\n", "<br>")
+        .replace('\n', "<br>")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::sync::mpsc;
+
+    fn export_pages(name: &str, pages: Vec<ExportPage>) -> (Result<u64, String>, Option<String>) {
+        let path =
+            std::env::temp_dir().join(format!("squealer-{}-{}.csv", name, std::process::id()));
+        let (sender, received) = mpsc::unbounded_channel();
+        for page in pages {
+            sender.send(page).unwrap();
+        }
+        drop(sender);
+        let result = export(&path, ExportFormat::Csv, vec!["a".to_string()], received);
+        let written = fs::read_to_string(&path).ok();
+        let _ = fs::remove_file(&path);
+        (result, written)
+    }
+
+    #[test]
+    fn succeeds_only_at_the_end_of_the_rows() {
+        let rows = || ExportPage::Rows(vec![vec![CellValue::Integer(1)], vec![CellValue::Null]]);
+        let (result, written) = export_pages("end", vec![rows(), ExportPage::End]);
+        assert_eq!(result, Ok(2));
+        assert_eq!(written.as_deref(), Some("a\n1\n\n"));
+
+        let (result, written) = export_pages("stopped", vec![rows()]);
+        assert!(result.is_err_and(|error| error.contains("stopped after 2 rows")));
+        assert_eq!(written, None);
+
+        let failed = ExportPage::Failed("lost the connection".to_string());
+        let (result, written) = export_pages("failed", vec![rows(), failed]);
+        assert!(result.is_err_and(|error| error.starts_with("lost the connection")));
+        assert_eq!(written, None);
+    }
+}
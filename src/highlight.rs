@@ -0,0 +1,337 @@
+use crate::lexer::{
+    backslash_escapes, block_comment_end, dollar_quote_tag, is_keyword, quoted_end, word_end,
+};
+use crate::script::Dialect;
+use std::ops::Range;
+
+/// What a run of SQL is, which decides its colour.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum TokenKind {
+    Keyword,
+    Identifier,
+    String,
+    Number,
+    Comment,
+    Parameter,
+    Bracket,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Token {
+    /// Where the token is in its line, in bytes.
+    pub range: Range<usize>,
+    pub kind: TokenKind,
+}
+
+/// What a line starts inside of, left open by the lines before it.
+#[derive(Clone, PartialEq, Debug, Default)]
+enum LexState {
+    #[default]
+    Code,
+    /// A block comment, nested this deep.
+    Comment(usize),
+    Quoted {
+        close: u8,
+        backslash_escapes: bool,
+        kind: TokenKind,
+    },
+    /// A Postgres `$tag$` string.
+    DollarQuoted(String),
+}
+
+/// How many lines either side of the cursor are searched for a matching
+/// bracket.
+const BRACKET_SEARCH_LINES: usize = 1000;
+
+/// Highlights the lines of the query editor. How each line starts is
+/// remembered, so only the lines drawn are lexed after the first frame
+/// rather than the whole script.
+#[derive(Default)]
+pub struct Highlighter {
+    dialect: Option<Dialect>,
+    /// The state at the start of each line lexed so far, from the first.
+    starts: Vec<LexState>,
+}
+
+impl Highlighter {
+    /// Forget how the lines after `row` start, after `row` was edited.
+    pub fn invalidate(&mut self, row: usize) {
+        self.starts.truncate(row + 1);
+    }
+
+    pub fn line(&mut self, lines: &[String], row: usize, dialect: Dialect) -> Vec<Token> {
+        let start = self.start_of(lines, row, dialect);
+        lex_line(&lines[row], start, dialect).0
+    }
+
+    fn start_of(&mut self, lines: &[String], row: usize, dialect: Dialect) -> LexState {
+        if self.dialect != Some(dialect) {
+            self.dialect = Some(dialect);
+            self.starts.clear();
+        }
+        if self.starts.is_empty() {
+            self.starts.push(LexState::Code);
+        }
+        while self.starts.len() <= row {
+            let last = self.starts.len() - 1;
+            let start = self.starts[last].clone();
+            let (_, end) = lex_line(&lines[last], start, dialect);
+            self.starts.push(end);
+        }
+        self.starts[row].clone()
+    }
+
+    /// The bracket at the cursor, or just before it, and the one that
+    /// matches it, as rows and byte offsets.
+    pub fn matching_brackets(
+        &mut self,
+        lines: &[String],
+        cursor: (usize, usize),
+        dialect: Dialect,
+    ) -> Option<[(usize, usize); 2]> {
+        let (row, column) = cursor;
+        let line = lines.get(row)?;
+        let offset = line
+            .char_indices()
+            .nth(column)
+            .map_or(line.len(), |(i, _)| i);
+        let tokens = self.line(lines, row, dialect);
+        let bracket = |at: usize| {
+            tokens
+                .iter()
+                .find(|token| token.kind == TokenKind::Bracket && token.range.start == at)
+        };
+        let previous = line[..offset].chars().next_back().map(char::len_utf8);
+        let at = bracket(offset)
+            .or_else(|| previous.and_then(|len| bracket(offset - len)))?
+            .range
+            .start;
+        let open = line[at..].chars().next()?;
+        let (close, forward) = match open {
+            '(' => (')', true),
+            '[' => (']', true),
+            ')' => ('(', false),
+            ']' => ('[', false),
+            _ => return None,
+        };
+        let mut depth = 0;
+        let rows: Box<dyn Iterator<Item = usize>> = match forward {
+            true => Box::new(row..lines.len().min(row + BRACKET_SEARCH_LINES)),
+            false => Box::new((row.saturating_sub(BRACKET_SEARCH_LINES)..=row).rev()),
+        };
+        for current in rows {
+            let tokens = self.line(lines, current, dialect);
+            let brackets = tokens
+                .iter()
+                .filter(|token| token.kind == TokenKind::Bracket)
+                .filter(|token| match (current == row, forward) {
+                    (false, _) => true,
+                    (true, true) => token.range.start >= at,
+                    (true, false) => token.range.start <= at,
+                });
+            let brackets: Box<dyn Iterator<Item = &Token>> = match forward {
+                true => Box::new(brackets),
+                false => Box::new(brackets.rev()),
+            };
+            for token in brackets {
+                match lines[current][token.range.clone()].chars().next() {
+                    Some(c) if c == open => depth += 1,
+                    Some(c) if c == close => {
+                        depth -= 1;
+                        if depth == 0 {
+                            return Some([(row, at), (current, token.range.start)]);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+        None
+    }
+}
+
+/// Split a line into tokens, given what the lines before it left open, and
+/// what it leaves open for the next.
+fn lex_line(line: &str, mut state: LexState, dialect: Dialect) -> (Vec<Token>, LexState) {
+    let bytes = line.as_bytes();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        let start = i;
+        let (end, kind) = match state.clone() {
+            LexState::Comment(depth) => {
+                let (end, depth) = block_comment_end(bytes, i, depth, dialect);
+                state = if depth == 0 {
+                    LexState::Code
+                } else {
+                    LexState::Comment(depth)
+                };
+                (end, TokenKind::Comment)
+            }
+            LexState::Quoted {
+                close,
+                backslash_escapes,
+                kind,
+            } => match quoted_end(bytes, i, close, backslash_escapes) {
+                Some(end) => {
+                    state = LexState::Code;
+                    (end, kind)
+                }
+                None => (bytes.len(), kind),
+            },
+            LexState::DollarQuoted(tag) => match line[i..].find(tag.as_str()) {
+                Some(found) => {
+                    let end = i + found + tag.len();
+                    state = LexState::Code;
+                    (end, TokenKind::String)
+                }
+                None => (bytes.len(), TokenKind::String),
+            },
+            LexState::Code => {
+                let c = line[i..].chars().next().unwrap_or_default();
+                let next = bytes.get(i + 1).copied();
+                match c {
+                    '-' if next == Some(b'-') => (bytes.len(), TokenKind::Comment),
+                    '#' if dialect == Dialect::MySql => (bytes.len(), TokenKind::Comment),
+                    '/' if next == Some(b'*') => {
+                        state = LexState::Comment(0);
+                        continue;
+                    }
+                    '\'' | '"' | '`' | '[' if opens_quote(c, dialect) => {
+                        let kind = match c {
+                            '\'' => TokenKind::String,
+                            '"' if dialect == Dialect::MySql => TokenKind::String,
+                            _ => TokenKind::Identifier,
+                        };
+                        let close = if c == '[' { b']' } else { c as u8 };
+                        let backslash_escapes = backslash_escapes(bytes, i, dialect);
+                        match quoted_end(bytes, i + 1, close, backslash_escapes) {
+                            Some(end) => (end, kind),
+                            None => {
+                                state = LexState::Quoted {
+                                    close,
+                                    backslash_escapes,
+                                    kind,
+                                };
+                                (bytes.len(), kind)
+                            }
+                        }
+                    }
+                    '$' if matches!(dialect, Dialect::Postgres | Dialect::DuckDb) => {
+                        match dollar_quote_tag(line, i) {
+                            Some(tag) => {
+                                let body = i + tag.len();
+                                match line[body..].find(tag) {
+                                    Some(found) => (body + found + tag.len(), TokenKind::String),
+                                    None => {
+                                        state = LexState::DollarQuoted(tag.to_string());
+                                        (bytes.len(), TokenKind::String)
+                                    }
+                                }
+                            }
+                            None => (word_end(line, i + 1), TokenKind::Parameter),
+                        }
+                    }
+                    '?' | '$' | '@' => (word_end(line, i + 1), TokenKind::Parameter),
+                    ':' if next == Some(b':') => {
+                        i += 2;
+                        continue;
+                    }
+                    ':' if next.is_some_and(|b| b.is_ascii_alphabetic() || b == b'_') => {
+                        (word_end(line, i + 1), TokenKind::Parameter)
+                    }
+                    '(' | ')' | '[' | ']' => (i + 1, TokenKind::Bracket),
+                    c if c.is_ascii_digit()
+                        || (c == '.' && next.is_some_and(|b| b.is_ascii_digit())) =>
+                    {
+                        (number_end(bytes, i), TokenKind::Number)
+                    }
+                    c if c.is_alphabetic() || c == '_' => {
+                        let end = word_end(line, i);
+                        let kind = match is_keyword(&line[i..end]) {
+                            true => TokenKind::Keyword,
+                            false => TokenKind::Identifier,
+                        };
+                        (end, kind)
+                    }
+                    c => {
+                        i += c.len_utf8().max(1);
+                        continue;
+                    }
+                }
+            }
+        };
+        if end > start {
+            tokens.push(Token {
+                range: start..end,
+                kind,
+            });
+        }
+        i = end.max(start + 1);
+    }
+    (tokens, state)
+}
+
+fn opens_quote(c: char, dialect: Dialect) -> bool {
+    c != '[' || dialect == Dialect::Sqlite
+}
+
+fn number_end(bytes: &[u8], start: usize) -> usize {
+    let mut i = start;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'e' | b'E' if matches!(bytes.get(i + 1), Some(b'+' | b'-')) => i += 2,
+            byte if byte.is_ascii_alphanumeric() || byte == b'.' || byte == b'_' => i += 1,
+            _ => break,
+        }
+    }
+    i
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds(lines: &[&str], dialect: Dialect) -> Vec<Vec<(String, TokenKind)>> {
+        let lines: Vec<String> = lines.iter().map(|line| line.to_string()).collect();
+        let mut highlighter = Highlighter::default();
+        (0..lines.len())
+            .map(|row| {
+                highlighter
+                    .line(&lines, row, dialect)
+                    .into_iter()
+                    .map(|token| (lines[row][token.range].to_string(), token.kind))
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn carries_strings_and_comments_across_lines() {
+        let lines = kinds(&["select 'a", "b' /* c", "d */ x"], Dialect::Sqlite);
+        assert_eq!(
+            lines[1],
+            [
+                ("b'".to_string(), TokenKind::String),
+                ("/* c".to_string(), TokenKind::Comment)
+            ]
+        );
+        assert_eq!(
+            lines[2],
+            [
+                ("d */".to_string(), TokenKind::Comment),
+                ("x".to_string(), TokenKind::Identifier)
+            ]
+        );
+    }
+
+    #[test]
+    fn escapes_backslashes_only_in_escape_strings() {
+        let postgres = kinds(&["select E'\\'', 'a\\' x"], Dialect::Postgres);
+        assert_eq!(postgres[0][1], ("E".to_string(), TokenKind::Identifier));
+        assert_eq!(postgres[0][2], ("'\\''".to_string(), TokenKind::String));
+        assert_eq!(postgres[0][3], ("'a\\'".to_string(), TokenKind::String));
+        let sqlite = kinds(&["select e'\\' x"], Dialect::Sqlite);
+        assert_eq!(sqlite[0][2], ("'\\'".to_string(), TokenKind::String));
+    }
+}
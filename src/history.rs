@@ -0,0 +1,113 @@
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePoolOptions};
+use sqlx::types::chrono::{DateTime, Utc};
+use sqlx::{Row, SqlitePool};
+use std::error::Error;
+use std::time::Duration;
+
+/// How many entries the history view lists at once.
+const SEARCH_LIMIT: i64 = 500;
+
+/// Every statement run, kept in a SQLite file under the data directory so
+/// that it outlives the session.
+#[derive(Clone, Debug)]
+pub struct History {
+    pool: SqlitePool,
+}
+
+#[derive(Clone, Debug)]
+pub struct HistoryEntry {
+    pub executed_at: DateTime<Utc>,
+    pub connection: String,
+    pub statement: String,
+    pub duration: Duration,
+    /// The rows returned or affected, unless it failed.
+    pub rows: Option<u64>,
+    pub error: Option<String>,
+}
+
+impl History {
+    /// Open the history file, usually `~/.local/share/squealer/history.db`,
+    /// creating it if needed.
+    pub async fn open() -> Result<History, Box<dyn Error>> {
+        let dir = dirs::data_dir()
+            .ok_or("no data directory")?
+            .join("squealer");
+        std::fs::create_dir_all(&dir)?;
+        let options = SqliteConnectOptions::new()
+            .filename(dir.join("history.db"))
+            .create_if_missing(true);
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect_with(options)
+            .await?;
+        sqlx::query(
+            "CREATE TABLE IF NOT EXISTS history (
+                id INTEGER PRIMARY KEY,
+                executed_at TEXT NOT NULL,
+                connection TEXT NOT NULL,
+                statement TEXT NOT NULL,
+                duration_ms INTEGER NOT NULL,
+                rows INTEGER,
+                error TEXT
+            )",
+        )
+        .execute(&pool)
+        .await?;
+        Ok(History { pool })
+    }
+
+    pub async fn record(&self, entries: &[HistoryEntry]) -> Result<(), Box<dyn Error>> {
+        let mut transaction = self.pool.begin().await?;
+        for entry in entries {
+            sqlx::query(
+                "INSERT INTO history (executed_at, connection, statement, duration_ms, rows, error)
+                VALUES (?, ?, ?, ?, ?, ?)",
+            )
+            .bind(entry.executed_at)
+            .bind(&entry.connection)
+            .bind(&entry.statement)
+            .bind(entry.duration.as_millis() as i64)
+            .bind(entry.rows.map(|rows| rows as i64))
+            .bind(&entry.error)
+            .execute(&mut *transaction)
+            .await?;
+        }
+        transaction.commit().await?;
+        Ok(())
+    }
+
+    /// The latest entries whose statement or connection contain `search`.
+    pub async fn search(&self, search: &str) -> Result<Vec<HistoryEntry>, Box<dyn Error>> {
+        let pattern = format!(
+            "%{}%",
+            search
+                .replace('\\', "\\\\")
+                .replace('%', "\\%")
+                .replace('_', "\\_")
+        );
+        let rows = sqlx::query(
+            "SELECT executed_at, connection, statement, duration_ms, rows, error FROM history
+            WHERE statement LIKE ?1 ESCAPE '\\' OR connection LIKE ?1 ESCAPE '\\'
+            ORDER BY id DESC LIMIT ?2",
+        )
+        .bind(pattern)
+        .bind(SEARCH_LIMIT)
+        .fetch_all(&self.pool)
+        .await?;
+        rows.iter()
+            .map(|row| {
+                Ok(HistoryEntry {
+                    executed_at: row.try_get("executed_at")?,
+                    connection: row.try_get("connection")?,
+                    statement: row.try_get("statement")?,
+                    duration: Duration::from_millis(row.try_get::<i64, _>("duration_ms")? as u64),
+                    rows: row
+                        .try_get::<Option<i64>, _>("rows")?
+                        .map(|rows| rows as u64),
+                    error: row.try_get("error")?,
+                })
+            })
+            .collect::<Result<_, sqlx::Error>>()
+            .map_err(Into::into)
+    }
+}
@@ -0,0 +1,730 @@
+use sqlx::types::chrono::{NaiveDate, NaiveDateTime};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use crate::database::{BoundStatement, Connection, Transaction, TransactionEnd};
+use crate::model::CellValue;
+use crate::schema::quote_name;
+use crate::script::Dialect;
+
+/// How many rows of a file are read to work out the type of its columns.
+const SAMPLE_ROWS: usize = 1000;
+/// How many rows of a file are shown before importing it.
+pub const PREVIEW_ROWS: usize = 50;
+/// How many failed rows are kept to report. The rest are only counted.
+const MAX_ROW_ERRORS: usize = 1000;
+/// The most rows inserted by one statement.
+const BATCH_ROWS: usize = 250;
+/// The most values bound to one statement, well under the limits of the
+/// backends.
+const BATCH_PARAMETERS: usize = 10_000;
+
+/// The type a column of a file looks like, going by the values in it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ColumnKind {
+    Integer,
+    Real,
+    Boolean,
+    Date,
+    Timestamp,
+    Text,
+}
+
+impl ColumnKind {
+    /// The narrowest kind every value given fits, or text if there are none.
+    fn infer<'a>(values: impl Iterator<Item = &'a str>) -> Self {
+        let mut candidates = vec![
+            ColumnKind::Integer,
+            ColumnKind::Real,
+            ColumnKind::Boolean,
+            ColumnKind::Date,
+            ColumnKind::Timestamp,
+        ];
+        let mut seen = false;
+        for value in values {
+            seen = true;
+            candidates.retain(|kind| kind.accepts(value));
+            if candidates.is_empty() {
+                break;
+            }
+        }
+        match seen {
+            true => candidates.first().copied().unwrap_or(ColumnKind::Text),
+            false => ColumnKind::Text,
+        }
+    }
+
+    fn accepts(self, value: &str) -> bool {
+        let value = value.trim();
+        match self {
+            ColumnKind::Integer => value.parse::<i64>().is_ok(),
+            ColumnKind::Real => value.parse::<f64>().is_ok_and(f64::is_finite),
+            ColumnKind::Boolean => parse_bool(value).is_some(),
+            ColumnKind::Date => NaiveDate::parse_from_str(value, "%Y-%m-%d").is_ok(),
+            ColumnKind::Timestamp => {
+                NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S%.f").is_ok()
+                    || NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M:%S%.f").is_ok()
+            }
+            ColumnKind::Text => true,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ColumnKind::Integer => "integer",
+            ColumnKind::Real => "real",
+            ColumnKind::Boolean => "boolean",
+            ColumnKind::Date => "date",
+            ColumnKind::Timestamp => "timestamp",
+            ColumnKind::Text => "text",
+        }
+    }
+
+    /// The column type a new table gets for this kind.
+    fn sql_type(self, dialect: Dialect) -> &'static str {
+        match (self, dialect) {
+            (ColumnKind::Integer, Dialect::Sqlite) => "INTEGER",
+            (ColumnKind::Integer, _) => "BIGINT",
+            (ColumnKind::Real, Dialect::Sqlite) => "REAL",
+            (ColumnKind::Real, Dialect::Postgres) => "DOUBLE PRECISION",
+            (ColumnKind::Real, _) => "DOUBLE",
+            (ColumnKind::Boolean, _) => "BOOLEAN",
+            (ColumnKind::Date, _) => "DATE",
+            (ColumnKind::Timestamp, Dialect::MySql) => "DATETIME",
+            (ColumnKind::Timestamp, _) => "TIMESTAMP",
+            (ColumnKind::Text, Dialect::DuckDb) => "VARCHAR",
+            (ColumnKind::Text, _) => "TEXT",
+        }
+    }
+
+    /// A field as the value bound for it. Anything that does not fit the
+    /// kind is bound as text and left to the database to take or refuse.
+    fn value(self, field: &Option<String>) -> CellValue {
+        let Some(text) = field else {
+            return CellValue::Null;
+        };
+        let trimmed = text.trim();
+        let value = match self {
+            ColumnKind::Integer => trimmed.parse().ok().map(CellValue::Integer),
+            ColumnKind::Real => trimmed.parse().ok().map(CellValue::Real),
+            ColumnKind::Boolean => parse_bool(trimmed).map(CellValue::Bool),
+            _ => None,
+        };
+        value.unwrap_or_else(|| CellValue::Text(text.clone()))
+    }
+}
+
+fn parse_bool(value: &str) -> Option<bool> {
+    match value.to_lowercase().as_str() {
+        "true" | "t" | "yes" => Some(true),
+        "false" | "f" | "no" => Some(false),
+        _ => None,
+    }
+}
+
+/// Reads the records of a delimited file. A field left empty is `None`, so
+/// NULL, while a quoted empty field is an empty string.
+pub struct CsvReader<R> {
+    input: R,
+    delimiter: char,
+    /// The line the next record starts on, counting from 1.
+    line: usize,
+    /// How far into the file the reader is.
+    pub bytes: u64,
+}
+
+impl CsvReader<BufReader<File>> {
+    pub fn open(path: &Path, delimiter: char) -> io::Result<Self> {
+        Ok(CsvReader::new(BufReader::new(File::open(path)?), delimiter))
+    }
+}
+
+impl<R: BufRead> CsvReader<R> {
+    pub fn new(input: R, delimiter: char) -> Self {
+        CsvReader {
+            input,
+            delimiter,
+            line: 1,
+            bytes: 0,
+        }
+    }
+
+    /// The next record and the line it starts on, skipping blank lines, or
+    /// `None` at the end of the file.
+    pub fn record(&mut self) -> io::Result<Option<(usize, Vec<Option<String>>)>> {
+        let mut text = String::new();
+        loop {
+            let start = self.line;
+            let Some(mut ending) = self.read_line(&mut text)? else {
+                return Ok(None);
+            };
+            if text.is_empty() {
+                continue;
+            }
+            let mut fields = Vec::new();
+            let mut field = String::new();
+            let (mut quoted, mut in_quotes) = (false, false);
+            loop {
+                let mut chars = text.chars().peekable();
+                while let Some(c) = chars.next() {
+                    match (in_quotes, c) {
+                        (true, '"') if chars.peek() == Some(&'"') => {
+                            chars.next();
+                            field.push('"');
+                        }
+                        (true, '"') => in_quotes = false,
+                        (true, c) => field.push(c),
+                        (false, c) if c == self.delimiter => {
+                            let field = std::mem::take(&mut field);
+                            fields.push((quoted || !field.is_empty()).then_some(field));
+                            quoted = false;
+                        }
+                        (false, '"') if field.is_empty() && !quoted => {
+                            (quoted, in_quotes) = (true, true);
+                        }
+                        (false, c) => field.push(c),
+                    }
+                }
+                // A quoted field carries on over line breaks, which are kept
+                // as they were.
+                if !in_quotes {
+                    break;
+                }
+                let previous = ending;
+                match self.read_line(&mut text)? {
+                    Some(next) => ending = next,
+                    None => break,
+                }
+                field.push_str(previous);
+            }
+            fields.push((quoted || !field.is_empty()).then_some(field));
+            return Ok(Some((start, fields)));
+        }
+    }
+
+    /// Read the next line into `text` without its line break, returning
+    /// the line break, or `None` at the end of the file.
+    fn read_line(&mut self, text: &mut String) -> io::Result<Option<&'static str>> {
+        let mut bytes = Vec::new();
+        let read = self.input.read_until(b'\n', &mut bytes)?;
+        if read == 0 {
+            return Ok(None);
+        }
+        self.bytes += read as u64;
+        self.line += 1;
+        let ending = match bytes.ends_with(b"\r\n") {
+            true => "\r\n",
+            false if bytes.ends_with(b"\n") => "\n",
+            false => "",
+        };
+        bytes.truncate(bytes.len() - ending.len());
+        *text = String::from_utf8(bytes)
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "the file is not UTF-8"))?;
+        Ok(Some(ending))
+    }
+}
+
+/// The start of a file, read to show it and work out its columns before
+/// importing it.
+pub struct CsvPreview {
+    pub path: PathBuf,
+    pub size: u64,
+    pub delimiter: char,
+    /// The names in the first row, made unique.
+    pub headers: Vec<String>,
+    pub kinds: Vec<ColumnKind>,
+    pub rows: Vec<Vec<Option<String>>>,
+}
+
+impl CsvPreview {
+    /// Read the first row for the names of the columns and the rows after
+    /// it for their types. The delimiter is whichever of a comma, semicolon,
+    /// tab or bar splits the first row into the most fields, preferring them
+    /// in that order.
+    pub fn read(path: &Path) -> io::Result<Self> {
+        let mut delimiter = ',';
+        let mut most = 1;
+        for candidate in [',', ';', '\t', '|'] {
+            let fields = CsvReader::open(path, candidate)?
+                .record()?
+                .map_or(0, |(_, fields)| fields.len());
+            if fields > most {
+                (delimiter, most) = (candidate, fields);
+            }
+        }
+        let mut reader = CsvReader::open(path, delimiter)?;
+        let Some((_, names)) = reader.record()? else {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "the file is empty",
+            ));
+        };
+        let mut headers: Vec<String> = Vec::new();
+        for (index, name) in names.into_iter().enumerate() {
+            let name = name.unwrap_or_default();
+            let name = name.trim_start_matches('\u{feff}').trim();
+            let mut name = match name.is_empty() {
+                true => format!("column{}", index + 1),
+                false => name.to_string(),
+            };
+            let base = name.clone();
+            let mut suffix = 1;
+            while headers
+                .iter()
+                .any(|header| header.eq_ignore_ascii_case(&name))
+            {
+                suffix += 1;
+                name = format!("{}_{}", base, suffix);
+            }
+            headers.push(name);
+        }
+        let mut sample = Vec::new();
+        while sample.len() < SAMPLE_ROWS {
+            match reader.record()? {
+                Some((_, fields)) => sample.push(fields),
+                None => break,
+            }
+        }
+        let kinds = (0..headers.len())
+            .map(|column| {
+                ColumnKind::infer(
+                    sample
+                        .iter()
+                        .filter_map(|row| row.get(column)?.as_deref())
+                        .filter(|value| !value.is_empty()),
+                )
+            })
+            .collect();
+        sample.truncate(PREVIEW_ROWS);
+        Ok(CsvPreview {
+            path: path.to_path_buf(),
+            size: std::fs::metadata(path)?.len(),
+            delimiter,
+            headers,
+            kinds,
+            rows: sample,
+        })
+    }
+
+    /// A statement creating a table with a column for each of the file's,
+    /// of the type it looks like.
+    pub fn create_table(&self, table: &str, dialect: Dialect) -> String {
+        let columns = self
+            .headers
+            .iter()
+            .zip(&self.kinds)
+            .map(|(name, kind)| format!("{} {}", quote_name(name, dialect), kind.sql_type(dialect)))
+            .collect::<Vec<_>>();
+        format!(
+            "CREATE TABLE {} (\n  {}\n)",
+            quote_name(table, dialect),
+            columns.join(",\n  ")
+        )
+    }
+}
+
+/// What to import and where to.
+pub struct ImportPlan {
+    pub path: PathBuf,
+    pub delimiter: char,
+    /// The table as statements name it, quoted.
+    pub table: String,
+    /// The statement creating the table, when it is a new one.
+    pub create: Option<String>,
+    /// How many fields each row should have.
+    pub width: usize,
+    /// The fields that are imported, by position, with the kind each looks
+    /// like and the column of the table it goes into.
+    pub columns: Vec<(usize, ColumnKind, String)>,
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ImportProgress {
+    pub inserted: u64,
+    pub failed: u64,
+    /// How far into the file the rows done so far go.
+    pub bytes: u64,
+}
+
+/// A row that could not be inserted and why.
+#[derive(Clone, Debug)]
+pub struct RowError {
+    pub line: usize,
+    pub error: String,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct ImportReport {
+    pub inserted: u64,
+    pub failed: u64,
+    /// The first rows that failed, in the order they came.
+    pub errors: Vec<RowError>,
+}
+
+/// Insert the rows of a file into a table in one transaction, creating the
+/// table first if it is new. Rows are inserted many to a statement, and a
+/// statement the database refuses is tried again a row at a time, so the
+/// rows it refuses are reported and left out and the rest are committed.
+/// Postgres abandons a transaction after an error, so there each statement
+/// runs under a savepoint; DuckDB cannot roll back to one, so there the
+/// first error rolls everything back, as does setting `cancelled`. MySQL
+/// commits the transaction a table is created in, so there the table is
+/// created before it begins, and is left behind if the import fails.
+pub async fn import(
+    connection: Connection,
+    mut plan: ImportPlan,
+    cancelled: Arc<AtomicBool>,
+    mut progress: impl FnMut(ImportProgress) + Send,
+) -> Result<ImportReport, String> {
+    if connection.dialect() == Dialect::MySql {
+        if let Some(create) = plan.create.take() {
+            let statement = BoundStatement {
+                sql: create,
+                parameters: Vec::new(),
+            };
+            let created = connection.fetch_bound(None, &statement).await;
+            created.map_err(|e| e.to_string())?;
+        }
+    }
+    let transaction = connection.begin().await.map_err(|e| e.to_string())?;
+    let inserted = insert_rows(&connection, &transaction, plan, &cancelled, &mut progress).await;
+    match inserted {
+        Ok(report) => {
+            let end = transaction.end(TransactionEnd::Commit).await;
+            end.map_err(|e| e.to_string())?;
+            Ok(report)
+        }
+        Err(error) => {
+            let _ = transaction.end(TransactionEnd::Rollback).await;
+            Err(error)
+        }
+    }
+}
+
+async fn insert_rows(
+    connection: &Connection,
+    transaction: &Transaction,
+    plan: ImportPlan,
+    cancelled: &AtomicBool,
+    progress: &mut (impl FnMut(ImportProgress) + Send),
+) -> Result<ImportReport, String> {
+    let dialect = connection.dialect();
+    if let Some(create) = plan.create {
+        run(connection, transaction, &create).await?;
+    }
+    let columns = plan
+        .columns
+        .iter()
+        .map(|(_, _, column)| quote_name(column, dialect))
+        .collect::<Vec<_>>();
+    let insert = Insert {
+        into: format!("INSERT INTO {} ({})", plan.table, columns.join(", ")),
+        width: columns.len(),
+        dialect,
+    };
+    let batch_rows = (BATCH_PARAMETERS / columns.len().max(1)).clamp(1, BATCH_ROWS);
+    let (records, mut received) = tokio::sync::mpsc::channel(512);
+    let (path, delimiter) = (plan.path, plan.delimiter);
+    let reader = tokio::task::spawn_blocking(move || -> io::Result<()> {
+        let mut reader = CsvReader::open(&path, delimiter)?;
+        reader.record()?;
+        while let Some(record) = reader.record()? {
+            if records.blocking_send((record, reader.bytes)).is_err() {
+                break;
+            }
+        }
+        Ok(())
+    });
+    let mut report = ImportReport::default();
+    let mut batch = Vec::with_capacity(batch_rows);
+    let mut bytes = 0;
+    loop {
+        let record = received.recv().await;
+        if cancelled.load(Ordering::Relaxed) {
+            return Err("cancelled".to_string());
+        }
+        let finished = record.is_none();
+        if let Some(((line, fields), read)) = record {
+            bytes = read;
+            match fields.len() == plan.width {
+                true => {
+                    let values = plan
+                        .columns
+                        .iter()
+                        .map(|(index, kind, _)| kind.value(&fields[*index]))
+                        .collect::<Vec<_>>();
+                    batch.push((line, values));
+                }
+                false => {
+                    let error =
+                        format!("expected {} fields but found {}", plan.width, fields.len());
+                    report.refused(line, error, dialect)?;
+                }
+            }
+            if batch.len() < batch_rows {
+                continue;
+            }
+        }
+        if !batch.is_empty() {
+            let rows = std::mem::replace(&mut batch, Vec::with_capacity(batch_rows));
+            insert
+                .rows(connection, transaction, rows, &mut report)
+                .await?;
+            progress(ImportProgress {
+                inserted: report.inserted,
+                failed: report.failed,
+                bytes,
+            });
+        }
+        if finished {
+            break;
+        }
+    }
+    match reader.await {
+        Ok(read) => read.map_err(|e| e.to_string())?,
+        Err(error) => return Err(error.to_string()),
+    }
+    // Rows with too few fields are refused before the batch they came in.
+    report.errors.sort_by_key(|error| error.line);
+    Ok(report)
+}
+
+impl ImportReport {
+    /// Count a row the database refused, which ends the import on DuckDB.
+    fn refused(&mut self, line: usize, error: String, dialect: Dialect) -> Result<(), String> {
+        if dialect == Dialect::DuckDb {
+            return Err(format!("line {}: {}", line, error));
+        }
+        self.failed += 1;
+        if self.errors.len() < MAX_ROW_ERRORS {
+            self.errors.push(RowError { line, error });
+        }
+        Ok(())
+    }
+}
+
+/// The start of the statement inserting rows into the table, and how many
+/// values each row has.
+struct Insert {
+    into: String,
+    width: usize,
+    dialect: Dialect,
+}
+
+impl Insert {
+    /// Insert rows, each with the line of the file it is from, in one
+    /// statement, or else one at a time to find those that are refused.
+    async fn rows(
+        &self,
+        connection: &Connection,
+        transaction: &Transaction,
+        rows: Vec<(usize, Vec<CellValue>)>,
+        report: &mut ImportReport,
+    ) -> Result<(), String> {
+        if rows.len() > 1 {
+            let statement = self.statement(rows.iter().map(|(_, values)| values));
+            match self.run(connection, transaction, &statement).await {
+                Ok(()) => {
+                    report.inserted += rows.len() as u64;
+                    return Ok(());
+                }
+                // DuckDB cannot carry on after a failed statement to find the
+                // row that failed.
+                Err(error) if self.dialect == Dialect::DuckDb => {
+                    let (first, last) = (rows[0].0, rows[rows.len() - 1].0);
+                    return Err(format!("lines {} to {}: {}", first, last, error));
+                }
+                Err(_) => {}
+            }
+        }
+        for (line, values) in rows {
+            let statement = self.statement([&values]);
+            match self.run(connection, transaction, &statement).await {
+                Ok(()) => report.inserted += 1,
+                Err(error) => report.refused(line, error, self.dialect)?,
+            }
+        }
+        Ok(())
+    }
+
+    /// An `INSERT` of the rows, with a placeholder for each of their values.
+    fn statement<'a>(&self, rows: impl IntoIterator<Item = &'a Vec<CellValue>>) -> BoundStatement {
+        let mut sql = format!("{} VALUES ", self.into);
+        let mut parameters = Vec::new();
+        for (row, values) in rows.into_iter().enumerate() {
+            let placeholders = (1..=self.width).map(|column| match self.dialect {
+                Dialect::Postgres => format!("${}", row * self.width + column),
+                _ => "?".to_string(),
+            });
+            let separator = if row == 0 { "" } else { ", " };
+            let placeholders = placeholders.collect::<Vec<_>>().join(", ");
+            sql.push_str(&format!("{}({})", separator, placeholders));
+            parameters.extend(values.iter().cloned());
+        }
+        BoundStatement { sql, parameters }
+    }
+
+    /// Run an insert, under a savepoint on Postgres so that a refused row
+    /// does not abandon the transaction.
+    async fn run(
+        &self,
+        connection: &Connection,
+        transaction: &Transaction,
+        statement: &BoundStatement,
+    ) -> Result<(), String> {
+        let savepoint = self.dialect == Dialect::Postgres;
+        if savepoint {
+            run(connection, transaction, "SAVEPOINT import_rows").await?;
+        }
+        let result = connection
+            .fetch_bound(Some(transaction), statement)
+            .await
+            .map(|_| ())
+            .map_err(|e| e.to_string());
+        if savepoint {
+            let end = match result {
+                Ok(()) => "RELEASE SAVEPOINT import_rows",
+                Err(_) => "ROLLBACK TO SAVEPOINT import_rows",
+            };
+            run(connection, transaction, end).await?;
+        }
+        result
+    }
+}
+
+async fn run(connection: &Connection, transaction: &Transaction, sql: &str) -> Result<(), String> {
+    let statement = BoundStatement {
+        sql: sql.to_string(),
+        parameters: Vec::new(),
+    };
+    match connection.fetch_bound(Some(transaction), &statement).await {
+        Ok(_) => Ok(()),
+        Err(error) => Err(error.to_string()),
+    }
+}
+
+/// A name for a new table made from a file's name, such as `sales_2024` for
+/// `Sales 2024.csv`.
+pub fn table_name(path: &Path) -> String {
+    let stem = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    let name = stem
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c } else { '_' })
+        .collect::<String>();
+    let name = name.trim_matches('_');
+    match name.chars().next() {
+        None => "imported".to_string(),
+        Some(c) if c.is_numeric() => format!("t_{}", name),
+        Some(_) => name.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn records(text: &str, delimiter: char) -> Vec<(usize, Vec<Option<String>>)> {
+        let mut reader = CsvReader::new(text.as_bytes(), delimiter);
+        let mut records = Vec::new();
+        while let Some(record) = reader.record().unwrap() {
+            records.push(record);
+        }
+        records
+    }
+
+    fn fields(values: &[Option<&str>]) -> Vec<Option<String>> {
+        values
+            .iter()
+            .map(|value| value.map(str::to_string))
+            .collect()
+    }
+
+    fn preview(name: &str, text: &str) -> CsvPreview {
+        let path =
+            std::env::temp_dir().join(format!("squealer-{}-{}.csv", name, std::process::id()));
+        std::fs::write(&path, text).unwrap();
+        let preview = CsvPreview::read(&path);
+        let _ = std::fs::remove_file(&path);
+        preview.unwrap()
+    }
+
+    #[test]
+    fn reads_quoted_delimiters_and_doubled_quotes() {
+        let read = records("\"a,b\",c\n\"say \"\"hi\"\"\",d\n", ',');
+        assert_eq!(read[0].1, fields(&[Some("a,b"), Some("c")]));
+        assert_eq!(read[1].1, fields(&[Some("say \"hi\""), Some("d")]));
+    }
+
+    #[test]
+    fn leaves_empty_fields_null_unless_quoted() {
+        let read = records("a,,\"\"\n,x,\n", ',');
+        assert_eq!(read[0].1, fields(&[Some("a"), None, Some("")]));
+        assert_eq!(read[1].1, fields(&[None, Some("x"), None]));
+    }
+
+    #[test]
+    fn keeps_line_breaks_inside_quotes_as_they_were() {
+        let read = records("\"x\r\ny\",z\r\n\r\n\"p\nq\"\nlast", ',');
+        assert_eq!(read[0], (1, fields(&[Some("x\r\ny"), Some("z")])));
+        assert_eq!(read[1], (4, fields(&[Some("p\nq")])));
+        assert_eq!(read[2], (6, fields(&[Some("last")])));
+    }
+
+    #[test]
+    fn names_blank_and_repeated_headers() {
+        let preview = preview("headers", "\u{feff}id,Name,,name\n1,a,b,c\n");
+        assert_eq!(preview.headers, ["id", "Name", "column3", "name_2"]);
+    }
+
+    #[test]
+    fn picks_the_delimiter_outside_quotes() {
+        assert_eq!(preview("quoted", "\"a,b\";\"c\"\n1;2\n").delimiter, ';');
+        assert_eq!(preview("tie", "a,b;c\n").delimiter, ',');
+        assert_eq!(preview("bar", "a|b|c\n").delimiter, '|');
+        assert_eq!(preview("single", "a\n1\n").delimiter, ',');
+    }
+
+    #[test]
+    fn infers_the_narrowest_kind() {
+        let infer = |values: &[&str]| ColumnKind::infer(values.iter().copied());
+        assert_eq!(infer(&["1", "-2"]), ColumnKind::Integer);
+        assert_eq!(infer(&["1", "2.5"]), ColumnKind::Real);
+        assert_eq!(infer(&["true", "No"]), ColumnKind::Boolean);
+        assert_eq!(infer(&["2024-01-02"]), ColumnKind::Date);
+        assert_eq!(
+            infer(&["2024-01-02 03:04:05", "2024-01-02T03:04:05.5"]),
+            ColumnKind::Timestamp
+        );
+        assert_eq!(infer(&["1", "x"]), ColumnKind::Text);
+        assert_eq!(infer(&[]), ColumnKind::Text);
+        let preview = preview("kinds", "a,b\n,x\n1,\n");
+        assert_eq!(preview.kinds, [ColumnKind::Integer, ColumnKind::Text]);
+    }
+
+    #[test]
+    fn numbers_the_placeholders_of_every_row() {
+        let insert = |dialect| Insert {
+            into: "INSERT INTO t (a, b)".to_string(),
+            width: 2,
+            dialect,
+        };
+        let rows = [
+            vec![CellValue::Integer(1), CellValue::Null],
+            vec![CellValue::Integer(2), CellValue::Text("x".to_string())],
+        ];
+        let statement = insert(Dialect::Postgres).statement(&rows);
+        assert_eq!(
+            statement.sql,
+            "INSERT INTO t (a, b) VALUES ($1, $2), ($3, $4)"
+        );
+        assert_eq!(statement.parameters.len(), 4);
+        let statement = insert(Dialect::MySql).statement(&rows[..1]);
+        assert_eq!(statement.sql, "INSERT INTO t (a, b) VALUES (?, ?)");
+    }
+}
@@ -0,0 +1,580 @@
+use crate::script::Dialect;
+
+/// Where the line a `--` or `#` comment starts on ends, after its newline.
+pub fn line_end(bytes: &[u8], start: usize) -> usize {
+    find(bytes, start, b"\n").map_or(bytes.len(), |end| end + 1)
+}
+
+/// Where a block comment ends, or the text if it does not, and how deeply
+/// it is still nested. Only Postgres nests them.
+pub fn block_comment_end(
+    bytes: &[u8],
+    start: usize,
+    mut depth: usize,
+    dialect: Dialect,
+) -> (usize, usize) {
+    let mut i = start;
+    while i + 1 < bytes.len() {
+        match (bytes[i], bytes[i + 1]) {
+            (b'/', b'*') if depth == 0 || dialect == Dialect::Postgres => {
+                depth += 1;
+                i += 2;
+            }
+            (b'*', b'/') => {
+                depth = depth.saturating_sub(1);
+                i += 2;
+                if depth == 0 {
+                    return (i, 0);
+                }
+            }
+            _ => i += 1,
+        }
+    }
+    (bytes.len(), depth)
+}
+
+/// Where a string or quoted name that is open at `start` ends, just past
+/// its closing quote, or `None` if the text ends first. A doubled quote is
+/// an escaped quote.
+pub fn quoted_end(bytes: &[u8], start: usize, quote: u8, backslash_escapes: bool) -> Option<usize> {
+    let mut i = start;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' if backslash_escapes => i += 2,
+            byte if byte == quote && bytes.get(i + 1) == Some(&quote) => i += 2,
+            byte if byte == quote => return Some(i + 1),
+            _ => i += 1,
+        }
+    }
+    None
+}
+
+/// Whether backslashes escape in the string whose quote is at `start`, as
+/// they do in MySQL's strings and in the `E'...'` strings of Postgres and
+/// DuckDB.
+pub fn backslash_escapes(bytes: &[u8], start: usize, dialect: Dialect) -> bool {
+    match (dialect, bytes[start]) {
+        (Dialect::MySql, b'\'' | b'"') => true,
+        (Dialect::Postgres | Dialect::DuckDb, b'\'') => {
+            match start.checked_sub(1).map(|i| bytes[i]) {
+                Some(b'E' | b'e') => start < 2 || !is_word_byte(bytes[start - 2]),
+                _ => false,
+            }
+        }
+        _ => false,
+    }
+}
+
+/// The `$tag$` opening a dollar quoted string at `start`, if there is one.
+/// `$1` style parameters are not dollar quotes.
+pub fn dollar_quote_tag(text: &str, start: usize) -> Option<&str> {
+    let rest = &text[start + 1..];
+    if rest.starts_with(|c: char| c.is_numeric()) {
+        return None;
+    }
+    let len = rest
+        .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+        .unwrap_or(rest.len());
+    rest[len..]
+        .starts_with('$')
+        .then(|| &text[start..start + len + 2])
+}
+
+/// Where the word starting at `start` ends.
+pub fn word_end(text: &str, start: usize) -> usize {
+    text[start..]
+        .find(|c: char| !is_word_char(c))
+        .map_or(text.len(), |end| start + end)
+}
+
+/// Whether a character can be part of a name, which in every dialect can
+/// have a `$` after its first character.
+pub fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '$'
+}
+
+pub fn is_word_byte(byte: u8) -> bool {
+    byte.is_ascii_alphanumeric() || byte == b'_'
+}
+
+pub fn find(bytes: &[u8], start: usize, needle: &[u8]) -> Option<usize> {
+    bytes
+        .get(start..)?
+        .windows(needle.len())
+        .position(|window| window == needle)
+        .map(|position| start + position)
+}
+
+#[derive(PartialEq, Debug)]
+pub enum Token {
+    /// A name or keyword as it was written.
+    Word(String),
+    /// A quoted name, with its quotes taken off.
+    Quoted(String),
+    Punct(char),
+}
+
+/// Break a statement into words, quoted names and punctuation, leaving out
+/// strings, numbers and comments.
+pub fn tokenize(text: &str, dialect: Dialect) -> Vec<Token> {
+    let bytes = text.as_bytes();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        let c = text[i..].chars().next().unwrap_or_default();
+        let next = bytes.get(i + 1).copied();
+        i = match c {
+            '-' if next == Some(b'-') => line_end(bytes, i),
+            '#' if dialect == Dialect::MySql => line_end(bytes, i),
+            '/' if next == Some(b'*') => block_comment_end(bytes, i, 0, dialect).0,
+            '\'' | '"' if c == '\'' || dialect == Dialect::MySql => {
+                let backslash_escapes = backslash_escapes(bytes, i, dialect);
+                quoted_end(bytes, i + 1, c as u8, backslash_escapes).unwrap_or(bytes.len())
+            }
+            '"' | '`' | '[' if c != '[' || dialect == Dialect::Sqlite => {
+                let close = if c == '[' { ']' } else { c };
+                let end = quoted_end(bytes, i + 1, close as u8, false);
+                let name = &text[i + 1..end.map_or(bytes.len(), |end| end - 1)];
+                let doubled = format!("{}{}", close, close);
+                tokens.push(Token::Quoted(name.replace(&doubled, &close.to_string())));
+                end.unwrap_or(bytes.len())
+            }
+            '$' if matches!(dialect, Dialect::Postgres | Dialect::DuckDb)
+                && dollar_quote_tag(text, i).is_some() =>
+            {
+                let tag = dollar_quote_tag(text, i).unwrap_or_default();
+                find(bytes, i + tag.len(), tag.as_bytes())
+                    .map_or(bytes.len(), |end| end + tag.len())
+            }
+            c if c.is_alphabetic() || c == '_' => {
+                let end = word_end(text, i);
+                tokens.push(Token::Word(text[i..end].to_string()));
+                end
+            }
+            c if c.is_numeric() => word_end(text, i),
+            c if c.is_whitespace() => i + c.len_utf8(),
+            c => {
+                tokens.push(Token::Punct(c));
+                i + c.len_utf8()
+            }
+        };
+    }
+    tokens
+}
+
+/// Whether a dialect reserves a word, so that it cannot be a name unless
+/// it is quoted. Keywords like `key` and `value` that are only reserved in
+/// some places are names.
+pub fn is_reserved(word: &str, dialect: Dialect) -> bool {
+    let reserved = |words: &[&str]| words.iter().any(|w| w.eq_ignore_ascii_case(word));
+    reserved(RESERVED)
+        || match dialect {
+            Dialect::Sqlite => reserved(SQLITE_RESERVED),
+            Dialect::Postgres | Dialect::DuckDb => reserved(POSTGRES_RESERVED),
+            Dialect::MySql => reserved(MYSQL_RESERVED),
+        }
+}
+
+/// The words every dialect reserves.
+const RESERVED: &[&str] = &[
+    "ALL",
+    "AND",
+    "AS",
+    "ASC",
+    "BETWEEN",
+    "BY",
+    "CASE",
+    "CHECK",
+    "CONSTRAINT",
+    "CREATE",
+    "CROSS",
+    "DEFAULT",
+    "DESC",
+    "DISTINCT",
+    "ELSE",
+    "EXCEPT",
+    "EXISTS",
+    "FALSE",
+    "FOREIGN",
+    "FROM",
+    "FULL",
+    "GROUP",
+    "HAVING",
+    "IN",
+    "INNER",
+    "INTERSECT",
+    "INTO",
+    "IS",
+    "JOIN",
+    "LEFT",
+    "LIKE",
+    "LIMIT",
+    "NATURAL",
+    "NOT",
+    "NULL",
+    "ON",
+    "OR",
+    "ORDER",
+    "OUTER",
+    "PRIMARY",
+    "REFERENCES",
+    "RIGHT",
+    "SELECT",
+    "TABLE",
+    "THEN",
+    "TO",
+    "TRUE",
+    "UNION",
+    "UNIQUE",
+    "USING",
+    "VALUES",
+    "WHEN",
+    "WHERE",
+    "WINDOW",
+    "WITH",
+];
+
+const SQLITE_RESERVED: &[&str] = &[
+    "ADD",
+    "ALTER",
+    "AUTOINCREMENT",
+    "COLLATE",
+    "COMMIT",
+    "DEFERRABLE",
+    "DELETE",
+    "DROP",
+    "ESCAPE",
+    "INDEX",
+    "INSERT",
+    "ISNULL",
+    "NOTHING",
+    "NOTNULL",
+    "OFFSET",
+    "RETURNING",
+    "SET",
+    "TRANSACTION",
+    "UPDATE",
+];
+
+/// Postgres' reserved words, which DuckDB shares.
+const POSTGRES_RESERVED: &[&str] = &[
+    "ANALYSE",
+    "ANALYZE",
+    "ANY",
+    "ARRAY",
+    "ASYMMETRIC",
+    "BINARY",
+    "BOTH",
+    "CAST",
+    "COLLATE",
+    "COLUMN",
+    "CURRENT_CATALOG",
+    "CURRENT_DATE",
+    "CURRENT_ROLE",
+    "CURRENT_TIME",
+    "CURRENT_TIMESTAMP",
+    "CURRENT_USER",
+    "DEFERRABLE",
+    "DO",
+    "END",
+    "FETCH",
+    "FOR",
+    "FREEZE",
+    "GRANT",
+    "ILIKE",
+    "INITIALLY",
+    "ISNULL",
+    "LATERAL",
+    "LEADING",
+    "LOCALTIME",
+    "LOCALTIMESTAMP",
+    "NOTNULL",
+    "OFFSET",
+    "ONLY",
+    "OVERLAPS",
+    "PLACING",
+    "RETURNING",
+    "SESSION_USER",
+    "SIMILAR",
+    "SOME",
+    "SYMMETRIC",
+    "TABLESAMPLE",
+    "TRAILING",
+    "USER",
+    "VARIADIC",
+    "VERBOSE",
+];
+
+const MYSQL_RESERVED: &[&str] = &[
+    "ADD",
+    "ALTER",
+    "ANALYZE",
+    "BEFORE",
+    "BOTH",
+    "CALL",
+    "CASCADE",
+    "CHANGE",
+    "COLLATE",
+    "COLUMN",
+    "CONDITION",
+    "CONVERT",
+    "CURRENT_DATE",
+    "CURRENT_TIME",
+    "CURRENT_TIMESTAMP",
+    "CURRENT_USER",
+    "DATABASE",
+    "DELETE",
+    "DESCRIBE",
+    "DIV",
+    "DROP",
+    "DUAL",
+    "EACH",
+    "ELSEIF",
+    "ESCAPED",
+    "EXPLAIN",
+    "FETCH",
+    "FOR",
+    "FORCE",
+    "GRANT",
+    "IF",
+    "IGNORE",
+    "INDEX",
+    "INSERT",
+    "INTERVAL",
+    "KEY",
+    "KEYS",
+    "KILL",
+    "LEADING",
+    "LINES",
+    "LOAD",
+    "LOCK",
+    "MATCH",
+    "MOD",
+    "OPTIMIZE",
+    "OPTION",
+    "OVER",
+    "PARTITION",
+    "PROCEDURE",
+    "RANGE",
+    "RANK",
+    "READ",
+    "REGEXP",
+    "RENAME",
+    "REPEAT",
+    "REPLACE",
+    "REQUIRE",
+    "RESTRICT",
+    "REVOKE",
+    "RLIKE",
+    "ROW",
+    "ROWS",
+    "SCHEMA",
+    "SEPARATOR",
+    "SET",
+    "SHOW",
+    "SQL",
+    "STRAIGHT_JOIN",
+    "TERMINATED",
+    "TRAILING",
+    "TRIGGER",
+    "UNLOCK",
+    "UPDATE",
+    "USAGE",
+    "USE",
+    "WHILE",
+    "WRITE",
+    "XOR",
+];
+
+/// The keywords of the dialects, in order so they can be searched.
+pub const KEYWORDS: &[&str] = &[
+    "ABORT",
+    "ACTION",
+    "ADD",
+    "AFTER",
+    "ALL",
+    "ALTER",
+    "ANALYZE",
+    "AND",
+    "ANY",
+    "ARRAY",
+    "AS",
+    "ASC",
+    "ATTACH",
+    "AUTOINCREMENT",
+    "AUTO_INCREMENT",
+    "BEFORE",
+    "BEGIN",
+    "BETWEEN",
+    "BIGINT",
+    "BLOB",
+    "BOOLEAN",
+    "BOTH",
+    "BY",
+    "CASCADE",
+    "CASE",
+    "CAST",
+    "CHAR",
+    "CHECK",
+    "COLLATE",
+    "COLUMN",
+    "COMMIT",
+    "CONFLICT",
+    "CONSTRAINT",
+    "COPY",
+    "CREATE",
+    "CROSS",
+    "CURRENT",
+    "CURRENT_DATE",
+    "CURRENT_TIME",
+    "CURRENT_TIMESTAMP",
+    "DATABASE",
+    "DATE",
+    "DECIMAL",
+    "DECLARE",
+    "DEFAULT",
+    "DEFERRABLE",
+    "DEFERRED",
+    "DELETE",
+    "DESC",
+    "DETACH",
+    "DISTINCT",
+    "DO",
+    "DOUBLE",
+    "DROP",
+    "EACH",
+    "ELSE",
+    "END",
+    "ESCAPE",
+    "EXCEPT",
+    "EXCLUSIVE",
+    "EXECUTE",
+    "EXISTS",
+    "EXPLAIN",
+    "EXTENSION",
+    "FALSE",
+    "FETCH",
+    "FILTER",
+    "FIRST",
+    "FLOAT",
+    "FOLLOWING",
+    "FOR",
+    "FOREIGN",
+    "FROM",
+    "FULL",
+    "FUNCTION",
+    "GLOB",
+    "GRANT",
+    "GROUP",
+    "GROUPS",
+    "HAVING",
+    "IF",
+    "IGNORE",
+    "ILIKE",
+    "IMMEDIATE",
+    "IN",
+    "INDEX",
+    "INNER",
+    "INSERT",
+    "INSTEAD",
+    "INT",
+    "INTEGER",
+    "INTERSECT",
+    "INTERVAL",
+    "INTO",
+    "IS",
+    "ISNULL",
+    "JOIN",
+    "JSON",
+    "JSONB",
+    "KEY",
+    "LANGUAGE",
+    "LAST",
+    "LATERAL",
+    "LEFT",
+    "LIKE",
+    "LIMIT",
+    "MATCH",
+    "MATERIALIZED",
+    "NATURAL",
+    "NO",
+    "NOT",
+    "NOTHING",
+    "NOTNULL",
+    "NULL",
+    "NULLS",
+    "NUMERIC",
+    "OF",
+    "OFFSET",
+    "ON",
+    "ONLY",
+    "OR",
+    "ORDER",
+    "OTHERS",
+    "OUTER",
+    "OVER",
+    "PARTITION",
+    "PRAGMA",
+    "PRECEDING",
+    "PRIMARY",
+    "PROCEDURE",
+    "RANGE",
+    "REAL",
+    "RECURSIVE",
+    "REFERENCES",
+    "REGEXP",
+    "REINDEX",
+    "RELEASE",
+    "RENAME",
+    "REPLACE",
+    "RESTRICT",
+    "RETURNING",
+    "RETURNS",
+    "REVOKE",
+    "RIGHT",
+    "ROLLBACK",
+    "ROW",
+    "ROWS",
+    "SAVEPOINT",
+    "SCHEMA",
+    "SELECT",
+    "SEQUENCE",
+    "SET",
+    "SHOW",
+    "SMALLINT",
+    "TABLE",
+    "TEMP",
+    "TEMPORARY",
+    "TEXT",
+    "THEN",
+    "TIES",
+    "TIME",
+    "TIMESTAMP",
+    "TO",
+    "TRANSACTION",
+    "TRIGGER",
+    "TRUE",
+    "TRUNCATE",
+    "UNBOUNDED",
+    "UNION",
+    "UNIQUE",
+    "UPDATE",
+    "USING",
+    "UUID",
+    "VACUUM",
+    "VALUES",
+    "VARCHAR",
+    "VIEW",
+    "VIRTUAL",
+    "WHEN",
+    "WHERE",
+    "WINDOW",
+    "WITH",
+    "WITHOUT",
+];
+
+pub fn is_keyword(word: &str) -> bool {
+    KEYWORDS
+        .binary_search(&word.to_ascii_uppercase().as_str())
+        .is_ok()
+}
@@ -0,0 +1,86 @@
+use std::error::Error;
+use std::path::{Path, PathBuf};
+
+/// A query kept as a `.sql` file in the library directory. Comments at the
+/// top of the file like `-- description: ...` describe it:
+///
+/// ```sql
+/// -- name: Active users
+/// -- description: Everyone who logged in this week
+/// -- profile: warehouse
+/// -- parameters: since, region
+/// SELECT * FROM users WHERE last_login > :since AND region = :region;
+/// ```
+#[derive(Clone, Debug)]
+pub struct SavedQuery {
+    /// The `name` given in the header, or the file name without `.sql`.
+    pub name: String,
+    pub path: PathBuf,
+    pub description: Option<String>,
+    /// The profile the query is meant to be run against.
+    pub profile: Option<String>,
+    pub parameters: Vec<String>,
+    pub sql: String,
+}
+
+/// Read every `.sql` file in the library directory, sorted by name. A
+/// directory that does not exist is an empty library.
+pub fn load_library(dir: &Path) -> Result<Vec<SavedQuery>, Box<dyn Error>> {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(error) => return Err(format!("could not read {}: {}", dir.display(), error).into()),
+    };
+    let mut queries = Vec::new();
+    for entry in entries {
+        let path = entry?.path();
+        if path.extension().and_then(|extension| extension.to_str()) != Some("sql") {
+            continue;
+        }
+        let sql = std::fs::read_to_string(&path)
+            .map_err(|error| format!("could not read {}: {}", path.display(), error))?;
+        queries.push(parse_saved_query(path, sql));
+    }
+    queries.sort_by_key(|query| query.name.to_lowercase());
+    Ok(queries)
+}
+
+fn parse_saved_query(path: PathBuf, sql: String) -> SavedQuery {
+    let stem = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let mut query = SavedQuery {
+        name: stem,
+        path,
+        description: None,
+        profile: None,
+        parameters: Vec::new(),
+        sql: String::new(),
+    };
+    let mut lines = sql.lines().peekable();
+    while let Some(comment) = lines.peek().and_then(|line| line.trim().strip_prefix("--")) {
+        if let Some((key, value)) = comment.split_once(':') {
+            let value = value.trim().to_string();
+            match key.trim().to_lowercase().as_str() {
+                "name" => query.name = value,
+                "description" => query.description = Some(value),
+                "profile" => query.profile = Some(value),
+                "parameters" | "params" => {
+                    query.parameters = value
+                        .split(',')
+                        .map(|parameter| parameter.trim().to_string())
+                        .filter(|parameter| !parameter.is_empty())
+                        .collect()
+                }
+                // Any other comment is part of the query.
+                _ => break,
+            }
+        } else {
+            break;
+        }
+        lines.next();
+    }
+    query.sql = lines.collect::<Vec<_>>().join("\n").trim().to_string();
+    query
+}
@@ -1,30 +1,51 @@
-mod app;
+mod completion;
 mod config;
 mod database;
-// mod event_handler;
+mod edit;
+mod export;
+mod highlight;
+mod history;
+mod import;
+mod lexer;
+mod library;
 mod message;
 mod model;
+mod plan;
+mod schema;
+mod script;
 mod terminal;
 mod ui;
 mod viewstate;
 
-use app::App;
 use crossterm::event::EventStream;
-use crossterm::event::{self, Event};
-use database::initial_database_conection;
+use database::Connection;
 use futures::StreamExt;
 use message::Message;
 use model::Home;
 use std::error::Error;
 use terminal::{restore_terminal, setup_terminal};
-use tokio;
 use tokio::sync::{broadcast, mpsc};
 use tokio::task;
-use viewstate::{ViewStateBox, ViewStateTrait};
+use viewstate::ViewStateBox;
 
-enum MainEvent {
+pub enum MainEvent {
     Input(crossterm::event::Event),
     Tick,
+    Connected(String, Result<Connection, String>),
+    QueryOutput(String, Result<(usize, database::Output), String>),
+    TransactionBegun(String, Result<database::Transaction, String>),
+    TransactionEnded(String, Result<database::TransactionEnd, String>),
+    SchemaLoaded(String, Result<schema::Schema, String>),
+    TableDetails(String, String, Result<schema::TableDetails, String>),
+    HistoryLoaded(String, Result<Vec<history::HistoryEntry>, String>),
+    Plan(String, Result<Vec<plan::PlanNode>, String>),
+    Exported(String, Result<u64, String>),
+    ImportProgress(import::ImportProgress),
+    Imported(Result<import::ImportReport, String>),
+    RowsChanged(
+        edit::ChangedRows,
+        Result<Vec<Vec<model::CellValue>>, String>,
+    ),
 }
 
 #[tokio::main]
@@ -33,12 +54,7 @@ async fn main() -> Result<(), Box<dyn Error>> {
     let (shutdown_tx, _) = broadcast::channel(1);
 
     let config = config::Config::new();
-    let mut model_app = model::App::<Home>::new(config);
-    let mut app = App::new(
-        "SELECT id, name, email FROM users",
-        initial_database_conection,
-    )
-    .await;
+    let model_app = model::App::<Home>::new(config, tx.clone()).await;
 
     let tx_crossterm = tx.clone();
     let mut shutdown_rx = shutdown_tx.subscribe();
@@ -69,7 +85,7 @@ async fn main() -> Result<(), Box<dyn Error>> {
         }
     });
 
-    let mut viewstate: ViewStateBox = Box::new(viewstate::ViewState::new());
+    let mut viewstate: ViewStateBox = Box::new(viewstate::ViewState::new(model_app));
 
     let mut terminal = setup_terminal()?;
 
@@ -83,8 +99,8 @@ async fn main() -> Result<(), Box<dyn Error>> {
 
     while let Some(event) = rx.recv().await {
         let message = match event {
-            MainEvent::Input(event) => viewstate.handle_input(event).clone(),
-            MainEvent::Tick => viewstate.handle_event(event).clone(),
+            MainEvent::Input(event) => viewstate.handle_input(event),
+            event => viewstate.handle_event(event),
         };
         viewstate = handle_message(message, viewstate);
         if viewstate.should_quit() {
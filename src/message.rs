@@ -1,7 +1,62 @@
-#[derive(Debug, Clone, Copy, PartialEq)]
+use crate::database::{Connection, Output, Transaction, TransactionEnd};
+use crate::edit::ChangedRows;
+use crate::history::HistoryEntry;
+use crate::import::{ImportProgress, ImportReport};
+use crate::model::{CellValue, RowAction};
+use crate::plan::PlanNode;
+use crate::schema::{Schema, TableDetails};
+use crossterm::event::KeyEvent;
+
+#[derive(Debug)]
 pub enum Message {
     NoOp,
     Quit,
     SelectMode,
     Escape,
+    Next,
+    Previous,
+    NextTab,
+    PreviousTab,
+    PageDown,
+    PageUp,
+    First,
+    Last,
+    Disconnect,
+    Execute,
+    Cancel,
+    NextConnection,
+    Input(KeyEvent),
+    Connected(String, Result<Connection, String>),
+    QueryOutput(String, Result<(usize, Output), String>),
+    BeginTransaction,
+    EndTransaction(TransactionEnd),
+    TransactionBegun(String, Result<Transaction, String>),
+    TransactionEnded(String, Result<TransactionEnd, String>),
+    Expand,
+    Collapse,
+    Refresh,
+    SwitchFocus,
+    SchemaLoaded(String, Result<Schema, String>),
+    Inspect,
+    Complete,
+    History,
+    Search,
+    HistoryLoaded(String, Result<Vec<HistoryEntry>, String>),
+    TableDetails(String, String, Result<TableDetails, String>),
+    Explain,
+    Plan(String, Result<Vec<PlanNode>, String>),
+    Save,
+    Exported(String, Result<u64, String>),
+    Files,
+    Import,
+    ImportProgress(ImportProgress),
+    Imported(Result<ImportReport, String>),
+    NextColumn,
+    PreviousColumn,
+    ResizeColumn(i16),
+    FitColumn,
+    FreezeColumns,
+    Mark,
+    ChangeRows(RowAction),
+    RowsChanged(ChangedRows, Result<Vec<Vec<CellValue>>, String>),
 }
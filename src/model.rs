@@ -1,104 +1,589 @@
-use sqlx::Sqlite;
-use sqlx::{sqlite::SqliteRow, Column, Pool, Row};
-use std::error::Error;
+use crossterm::event::{Event, KeyCode, KeyEvent};
+use ratatui::widgets::{ListState, TableState};
+use ratatui_explorer::{FileExplorer, Theme};
+use sqlx::types::chrono::{DateTime, NaiveDate, NaiveDateTime, NaiveTime, Utc};
+use sqlx::types::{BigDecimal, JsonValue, Uuid};
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fmt;
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc::{self, UnboundedSender};
+use tui_textarea::{CursorMove, TextArea};
+use unicode_width::UnicodeWidthStr;
 
+use crate::completion::{complete, Completion};
 use crate::config::*;
 use crate::database::*;
-use crate::message::Message;
+use crate::edit::RowSource;
+use crate::export::{ExportFormat, ExportPage};
+use crate::highlight::Highlighter;
+use crate::history::{History, HistoryEntry};
+use crate::import::{CsvPreview, ImportProgress, ImportReport};
+use crate::library::SavedQuery;
+use crate::plan::PlanNode;
+use crate::schema::{
+    load_schema, load_table_details, ColumnInfo, Dependent, Relation, Schema, TableDetails,
+};
+use crate::script::{
+    bind_placeholders, changes_schema, placeholders, split_statements, statement_ranges, Dialect,
+};
+use crate::MainEvent;
 
-trait Update<NewMode> {
-    fn update(self, message: Message) -> (App<NewMode>, Option<Message>);
-}
+mod edit;
+mod explain;
+mod export;
+mod history;
+mod import;
+mod library;
 
 // Modes
 
 pub struct Home;
-pub struct EditQuery;
-pub struct BrowseSqliteDBFiles;
-pub struct ExploreResults;
-pub struct SaveResults;
-pub struct ExploreConnection;
-pub struct ConfigEditor;
+#[derive(Default)]
+pub struct EditQuery {
+    pub editor: TextArea<'static>,
+    /// The completions offered for the word before the cursor, while the
+    /// popup is open.
+    pub completion: Option<CompletionPopup>,
+    pub highlighter: Highlighter,
+    /// The first row and column the editor shows, which it keeps to itself,
+    /// worked out the same way.
+    pub scroll_top: (u16, u16),
+}
+
+pub struct CompletionPopup {
+    pub completions: Vec<Completion>,
+    pub list: ListState,
+    /// How many characters before the cursor a completion replaces.
+    pub replaces: usize,
+}
+/// Picking a database file to open or a delimited file to import.
+#[derive(Default)]
+pub struct BrowseSqliteDBFiles {
+    /// The files in the directory being looked at, unless it could not be
+    /// read.
+    pub explorer: Option<FileExplorer>,
+}
+/// Importing a delimited file into a table of the active connection.
+pub struct ImportCsv {
+    pub connection: String,
+    /// The start of the file, once it has been read.
+    pub preview: Result<CsvPreview, String>,
+    /// The table to import into, which is created if it does not exist.
+    pub table: String,
+    /// For each of the file's columns, the column of the existing table it
+    /// goes into, or `None` to leave it out.
+    pub mapping: Vec<Option<usize>>,
+    /// The file's column whose mapping is being changed.
+    pub column: usize,
+    pub state: ImportState,
+    pub scroll: u16,
+    /// The files to go back to.
+    files: BrowseSqliteDBFiles,
+}
+pub enum ImportState {
+    Mapping,
+    Running {
+        progress: ImportProgress,
+        cancelled: Arc<AtomicBool>,
+    },
+    Finished(Result<ImportReport, String>),
+}
+#[derive(Default)]
+pub struct ExploreResults {
+    pub table: TableState,
+    pub grid: Grid,
+}
+/// Where the results view is among the columns of the results, and the
+/// rows picked out in it.
+#[derive(Default)]
+pub struct Grid {
+    /// The column of the selected row that is picked out.
+    pub column: usize,
+    /// The rows marked to be deleted together.
+    pub marked: BTreeSet<usize>,
+    /// The widths columns have been given by hand, in place of the width
+    /// that fits their values.
+    pub widths: HashMap<usize, u16>,
+    /// How many of the first columns stay in view while the others scroll.
+    pub frozen: usize,
+    /// The first column shown after the frozen ones, which follows the
+    /// picked column as the table is drawn.
+    pub offset: usize,
+}
+/// What can be done to the rows of the results from the results view.
+#[derive(Clone, Copy, Debug)]
+pub enum RowAction {
+    Edit,
+    Insert,
+    Duplicate,
+    Delete,
+}
+/// Changing one value of a row of the results, then confirming the
+/// statement that makes the change.
+pub struct EditCell {
+    pub source: RowSource,
+    pub row: usize,
+    pub column: usize,
+    /// The new value, typed as a parameter's value is.
+    pub input: String,
+    /// The statement that makes the change, and how it is shown, while it
+    /// waits to be confirmed.
+    pub update: Option<(BoundStatement, String)>,
+    /// The results view to go back to.
+    results: ExploreResults,
+}
+/// Filling in a row to insert into the table the results came from, then
+/// confirming the statement that inserts it.
+pub struct InsertRow {
+    pub source: RowSource,
+    /// What is typed for each of the table's columns. A column left empty
+    /// is left out, so that it takes its default.
+    pub inputs: Vec<String>,
+    pub selected: usize,
+    /// The statement that inserts the row, and how it is shown, while it
+    /// waits to be confirmed.
+    pub insert: Option<(BoundStatement, String)>,
+    /// Where the row goes in the results once it comes back.
+    position: usize,
+    results: ExploreResults,
+}
+/// Confirming the statement that deletes rows of the results.
+pub struct DeleteRows {
+    pub source: RowSource,
+    pub rows: Vec<usize>,
+    pub delete: (BoundStatement, String),
+    results: ExploreResults,
+}
+/// Choosing where and in what format to write the results being shown.
+pub struct SaveResults {
+    pub path: String,
+    pub format: ExportFormat,
+    /// The results view to go back to.
+    results: ExploreResults,
+}
+#[derive(Default)]
+pub struct ExploreConnection {
+    pub entries: ListState,
+    /// The selected row of the active connection's schema tree.
+    pub tree: ListState,
+    /// The paths of the schema tree nodes that are expanded.
+    expanded: HashSet<String>,
+    /// Whether keys go to the schema tree rather than the connection list.
+    pub schema_focused: bool,
+}
+/// Looking at one table's definition, keys, indexes and triggers.
+pub struct InspectTable {
+    pub relation: Relation,
+    pub is_view: bool,
+    /// The definition and row count, once they have been read.
+    pub details: Option<TableDetails>,
+    pub scroll: u16,
+    /// The explorer to go back to.
+    explorer: ExploreConnection,
+}
+/// Looking through the statements run before, newest first.
+#[derive(Default)]
+pub struct BrowseHistory {
+    pub entries: Vec<HistoryEntry>,
+    pub table: TableState,
+    /// What the statements listed contain.
+    pub search: String,
+    /// Whether keys go to the search rather than the list.
+    pub searching: bool,
+}
+/// Choosing a saved query to open in the editor.
+pub struct BrowseLibrary {
+    pub list: ListState,
+}
+/// Asking for the values of the placeholders in a query before running it.
+pub struct BindParameters {
+    pub inputs: Vec<ParameterInput>,
+    pub selected: usize,
+    /// The editor to go back to.
+    query: EditQuery,
+}
+
+/// Looking at how the database would run the statement under the cursor.
+pub struct ExplainQuery {
+    pub connection: String,
+    pub statement: String,
+    /// The steps of the plan, once the database has worked them out.
+    pub plan: Option<Result<Vec<PlanNode>, String>>,
+    pub scroll: u16,
+    /// The editor to go back to.
+    query: EditQuery,
+}
+
+pub struct ParameterInput {
+    pub name: String,
+    pub value: String,
+}
+/// Asking whether to quit while a transaction is still open.
+pub struct ConfirmQuit;
 pub struct Quit;
 
 pub struct App<Mode> {
-    mode: Mode,
+    pub mode: Mode,
+    config: Config,
+    events: UnboundedSender<MainEvent>,
     connections: Connections,
-    results: Results,
+    results: Vec<StatementResult>,
+    /// The tab of `results` being shown.
+    selected_result: usize,
+    /// The name of the connection that produced `results`.
+    results_connection: Option<String>,
     queries: Queries,
+    running: Option<RunningQuery>,
+    transaction: Option<OpenTransaction>,
+    /// The schema of each open connection that has been read, or `None`
+    /// while it is being read.
+    schemas: HashMap<String, Option<Schema>>,
+    status: Option<String>,
+}
+
+/// A script whose statements are still running, or whose last statement's
+/// rows are still being fetched, in the background. What each statement
+/// produces comes back as a `MainEvent::QueryOutput`.
+pub struct RunningQuery {
+    pub connection: String,
+    /// When the script or the page being fetched was asked for, or `None`
+    /// while waiting to be asked for more.
+    pub fetching: Option<Instant>,
+    statements: Vec<String>,
+    /// Whether the script runs in the open transaction.
+    in_transaction: bool,
+    first_output: bool,
+    /// Asks for another page. Dropping it stops the script.
+    requests: Option<UnboundedSender<()>>,
+    executed_at: DateTime<Utc>,
+    /// When the last statement to finish came back, to time the next.
+    mark: Instant,
+    /// How long each statement that has come back took.
+    durations: Vec<Duration>,
+    /// Whether the statements have been written to the history.
+    recorded: bool,
+    /// Where the rows of the last statement are being written as they are
+    /// fetched, instead of being added to its tab.
+    export: Option<Export>,
+}
+
+/// The rows still to come for an export, sent on to the task writing them.
+struct Export {
+    path: String,
+    pages: UnboundedSender<ExportPage>,
+    /// How many rows have been sent on so far.
+    rows: usize,
+}
+
+/// A transaction open on a connection pinned from its pool. Scripts run on
+/// that connection go through it until it is committed or rolled back.
+pub struct OpenTransaction {
+    pub connection: String,
+    /// How many statements have run in it.
+    pub pending: usize,
+    transaction: Transaction,
+}
+
+/// What one statement of the last script run produced, shown as a tab of
+/// the results pane.
+pub struct StatementResult {
+    pub statement: String,
+    pub outcome: Outcome,
+    /// Whether rows were left unfetched because a later statement ran.
+    pub truncated: bool,
 }
 
+pub enum Outcome {
+    Rows(Results),
+    Affected(u64),
+    Failed(String),
+}
+
+#[derive(Default)]
 struct Queries {
     current_query: String,
-    queries: Vec<String>,
+    /// Where every statement run is recorded, unless it could not be opened.
+    history: Option<History>,
+    library: Vec<SavedQuery>,
+    /// The value last given for each parameter name.
+    parameters: HashMap<String, String>,
 }
 
-impl Default for Queries {
-    fn default() -> Self {
-        Self {
-            current_query: String::new(),
-            queries: Vec::new(),
-        }
-    }
+/// A live connection and the name it is shown under.
+pub struct OpenConnection {
+    pub name: String,
+    pub connection: Connection,
 }
 
-struct Connections {
-    sqlite_pool: Option<Pool<Sqlite>>,
+/// Every open connection, with the one queries currently run against.
+#[derive(Default)]
+pub struct Connections {
+    open: Vec<OpenConnection>,
+    active: Option<usize>,
 }
 
 impl Connections {
-    async fn init_connections(config: Config) -> Self {
-        let sqlite_pool = initial_database_conection(config).await;
-        Self { sqlite_pool }
+    /// Add a connection and make it active, replacing any open connection
+    /// with the same name.
+    fn add(&mut self, name: String, connection: Connection) {
+        let index = match self.open.iter().position(|open| open.name == name) {
+            Some(index) => {
+                self.open[index].connection = connection;
+                index
+            }
+            None => {
+                self.open.push(OpenConnection { name, connection });
+                self.open.len() - 1
+            }
+        };
+        self.active = Some(index);
+    }
+
+    fn disconnect(&mut self, index: usize) -> Option<OpenConnection> {
+        if index >= self.open.len() {
+            return None;
+        }
+        let removed = self.open.remove(index);
+        self.active = match self.active {
+            _ if self.open.is_empty() => None,
+            Some(active) if active > index => Some(active - 1),
+            Some(active) if active == index => Some(index.min(self.open.len() - 1)),
+            active => active,
+        };
+        Some(removed)
     }
 
-    async fn connect_to_sqlite_db(file: &str) -> Self {
-        let sqlite_pool = connect_to_database_file(file).await;
-        Self { sqlite_pool }
+    pub fn open(&self) -> &[OpenConnection] {
+        &self.open
     }
 
-    fn disconnect(self) -> Self {
-        Self { sqlite_pool: None }
+    pub fn active(&self) -> Option<&OpenConnection> {
+        self.active.map(|index| &self.open[index])
+    }
+
+    pub fn active_index(&self) -> Option<usize> {
+        self.active
+    }
+
+    pub fn position(&self, name: &str) -> Option<usize> {
+        self.open.iter().position(|open| open.name == name)
     }
 }
 
-enum Results {
+#[derive(Debug, Default)]
+pub enum Results {
     Some {
         headers: Vec<String>,
-        data: Vec<Vec<String>>,
+        data: Vec<Vec<CellValue>>,
+        /// The width each column needs, see `column_widths`, kept from when
+        /// the rows came rather than worked out each time they are drawn.
+        widths: Vec<u16>,
     },
+    #[default]
     None,
 }
 
-impl Default for Results {
-    fn default() -> Self {
-        Self::None
+/// A single value from a result row, decoded from the column's type.
+#[derive(Clone, Debug, PartialEq)]
+pub enum CellValue {
+    Null,
+    Bool(bool),
+    Integer(i64),
+    Unsigned(u64),
+    Real(f64),
+    Decimal(BigDecimal),
+    Text(String),
+    Blob(Vec<u8>),
+    Date(NaiveDate),
+    Time(NaiveTime),
+    DateTime(NaiveDateTime),
+    /// A timestamp with a time zone, normalised to UTC.
+    Timestamp(DateTime<Utc>),
+    Json(JsonValue),
+    Uuid(Uuid),
+    /// A value of a type that has no variant of its own, already formatted.
+    Other(String),
+}
+
+impl CellValue {
+    pub fn is_null(&self) -> bool {
+        matches!(self, CellValue::Null)
+    }
+
+    /// The value as plain text, empty if it is NULL, for reading catalog
+    /// and plan columns. Some MySQL versions return those as binary strings.
+    pub fn text(&self) -> String {
+        match self {
+            CellValue::Null => String::new(),
+            CellValue::Blob(bytes) => String::from_utf8_lossy(bytes).into_owned(),
+            value => value.to_string(),
+        }
+    }
+
+    /// The value on one line, as the results table shows it.
+    pub fn to_line(&self) -> String {
+        let text = self.to_string();
+        match text.contains(['\n', '\r', '\t']) {
+            true => text.replace("\r\n", " ").replace(['\n', '\r', '\t'], " "),
+            false => text,
+        }
+    }
+
+    /// The value typed in for a parameter: `NULL`, `true` or `false`, a
+    /// number, or else text. Quoting it in `'` makes it text whatever it is.
+    pub fn from_input(input: &str) -> Self {
+        let trimmed = input.trim();
+        if let Some(quoted) = trimmed
+            .strip_prefix('\'')
+            .and_then(|rest| rest.strip_suffix('\''))
+        {
+            return CellValue::Text(quoted.replace("''", "'"));
+        }
+        let numeric = !trimmed.is_empty()
+            && trimmed
+                .chars()
+                .all(|c| c.is_ascii_digit() || matches!(c, '.' | '-' | '+' | 'e' | 'E'));
+        match trimmed.to_lowercase().as_str() {
+            "null" => CellValue::Null,
+            "true" => CellValue::Bool(true),
+            "false" => CellValue::Bool(false),
+            _ if numeric => trimmed
+                .parse()
+                .map(CellValue::Integer)
+                .or_else(|_| trimmed.parse().map(CellValue::Real))
+                .unwrap_or_else(|_| CellValue::Text(input.to_string())),
+            _ => CellValue::Text(input.to_string()),
+        }
+    }
+
+    /// What kind of value this is, as a hint next to a parameter's value.
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            CellValue::Null => "null",
+            CellValue::Bool(_) => "boolean",
+            CellValue::Integer(_) | CellValue::Unsigned(_) => "integer",
+            CellValue::Real(_) => "real",
+            CellValue::Decimal(_) => "decimal",
+            CellValue::Text(_) | CellValue::Other(_) => "text",
+            CellValue::Blob(_) => "blob",
+            CellValue::Date(_) => "date",
+            CellValue::Time(_) => "time",
+            CellValue::DateTime(_) | CellValue::Timestamp(_) => "timestamp",
+            CellValue::Json(_) => "json",
+            CellValue::Uuid(_) => "uuid",
+        }
+    }
+}
+
+impl fmt::Display for CellValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CellValue::Null => write!(f, "NULL"),
+            CellValue::Bool(value) => write!(f, "{}", value),
+            CellValue::Integer(value) => write!(f, "{}", value),
+            CellValue::Unsigned(value) => write!(f, "{}", value),
+            CellValue::Real(value) => write!(f, "{}", value),
+            CellValue::Decimal(value) => write!(f, "{}", value),
+            CellValue::Text(value) | CellValue::Other(value) => write!(f, "{}", value),
+            CellValue::Blob(bytes) => {
+                write!(f, "x'")?;
+                for byte in bytes.iter().take(32) {
+                    write!(f, "{:02x}", byte)?;
+                }
+                match bytes.len() > 32 {
+                    true => write!(f, "...' ({} bytes)", bytes.len()),
+                    false => write!(f, "'"),
+                }
+            }
+            CellValue::Date(value) => write!(f, "{}", value),
+            CellValue::Time(value) => write!(f, "{}", value),
+            CellValue::DateTime(value) => write!(f, "{}", value),
+            CellValue::Timestamp(value) => write!(f, "{}", value),
+            CellValue::Json(value) => write!(f, "{}", value),
+            CellValue::Uuid(value) => write!(f, "{}", value),
+        }
     }
 }
 
 impl Results {
-    fn new(rows: Vec<SqliteRow>) -> Self {
-        let headers = rows
-            .first()
-            .map(|row| {
-                row.columns()
-                    .iter()
-                    .map(|col| col.name().to_string())
-                    .collect::<Vec<_>>()
-            })
-            .unwrap_or_default();
-        let data = rows
-            .iter()
-            .map(|row| {
-                (0..row.len())
-                    .map(|i| row.try_get::<&str, _>(i).unwrap_or_default().to_string())
-                    .collect::<Vec<_>>()
-            })
-            .collect::<Vec<_>>();
-        Results::Some { headers, data }
+    pub fn new(headers: Vec<String>, data: Vec<Vec<CellValue>>) -> Self {
+        let widths = column_widths(&headers, &data);
+        Results::Some {
+            headers,
+            data,
+            widths,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        match self {
+            Results::Some { data, .. } => data.len(),
+            Results::None => 0,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Add the rows of a later page of the same query, widening the
+    /// columns for them while they are among the rows the widths are
+    /// sized to.
+    pub fn append(&mut self, page: Results) {
+        match (self, page) {
+            (results @ Results::Some { .. }, Results::Some { data: more, .. }) => {
+                let sampled = results.len() < WIDTH_SAMPLE;
+                if let Results::Some { data, .. } = &mut *results {
+                    data.extend(more);
+                }
+                if sampled {
+                    results.fit_widths();
+                }
+            }
+            (results @ Results::None, page) => *results = page,
+            (_, Results::None) => {}
+        }
+    }
+
+    /// Size the columns to the rows again, after some were changed.
+    pub fn fit_widths(&mut self) {
+        if let Results::Some {
+            headers,
+            data,
+            widths,
+        } = self
+        {
+            *widths = column_widths(headers, data);
+        }
     }
 }
 
+/// How many rows are looked at to size the columns of the results table.
+const WIDTH_SAMPLE: usize = 200;
+/// The widest a column is made to fit its values. Longer values are cut
+/// short until the column is widened by hand.
+pub const MAX_COLUMN_WIDTH: u16 = 40;
+
+/// The display width each column needs for its header and the values in
+/// the first rows, between the width of `NULL` and `MAX_COLUMN_WIDTH`.
+pub fn column_widths(headers: &[String], data: &[Vec<CellValue>]) -> Vec<u16> {
+    let mut widths = headers
+        .iter()
+        .map(|header| header.width())
+        .collect::<Vec<_>>();
+    for row in data.iter().take(WIDTH_SAMPLE) {
+        for (width, value) in widths.iter_mut().zip(row) {
+            *width = (*width).max(value.to_line().width());
+        }
+    }
+    widths
+        .into_iter()
+        .map(|width| width.clamp(4, MAX_COLUMN_WIDTH as usize) as u16)
+        .collect()
+}
+
 impl<T> App<T> {
     pub fn cancel(self) -> App<Home> {
         self.copy_app_with_new_mode(Home)
@@ -107,19 +592,488 @@ impl<T> App<T> {
     fn copy_app_with_new_mode<NewMode>(self, mode: NewMode) -> App<NewMode> {
         App {
             mode,
+            config: self.config,
+            events: self.events,
             connections: self.connections,
             results: self.results,
+            selected_result: self.selected_result,
+            results_connection: self.results_connection,
             queries: self.queries,
+            running: self.running,
+            transaction: self.transaction,
+            schemas: self.schemas,
+            status: self.status,
         }
     }
 
-    pub fn add_results(self, rows: Vec<SqliteRow>) -> App<T> {
-        App {
-            mode: self.mode,
-            connections: self.connections,
-            results: Results::new(rows),
-            queries: self.queries,
+    /// Add a connection opened in the background and make it active, or
+    /// report why it could not be opened.
+    pub fn connected(mut self, name: String, result: Result<Connection, String>) -> App<T> {
+        match result {
+            Ok(connection) => {
+                self.status = Some(format!("Connected to {}", name));
+                self.schemas.remove(&name);
+                self.connections.add(name, connection);
+                self.load_schema(false);
+            }
+            Err(error) => self.status = Some(format!("Could not connect to {}: {}", name, error)),
+        }
+        self
+    }
+
+    /// Connect to a profile in the background. The connection comes back as
+    /// a `MainEvent::Connected`.
+    fn open_profile(&mut self, name: String, profile: &Profile) {
+        let (profile, events) = (profile.clone(), self.events.clone());
+        self.status = Some(format!("Connecting to {}...", name));
+        tokio::spawn(async move {
+            let result = connect_to_profile(&profile)
+                .await
+                .map_err(|error| error.to_string());
+            let _ = events.send(MainEvent::Connected(name, result));
+        });
+    }
+
+    /// Make the connection to a profile the active one, connecting to it
+    /// first if it is not open.
+    fn use_profile(&mut self, name: &str) {
+        let open = self
+            .connections
+            .open()
+            .iter()
+            .position(|open| open.name == name);
+        match (open, self.config.profiles.get(name)) {
+            (Some(index), _) => {
+                self.connections.active = Some(index);
+                self.load_schema(false);
+            }
+            (None, Some(profile)) => {
+                let profile = profile.clone();
+                self.open_profile(name.to_string(), &profile);
+            }
+            (None, None) => self.status = Some(format!("No profile named {}", name)),
+        }
+    }
+
+    /// Ask the running query to stop. If a page is being fetched the task
+    /// still reports back once the database has given up on it.
+    pub fn cancel_query(mut self) -> App<T> {
+        let rows = self.results().len();
+        match &mut self.running {
+            Some(running) if running.fetching.is_some() => {
+                if let Some(_requests) = running.requests.take() {
+                    self.status = Some(format!("Cancelling query on {}...", running.connection));
+                }
+            }
+            Some(running) => {
+                self.status = Some(format!(
+                    "Stopped fetching from {} after {} rows",
+                    running.connection, rows
+                ));
+                self.running = None;
+            }
+            None => {}
+        }
+        self
+    }
+
+    /// Ask for the next page of the running query, unless one is already
+    /// being fetched.
+    pub fn fetch_more(mut self) -> App<T> {
+        if let Some(running) = &mut self.running {
+            if let (None, Some(requests)) = (running.fetching, &running.requests) {
+                if requests.send(()).is_ok() {
+                    running.fetching = Some(Instant::now());
+                }
+            }
+        }
+        self
+    }
+
+    /// Whether the results being shown are from the last statement of the
+    /// running script and it has more rows waiting to be fetched.
+    pub fn more_rows(&self) -> bool {
+        self.running.as_ref().is_some_and(|running| {
+            !running.first_output
+                && running.requests.is_some()
+                && self.results.len() == running.statements.len()
+                && self.selected_result + 1 == self.results.len()
+        })
+    }
+
+    /// Add what a statement of the running script produced, as a new tab
+    /// or as more rows for the last one.
+    pub fn query_output(
+        mut self,
+        connection: String,
+        result: Result<(usize, Output), String>,
+    ) -> App<T> {
+        let Some(running) = &mut self.running else {
+            return self;
+        };
+        let elapsed = running
+            .fetching
+            .map(|started| started.elapsed())
+            .unwrap_or_default();
+        if std::mem::take(&mut running.first_output) {
+            self.results.clear();
+            self.selected_result = 0;
+            self.results_connection = Some(connection.clone());
+        }
+        let count = running.statements.len();
+        let mut refresh_schema = false;
+        let (index, more) = match result {
+            Ok((index, output)) => {
+                let more = matches!(output, Output::Rows { more: true, .. });
+                match (self.results.get_mut(index), output) {
+                    (Some(tab), Output::Rows { results, .. }) => {
+                        match (&mut running.export, &mut tab.outcome) {
+                            (Some(export), _) => {
+                                if let Results::Some { data, .. } = results {
+                                    export.rows += data.len();
+                                    let _ = export.pages.send(ExportPage::Rows(data));
+                                }
+                            }
+                            (None, Outcome::Rows(rows)) => rows.append(results),
+                            (None, outcome) => *outcome = Outcome::Rows(results),
+                        }
+                    }
+                    (Some(_), Output::Done { .. }) => {}
+                    (None, output) => {
+                        running.durations.push(running.mark.elapsed());
+                        running.mark = Instant::now();
+                        let statement = &running.statements[index];
+                        if changes_schema(statement) && self.schemas.contains_key(&connection) {
+                            refresh_schema = true;
+                        }
+                        let outcome = match output {
+                            Output::Rows { results, .. } => Outcome::Rows(results),
+                            Output::Done { rows_affected } => Outcome::Affected(rows_affected),
+                        };
+                        self.results.push(StatementResult {
+                            statement: statement.clone(),
+                            outcome,
+                            truncated: more && index + 1 < count,
+                        });
+                        self.selected_result = index;
+                        if let (true, Some(transaction)) =
+                            (running.in_transaction, &mut self.transaction)
+                        {
+                            transaction.pending += 1;
+                        }
+                    }
+                }
+                (index, more)
+            }
+            Err(error) => {
+                if let Some(export) = running.export.take() {
+                    let _ = export.pages.send(ExportPage::Failed(error.clone()));
+                }
+                let index = self.results.len();
+                if let Some(statement) = running.statements.get(index) {
+                    running.durations.push(running.mark.elapsed());
+                    self.results.push(StatementResult {
+                        statement: statement.clone(),
+                        outcome: Outcome::Failed(error.clone()),
+                        truncated: false,
+                    });
+                    self.selected_result = index;
+                }
+                self.record_history(&connection);
+                self.running = None;
+                self.status = Some(match count {
+                    1 => format!(
+                        "Query on {} failed after {:.2?}: {}",
+                        connection, elapsed, error
+                    ),
+                    _ => format!(
+                        "Statement {} of {} on {} failed after {:.2?}: {}",
+                        index.min(count - 1) + 1,
+                        count,
+                        connection,
+                        elapsed,
+                        error
+                    ),
+                });
+                return self;
+            }
+        };
+        if refresh_schema {
+            self.read_schema(&connection);
+        }
+        if index + 1 < count {
+            return self;
+        }
+        self.record_history(&connection);
+        let rows = self.results().len();
+        let Some(running) = &mut self.running else {
+            return self;
+        };
+        running.fetching = None;
+        if let (true, Some(_), Some(export)) = (more, &running.requests, &running.export) {
+            self.status = Some(format!(
+                "Exporting to {}, {} rows so far...",
+                export.path, export.rows
+            ));
+            return self.fetch_more();
+        }
+        // An export that still has rows to come when the query is cancelled
+        // is never told it has them all, and fails.
+        if let (false, Some(export)) = (more, running.export.take()) {
+            let _ = export.pages.send(ExportPage::End);
+        }
+        self.status = Some(if more && running.requests.is_some() {
+            format!("{} rows loaded from {}, more available", rows, connection)
+        } else {
+            self.running = None;
+            match count {
+                1 => format!("{} rows from {} in {:.2?}", rows, connection, elapsed),
+                _ => format!(
+                    "Ran {} statements on {} in {:.2?}",
+                    count, connection, elapsed
+                ),
+            }
+        });
+        self
+    }
+
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    pub fn connections(&self) -> &Connections {
+        &self.connections
+    }
+
+    /// The rows of the tab being shown, if it has any.
+    pub fn results(&self) -> &Results {
+        match self.results.get(self.selected_result) {
+            Some(StatementResult {
+                outcome: Outcome::Rows(results),
+                ..
+            }) => results,
+            _ => &Results::None,
+        }
+    }
+
+    pub fn statement_results(&self) -> &[StatementResult] {
+        &self.results
+    }
+
+    pub fn selected_result(&self) -> usize {
+        self.selected_result
+    }
+
+    pub fn results_connection(&self) -> Option<&str> {
+        self.results_connection.as_deref()
+    }
+
+    pub fn current_query(&self) -> &str {
+        &self.queries.current_query
+    }
+
+    /// Open a transaction on the active connection in the background. It
+    /// comes back as a `MainEvent::TransactionBegun`.
+    pub fn begin_transaction(mut self) -> App<T> {
+        if let Some(transaction) = &self.transaction {
+            self.status = Some(format!(
+                "A transaction is already open on {}",
+                transaction.connection
+            ));
+            return self;
+        }
+        if !self.stop_idle_query() {
+            return self;
+        }
+        let Some(open) = self.connections.active() else {
+            self.status = Some("Not connected to a database".to_string());
+            return self;
+        };
+        let (name, connection) = (open.name.clone(), open.connection.clone());
+        let events = self.events.clone();
+        self.status = Some(format!("Starting a transaction on {}...", name));
+        tokio::spawn(async move {
+            let result = connection.begin().await.map_err(|error| error.to_string());
+            let _ = events.send(MainEvent::TransactionBegun(name, result));
+        });
+        self
+    }
+
+    pub fn transaction_begun(
+        mut self,
+        connection: String,
+        result: Result<Transaction, String>,
+    ) -> App<T> {
+        match result {
+            Ok(transaction) if self.transaction.is_none() => {
+                self.status = Some(format!("Transaction open on {}", connection));
+                self.transaction = Some(OpenTransaction {
+                    connection,
+                    pending: 0,
+                    transaction,
+                });
+            }
+            // Begun twice before either came back; dropping this one rolls
+            // it back.
+            Ok(_) => {}
+            Err(error) => {
+                self.status = Some(format!(
+                    "Could not start a transaction on {}: {}",
+                    connection, error
+                ))
+            }
+        }
+        self
+    }
+
+    /// Commit or roll back the open transaction in the background. The
+    /// outcome comes back as a `MainEvent::TransactionEnded`.
+    pub fn end_transaction(mut self, end: TransactionEnd) -> App<T> {
+        if self.transaction.is_none() {
+            self.status = Some("No transaction is open".to_string());
+            return self;
+        }
+        if !self.stop_idle_query() {
+            return self;
+        }
+        let Some(OpenTransaction {
+            connection,
+            pending,
+            transaction,
+        }) = self.transaction.take()
+        else {
+            return self;
+        };
+        let events = self.events.clone();
+        self.status = Some(match end {
+            TransactionEnd::Commit => {
+                format!("Committing {} statements on {}...", pending, connection)
+            }
+            TransactionEnd::Rollback => {
+                format!("Rolling back {} statements on {}...", pending, connection)
+            }
+        });
+        tokio::spawn(async move {
+            let result = transaction
+                .end(end)
+                .await
+                .map(|()| end)
+                .map_err(|error| error.to_string());
+            let _ = events.send(MainEvent::TransactionEnded(connection, result));
+        });
+        self
+    }
+
+    pub fn transaction_ended(
+        mut self,
+        connection: String,
+        result: Result<TransactionEnd, String>,
+    ) -> App<T> {
+        if matches!(result, Ok(TransactionEnd::Commit)) && self.schemas.contains_key(&connection) {
+            self.read_schema(&connection);
+        }
+        self.status = Some(match result {
+            Ok(TransactionEnd::Commit) => format!("Committed on {}", connection),
+            Ok(TransactionEnd::Rollback) => format!("Rolled back on {}", connection),
+            Err(error) => format!(
+                "Transaction on {} failed and was rolled back: {}",
+                connection, error
+            ),
+        });
+        self
+    }
+
+    /// Stop a query that is waiting to be asked for more rows, so that its
+    /// connection is free. Returns false if a query is still running.
+    fn stop_idle_query(&mut self) -> bool {
+        match &self.running {
+            Some(running) if running.fetching.is_some() => {
+                self.status = Some("A query is still running".to_string());
+                false
+            }
+            _ => {
+                self.running = None;
+                true
+            }
+        }
+    }
+
+    /// Read the active connection's schema in the background, unless it
+    /// has already been read and `refresh` is not set. It comes back as a
+    /// `MainEvent::SchemaLoaded`.
+    pub fn load_schema(&mut self, refresh: bool) {
+        let Some(open) = self.connections.active() else {
+            return;
+        };
+        if !refresh && self.schemas.contains_key(&open.name) {
+            return;
         }
+        let name = open.name.clone();
+        self.schemas.insert(name.clone(), None);
+        self.read_schema(&name);
+    }
+
+    /// Read a connection's schema again after a statement changed it,
+    /// keeping what was read before until it comes back.
+    fn read_schema(&self, name: &str) {
+        let Some(open) = self
+            .connections
+            .open()
+            .iter()
+            .find(|open| open.name == name)
+        else {
+            return;
+        };
+        let (name, connection) = (open.name.clone(), open.connection.clone());
+        let events = self.events.clone();
+        tokio::spawn(async move {
+            let result = load_schema(&connection)
+                .await
+                .map_err(|error| error.to_string());
+            let _ = events.send(MainEvent::SchemaLoaded(name, result));
+        });
+    }
+
+    pub fn schema_loaded(mut self, connection: String, result: Result<Schema, String>) -> App<T> {
+        // Dropped if the connection was closed while its schema was read.
+        if let Some(schema) = self.schemas.get_mut(&connection) {
+            match result {
+                Ok(loaded) => *schema = Some(loaded),
+                Err(error) => {
+                    self.schemas.remove(&connection);
+                    self.status = Some(format!(
+                        "Could not read the schema of {}: {}",
+                        connection, error
+                    ));
+                }
+            }
+        }
+        self
+    }
+
+    /// The dialect of the active connection's SQL.
+    pub fn dialect(&self) -> Dialect {
+        self.connections
+            .active()
+            .map_or(Dialect::Sqlite, |open| open.connection.dialect())
+    }
+
+    /// The schema of the active connection, if it has been read.
+    pub fn schema(&self) -> Option<&Schema> {
+        let open = self.connections.active()?;
+        self.schemas.get(&open.name)?.as_ref()
+    }
+
+    pub fn transaction(&self) -> Option<&OpenTransaction> {
+        self.transaction.as_ref()
+    }
+
+    pub fn running(&self) -> Option<&RunningQuery> {
+        self.running.as_ref()
+    }
+
+    pub fn status(&self) -> Option<&str> {
+        self.status.as_deref()
     }
 
     pub fn quit(self) -> App<Quit> {
@@ -128,52 +1082,901 @@ impl<T> App<T> {
 }
 
 impl App<Home> {
-    pub async fn new(config: Config) -> Self {
-        App {
+    pub async fn new(config: Config, events: UnboundedSender<MainEvent>) -> Self {
+        let mut app = App {
             mode: Home,
-            connections: Connections::init_connections(config).await,
-            results: Results::default(),
+            config: config.clone(),
+            events,
+            connections: Connections::default(),
+            results: Vec::new(),
+            selected_result: 0,
+            results_connection: None,
             queries: Queries::default(),
+            running: None,
+            transaction: None,
+            schemas: HashMap::new(),
+            status: None,
+        };
+        match History::open().await {
+            Ok(history) => app.queries.history = Some(history),
+            Err(error) => app.status = Some(format!("Query history is off: {}", error)),
+        }
+        app.load_library();
+        if let Some(name) = config.starting_db.name() {
+            match initial_database_conection(config).await {
+                Ok(connection) => app.connections.add(name, connection),
+                Err(error) => {
+                    app.status = Some(format!("Could not connect to {}: {}", name, error))
+                }
+            }
         }
+        app
     }
 
     pub fn edit_query(self) -> App<EditQuery> {
-        self.copy_app_with_new_mode(EditQuery)
+        let mut editor = TextArea::from(self.queries.current_query.lines());
+        editor.move_cursor(CursorMove::Bottom);
+        editor.move_cursor(CursorMove::End);
+        let mut app = self.copy_app_with_new_mode(EditQuery {
+            editor,
+            completion: None,
+            highlighter: Highlighter::default(),
+            scroll_top: (0, 0),
+        });
+        app.load_schema(false);
+        app
     }
 
-    pub fn open_sqlite_db(self) -> App<BrowseSqliteDBFiles> {
-        self.copy_app_with_new_mode(BrowseSqliteDBFiles)
+    pub fn open_sqlite_db(mut self) -> App<BrowseSqliteDBFiles> {
+        let explorer = match FileExplorer::with_theme(Theme::default().add_default_title()) {
+            Ok(explorer) => Some(explorer),
+            Err(error) => {
+                self.status = Some(format!("Could not list the files here: {}", error));
+                None
+            }
+        };
+        self.copy_app_with_new_mode(BrowseSqliteDBFiles { explorer })
     }
 
     pub fn explore_results(self) -> App<ExploreResults> {
-        self.copy_app_with_new_mode(ExploreResults)
+        let mut table = TableState::default();
+        if !self.results().is_empty() {
+            table.select(Some(0));
+        }
+        self.copy_app_with_new_mode(ExploreResults {
+            table,
+            grid: Grid::default(),
+        })
+    }
+
+    pub fn explore_connection(mut self) -> App<ExploreConnection> {
+        let mut entries = ListState::default();
+        entries.select(Some(self.connections.active_index().unwrap_or(0)));
+        self.load_schema(false);
+        self.copy_app_with_new_mode(ExploreConnection {
+            entries,
+            tree: ListState::default().with_selected(Some(0)),
+            ..ExploreConnection::default()
+        })
+    }
+
+    pub fn browse_history(self) -> App<BrowseHistory> {
+        let mut app = self.copy_app_with_new_mode(BrowseHistory::default());
+        app.search_history();
+        app
     }
 
-    pub fn explore_connection(self) -> App<ExploreConnection> {
-        self.copy_app_with_new_mode(ExploreConnection)
+    pub fn browse_library(mut self) -> App<BrowseLibrary> {
+        self.load_library();
+        let first = (!self.queries.library.is_empty()).then_some(0);
+        self.copy_app_with_new_mode(BrowseLibrary {
+            list: ListState::default().with_selected(first),
+        })
     }
 
-    pub fn edit_config(self) -> App<ConfigEditor> {
-        self.copy_app_with_new_mode(ConfigEditor)
+    pub fn confirm_quit(self) -> App<ConfirmQuit> {
+        self.copy_app_with_new_mode(ConfirmQuit)
     }
 }
 
-impl App<EditQuery> {}
+impl App<EditQuery> {
+    fn save_query(&mut self) {
+        self.queries.current_query = self.mode.editor.lines().join("\n");
+    }
+
+    pub fn finish_editing(mut self) -> App<Home> {
+        self.save_query();
+        self.copy_app_with_new_mode(Home)
+    }
+
+    /// Run the statements in the editor against the active connection in
+    /// the background, so the UI keeps drawing while they run. Only the first
+    /// page of each statement's rows is fetched, and only the last
+    /// statement's rows can be asked for after that.
+    pub fn execute_query(mut self) -> Self {
+        self.save_query();
+        self.run_script(&HashMap::new())
+    }
+
+    /// The names of the placeholders in the editor's statements, each once,
+    /// which need values before they can run.
+    pub fn parameters(&self) -> Vec<String> {
+        let script = self.mode.editor.lines().join("\n");
+        let mut names: Vec<String> = Vec::new();
+        for statement in split_statements(&script, self.dialect()) {
+            for placeholder in placeholders(&statement, self.dialect()) {
+                if !names.contains(&placeholder.name) {
+                    names.push(placeholder.name);
+                }
+            }
+        }
+        names
+    }
+
+    /// Ask for the values of the query's placeholders, starting from those
+    /// given last time.
+    pub fn bind_parameters(mut self) -> App<BindParameters> {
+        self.save_query();
+        let inputs = self
+            .parameters()
+            .into_iter()
+            .map(|name| ParameterInput {
+                value: self
+                    .queries
+                    .parameters
+                    .get(&name)
+                    .cloned()
+                    .unwrap_or_default(),
+                name,
+            })
+            .collect();
+        let query = std::mem::take(&mut self.mode);
+        self.copy_app_with_new_mode(BindParameters {
+            inputs,
+            selected: 0,
+            query,
+        })
+    }
+
+    /// Run the query with the values given for its placeholders, by name.
+    fn run_script(mut self, values: &HashMap<String, CellValue>) -> Self {
+        if self
+            .running
+            .as_ref()
+            .is_some_and(|running| running.fetching.is_some())
+        {
+            self.status = Some("A query is already running".to_string());
+            return self;
+        }
+        let Some(open) = self.connections.active() else {
+            self.status = Some("Not connected to a database".to_string());
+            return self;
+        };
+        let (name, connection) = (open.name.clone(), open.connection.clone());
+        let transaction = self
+            .transaction
+            .as_ref()
+            .filter(|transaction| transaction.connection == name)
+            .map(|transaction| transaction.transaction.clone());
+        let dialect = connection.dialect();
+        let statements = split_statements(&self.queries.current_query, dialect);
+        if statements.is_empty() {
+            self.status = Some("Nothing to run".to_string());
+            return self;
+        }
+        let bound = statements
+            .iter()
+            .map(|statement| {
+                let (sql, names) = bind_placeholders(statement, dialect);
+                let parameters = names
+                    .iter()
+                    .map(|name| values.get(name).cloned().unwrap_or(CellValue::Null))
+                    .collect();
+                BoundStatement { sql, parameters }
+            })
+            .collect::<Vec<_>>();
+        let (requests, pending) = mpsc::unbounded_channel();
+        let events = self.events.clone();
+        tokio::spawn({
+            let (name, transaction) = (name.clone(), transaction.clone());
+            async move {
+                let on_output = |index, output| {
+                    let _ = events.send(MainEvent::QueryOutput(name.clone(), Ok((index, output))));
+                };
+                let result = connection
+                    .run_script(transaction.as_ref(), &bound, pending, on_output)
+                    .await;
+                if let Err(error) = result {
+                    let _ = events.send(MainEvent::QueryOutput(name, Err(error.to_string())));
+                }
+            }
+        });
+        // Replacing a previous script drops its requests, which stops it.
+        self.running = Some(RunningQuery {
+            connection: name,
+            fetching: Some(Instant::now()),
+            statements,
+            in_transaction: transaction.is_some(),
+            first_output: true,
+            requests: Some(requests),
+            executed_at: Utc::now(),
+            mark: Instant::now(),
+            durations: Vec::new(),
+            recorded: false,
+            export: None,
+        });
+        self.status = None;
+        self
+    }
+
+    /// Make the next open connection the one queries run against.
+    pub fn next_connection(mut self) -> Self {
+        let count = self.connections.open().len();
+        if let Some(active) = self.connections.active {
+            let next = (active + 1) % count;
+            self.connections.active = Some(next);
+            self.status = Some(format!(
+                "Queries will run on {}",
+                self.connections.open[next].name
+            ));
+            self.load_schema(false);
+        }
+        self
+    }
+
+    pub fn input(mut self, key: KeyEvent) -> Self {
+        let (row, _) = self.mode.editor.cursor();
+        self.mode.editor.input(key);
+        let edited = row.min(self.mode.editor.cursor().0);
+        self.mode.highlighter.invalidate(edited);
+        if self.mode.completion.is_some() {
+            self.mode.completion = self.completions();
+        }
+        self
+    }
+
+    /// The text in the editor and the byte offset of the cursor in it.
+    fn script_and_cursor(&self) -> (String, usize) {
+        let lines = self.mode.editor.lines();
+        let (row, column) = self.mode.editor.cursor();
+        let cursor = lines[..row]
+            .iter()
+            .map(|line| line.len() + 1)
+            .sum::<usize>()
+            + lines[row]
+                .char_indices()
+                .nth(column)
+                .map_or(lines[row].len(), |(i, _)| i);
+        (lines.join("\n"), cursor)
+    }
+
+    /// The statement the cursor is in, or the one before it when the cursor
+    /// is between statements.
+    fn current_statement(&self) -> Option<String> {
+        let (script, cursor) = self.script_and_cursor();
+        let ranges = statement_ranges(&script, self.dialect());
+        let range = ranges
+            .iter()
+            .rev()
+            .find(|range| range.start <= cursor)
+            .or(ranges.first())?;
+        Some(script[range.clone()].trim().to_string())
+    }
+
+    /// What the word before the cursor could be completed with, from the
+    /// active connection's schema.
+    fn completions(&self) -> Option<CompletionPopup> {
+        let (script, cursor) = self.script_and_cursor();
+        let (replaces, completions) = complete(&script, cursor, self.dialect(), self.schema());
+        (!completions.is_empty()).then(|| CompletionPopup {
+            completions,
+            list: ListState::default().with_selected(Some(0)),
+            replaces,
+        })
+    }
+
+    /// Open the completion popup, or complete the word straight away when
+    /// there is only one way to.
+    pub fn complete(mut self) -> Self {
+        self.mode.completion = self.completions();
+        match &self.mode.completion {
+            None => self.status = Some("No completions".to_string()),
+            Some(popup) if popup.completions.len() == 1 => return self.accept_completion(),
+            Some(_) => {}
+        }
+        self
+    }
+
+    pub fn select_completion(mut self, forward: bool) -> Self {
+        if let Some(popup) = &mut self.mode.completion {
+            let count = popup.completions.len();
+            let selected = popup.list.selected().unwrap_or(0);
+            popup.list.select(Some(match forward {
+                true => (selected + 1) % count,
+                false => (selected + count - 1) % count,
+            }));
+        }
+        self
+    }
+
+    pub fn accept_completion(mut self) -> Self {
+        let Some(popup) = self.mode.completion.take() else {
+            return self;
+        };
+        let selected = popup.list.selected().unwrap_or(0);
+        if let Some(completion) = popup.completions.get(selected) {
+            for _ in 0..popup.replaces {
+                self.mode.editor.delete_char();
+            }
+            self.mode.editor.insert_str(&completion.text);
+            let (row, _) = self.mode.editor.cursor();
+            self.mode.highlighter.invalidate(row);
+        }
+        self
+    }
+
+    pub fn close_completion(mut self) -> Self {
+        self.mode.completion = None;
+        self
+    }
+}
+
+impl App<BindParameters> {
+    pub fn select_parameter(mut self, forward: bool) -> Self {
+        let count = self.mode.inputs.len();
+        self.mode.selected = match forward {
+            true => (self.mode.selected + 1) % count,
+            false => (self.mode.selected + count - 1) % count,
+        };
+        self
+    }
+
+    pub fn parameter_input(mut self, key: KeyEvent) -> Self {
+        if let Some(input) = self.mode.inputs.get_mut(self.mode.selected) {
+            match key.code {
+                KeyCode::Char(c) => input.value.push(c),
+                KeyCode::Backspace => {
+                    input.value.pop();
+                }
+                _ => {}
+            }
+        }
+        self
+    }
+
+    /// Run the query with the values given, remembering them for next time.
+    pub fn run(mut self) -> App<EditQuery> {
+        let mut values = HashMap::new();
+        for input in std::mem::take(&mut self.mode.inputs) {
+            values.insert(input.name.clone(), CellValue::from_input(&input.value));
+            self.queries.parameters.insert(input.name, input.value);
+        }
+        self.back_to_editor().run_script(&values)
+    }
+
+    pub fn back_to_editor(mut self) -> App<EditQuery> {
+        let query = std::mem::take(&mut self.mode.query);
+        self.copy_app_with_new_mode(query)
+    }
+}
 
 impl App<BrowseSqliteDBFiles> {
-    pub async fn open_sqlite_db(self, file: &str) -> App<Home> {
-        let sqlite_pool = Connections::connect_to_sqlite_db(file).await;
-        App {
-            mode: Home,
-            connections: sqlite_pool,
-            results: self.results,
-            queries: self.queries,
+    pub fn browse_files(mut self, key: KeyEvent) -> Self {
+        if let Some(explorer) = &mut self.mode.explorer {
+            if let Err(error) = explorer.handle(&Event::Key(key)) {
+                self.status = Some(format!("Could not list the files there: {}", error));
+            }
         }
+        self
+    }
+
+    fn selected_file(&self) -> Option<&ratatui_explorer::File> {
+        self.mode
+            .explorer
+            .as_ref()
+            .map(FileExplorer::current)
+            .filter(|file| !file.is_dir())
+    }
+
+    pub fn selected_directory(&self) -> bool {
+        self.mode
+            .explorer
+            .as_ref()
+            .is_some_and(|explorer| explorer.current().is_dir())
+    }
+
+    /// Open the file selected as a database in the background. The
+    /// connection comes back as a `MainEvent::Connected`.
+    pub fn open_sqlite_db(self) -> App<Home> {
+        let Some(file) = self.selected_file() else {
+            return self.cancel();
+        };
+        let file = file.path().display().to_string();
+        let events = self.events.clone();
+        tokio::spawn(async move {
+            let result = Connection::connect(&file_url(&file))
+                .await
+                .map_err(|error| error.to_string());
+            let _ = events.send(MainEvent::Connected(file, result));
+        });
+        let mut app = self.cancel();
+        app.status = Some("Opening the database...".to_string());
+        app
     }
 }
 
-impl App<ExploreResults> {}
+impl App<ExploreResults> {
+    pub fn results_table(&mut self) -> (&[StatementResult], usize, &mut TableState, &mut Grid) {
+        (
+            &self.results,
+            self.selected_result,
+            &mut self.mode.table,
+            &mut self.mode.grid,
+        )
+    }
+
+    /// Move the selected row, fetching the next page of the running query
+    /// once the selection gets within half a page of the last row loaded.
+    pub fn scroll_results(mut self, rows: isize) -> Self {
+        let count = self.results().len();
+        if count == 0 {
+            return self;
+        }
+        let selected = self.mode.table.selected().unwrap_or(0);
+        let selected = selected.saturating_add_signed(rows).min(count - 1);
+        self.mode.table.select(Some(selected));
+        if selected + PAGE_SIZE / 2 >= count && self.more_rows() {
+            self = self.fetch_more();
+        }
+        self
+    }
+
+    /// Show the next or previous statement's tab, wrapping around.
+    pub fn switch_result(mut self, forward: bool) -> Self {
+        let count = self.results.len();
+        if count > 1 {
+            self.selected_result = match forward {
+                true => (self.selected_result + 1) % count,
+                false => (self.selected_result + count - 1) % count,
+            };
+            let first = (!self.results().is_empty()).then_some(0);
+            self.mode.table = TableState::default().with_selected(first);
+            self.mode.grid = Grid::default();
+        }
+        self
+    }
+
+    /// Pick out the next or previous column of the selected row.
+    pub fn move_column(mut self, forward: bool) -> Self {
+        let count = match self.results() {
+            Results::Some { headers, .. } => headers.len(),
+            Results::None => 0,
+        };
+        let grid = &mut self.mode.grid;
+        grid.column = match forward {
+            true => (grid.column + 1).min(count.saturating_sub(1)),
+            false => grid.column.saturating_sub(1),
+        };
+        self
+    }
 
-impl App<ExploreConnection> {}
+    /// Widen or narrow the column that is picked out by a number of cells.
+    pub fn resize_column(mut self, change: i16) -> Self {
+        let column = self.mode.grid.column;
+        let fitted = match self.results() {
+            Results::Some { widths, .. } => widths.get(column).copied(),
+            Results::None => None,
+        };
+        if let Some(fitted) = fitted {
+            let widths = &mut self.mode.grid.widths;
+            let width = widths.get(&column).copied().unwrap_or(fitted);
+            widths.insert(column, width.saturating_add_signed(change).clamp(1, 500));
+        }
+        self
+    }
 
-impl App<ConfigEditor> {}
+    /// Freeze the columns up to the one picked out so they stay in view as
+    /// the others scroll, or unfreeze them if they already are.
+    pub fn freeze_columns(mut self) -> Self {
+        let grid = &mut self.mode.grid;
+        grid.frozen = match grid.frozen == grid.column + 1 {
+            true => 0,
+            false => grid.column + 1,
+        };
+        self
+    }
+
+    /// Size the column that is picked out to fit its values again.
+    pub fn fit_column(mut self) -> Self {
+        let column = self.mode.grid.column;
+        self.mode.grid.widths.remove(&column);
+        self
+    }
+}
+
+/// A row in the connection explorer: an open connection, or a profile from
+/// the config file that is not open yet.
+pub enum ConnectionEntry<'a> {
+    Open(usize, &'a OpenConnection),
+    Profile(&'a str, &'a Profile),
+}
+
+impl App<ExploreConnection> {
+    pub fn entries(&self) -> Vec<ConnectionEntry<'_>> {
+        let open = self
+            .connections
+            .open()
+            .iter()
+            .enumerate()
+            .map(|(index, open)| ConnectionEntry::Open(index, open));
+        let profiles = self
+            .config
+            .profiles
+            .iter()
+            .filter(|(name, _)| self.connections.position(name).is_none())
+            .map(|(name, profile)| ConnectionEntry::Profile(name, profile));
+        open.chain(profiles).collect()
+    }
+
+    pub fn select_next_entry(mut self) -> Self {
+        let count = self.entries().len();
+        if count > 0 {
+            let next = self.mode.entries.selected().map_or(0, |i| (i + 1) % count);
+            self.mode.entries.select(Some(next));
+        }
+        self
+    }
+
+    pub fn select_previous_entry(mut self) -> Self {
+        let count = self.entries().len();
+        if count > 0 {
+            let previous = self
+                .mode
+                .entries
+                .selected()
+                .map_or(0, |i| (i + count - 1) % count);
+            self.mode.entries.select(Some(previous));
+        }
+        self
+    }
+
+    /// Make the highlighted connection active, or connect to the highlighted
+    /// profile in the background. A new connection comes back as a
+    /// `MainEvent::Connected`.
+    pub fn connect_to_selected(mut self) -> Self {
+        let selected = self.mode.entries.selected().unwrap_or(0);
+        match self.entries().into_iter().nth(selected) {
+            Some(ConnectionEntry::Open(index, open)) => {
+                self.status = Some(format!("Switched to {}", open.name));
+                self.connections.active = Some(index);
+                self.mode.tree.select(Some(0));
+                self.load_schema(false);
+            }
+            Some(ConnectionEntry::Profile(name, profile)) => {
+                let (name, profile) = (name.to_string(), profile.clone());
+                self.open_profile(name, &profile);
+            }
+            None => {}
+        }
+        self
+    }
+
+    pub fn disconnect_selected(mut self) -> Self {
+        let selected = self.mode.entries.selected().unwrap_or(0);
+        if let Some(closed) = self.connections.disconnect(selected) {
+            self.status = Some(format!("Disconnected from {}", closed.name));
+            self.schemas.remove(&closed.name);
+            if let Some(transaction) = &self.transaction {
+                if transaction.connection == closed.name {
+                    self.transaction = None;
+                    self.status = Some(format!(
+                        "Disconnected from {}, rolling back its transaction",
+                        closed.name
+                    ));
+                }
+            }
+        }
+        self
+    }
+}
+
+/// A visible row of the schema tree.
+pub struct SchemaNode<'a> {
+    pub depth: usize,
+    /// Whether the node is expanded, or `None` if it has no children.
+    pub expanded: Option<bool>,
+    pub item: SchemaItem<'a>,
+    path: String,
+}
+
+pub enum SchemaItem<'a> {
+    /// A heading for a kind of object, with how many there are.
+    Group(&'static str, usize),
+    Relation(&'a Relation),
+    Column(&'a ColumnInfo),
+    Dependent(&'a Dependent),
+    /// A line describing an index or trigger.
+    Detail(String),
+}
+
+impl App<ExploreConnection> {
+    /// The rows of the schema tree that are visible with the nodes expanded
+    /// so far.
+    pub fn schema_nodes(&self) -> Vec<SchemaNode<'_>> {
+        let Some(schema) = self.schema() else {
+            return Vec::new();
+        };
+        let expanded = &self.mode.expanded;
+        let mut nodes = Vec::new();
+        let mut node = |depth, path: String, has_children: bool, item| {
+            let open = has_children && expanded.contains(&path);
+            nodes.push(SchemaNode {
+                depth,
+                expanded: has_children.then_some(open),
+                item,
+                path,
+            });
+            open
+        };
+        for (group, relations) in [("Tables", &schema.tables), ("Views", &schema.views)] {
+            let item = SchemaItem::Group(group, relations.len());
+            if !node(0, group.to_string(), !relations.is_empty(), item) {
+                continue;
+            }
+            for relation in relations {
+                let path = format!("{}/{}", group, relation.qualified_name());
+                let item = SchemaItem::Relation(relation);
+                if node(1, path, !relation.columns.is_empty(), item) {
+                    for column in &relation.columns {
+                        node(2, String::new(), false, SchemaItem::Column(column));
+                    }
+                }
+            }
+        }
+        for (group, dependents) in [("Indexes", &schema.indexes), ("Triggers", &schema.triggers)] {
+            let item = SchemaItem::Group(group, dependents.len());
+            if !node(0, group.to_string(), !dependents.is_empty(), item) {
+                continue;
+            }
+            for dependent in dependents {
+                let path = format!("{}/{}", group, dependent.name);
+                if node(1, path, true, SchemaItem::Dependent(dependent)) {
+                    let table = SchemaItem::Detail(format!("on {}", dependent.table));
+                    node(2, String::new(), false, table);
+                    if !dependent.detail.is_empty() {
+                        let detail = SchemaItem::Detail(dependent.detail.clone());
+                        node(2, String::new(), false, detail);
+                    }
+                }
+            }
+        }
+        nodes
+    }
+
+    pub fn toggle_schema_focus(mut self) -> Self {
+        self.mode.schema_focused = !self.mode.schema_focused;
+        self
+    }
+
+    pub fn select_next_node(mut self) -> Self {
+        let count = self.schema_nodes().len();
+        let selected = self.mode.tree.selected().unwrap_or(0);
+        self.mode
+            .tree
+            .select(Some((selected + 1).min(count.saturating_sub(1))));
+        self
+    }
+
+    pub fn select_previous_node(mut self) -> Self {
+        let selected = self.mode.tree.selected().unwrap_or(0);
+        self.mode.tree.select(Some(selected.saturating_sub(1)));
+        self
+    }
+
+    pub fn expand_node(mut self) -> Self {
+        let selected = self.mode.tree.selected().unwrap_or(0);
+        let path = match self.schema_nodes().get(selected) {
+            Some(node) if node.expanded == Some(false) => node.path.clone(),
+            _ => return self,
+        };
+        self.mode.expanded.insert(path);
+        self
+    }
+
+    /// Collapse the selected node, or move to its parent if it is not
+    /// expanded.
+    pub fn collapse_node(mut self) -> Self {
+        let selected = self.mode.tree.selected().unwrap_or(0);
+        let nodes = self.schema_nodes();
+        let Some(node) = nodes.get(selected) else {
+            return self;
+        };
+        if node.expanded == Some(true) {
+            let path = node.path.clone();
+            self.mode.expanded.remove(&path);
+        } else if let Some(parent) = nodes[..selected]
+            .iter()
+            .rposition(|parent| parent.depth < node.depth)
+        {
+            self.mode.tree.select(Some(parent));
+        }
+        self
+    }
+
+    /// The table or view of the selected node, or of the column selected.
+    fn selected_relation(&self) -> Option<(&Relation, bool)> {
+        let nodes = self.schema_nodes();
+        let selected = self.mode.tree.selected()?.min(nodes.len().checked_sub(1)?);
+        let relation = nodes[..=selected]
+            .iter()
+            .rev()
+            .take_while(|node| !matches!(node.item, SchemaItem::Group(..)))
+            .find_map(|node| match node.item {
+                SchemaItem::Relation(relation) => Some(relation),
+                _ => None,
+            })?;
+        let schema = self.schema()?;
+        let is_view = schema
+            .views
+            .iter()
+            .any(|view| view.schema == relation.schema && view.name == relation.name);
+        Some((relation, is_view))
+    }
+
+    /// Whether a table is selected and its connection is still open.
+    pub fn can_inspect(&self) -> bool {
+        self.selected_relation().is_some() && self.connections.active().is_some()
+    }
+
+    /// Inspect the selected table, reading its definition and row count in
+    /// the background. They come back as a `MainEvent::TableDetails`.
+    pub fn inspect_selected(self) -> App<InspectTable> {
+        let (relation, is_view) = self.selected_relation().expect("no table selected");
+        let open = self.connections.active().expect("not connected");
+        let (relation, name, connection) =
+            (relation.clone(), open.name.clone(), open.connection.clone());
+        let events = self.events.clone();
+        tokio::spawn({
+            let relation = relation.clone();
+            async move {
+                let result = load_table_details(&connection, &relation, is_view)
+                    .await
+                    .map_err(|error| error.to_string());
+                let table = relation.qualified_name();
+                let _ = events.send(MainEvent::TableDetails(name, table, result));
+            }
+        });
+        let mut app = self;
+        let explorer = std::mem::take(&mut app.mode);
+        app.copy_app_with_new_mode(InspectTable {
+            relation,
+            is_view,
+            details: None,
+            scroll: 0,
+            explorer,
+        })
+    }
+
+    pub fn refresh_schema(mut self) -> Self {
+        self.load_schema(true);
+        self
+    }
+}
+
+impl App<InspectTable> {
+    pub fn table_details(
+        mut self,
+        connection: String,
+        table: String,
+        result: Result<TableDetails, String>,
+    ) -> Self {
+        let active = self.connections.active().map(|open| open.name.as_str());
+        if active != Some(connection.as_str()) || table != self.mode.relation.qualified_name() {
+            return self;
+        }
+        match result {
+            Ok(details) => self.mode.details = Some(details),
+            Err(error) => {
+                self.status = Some(format!(
+                    "Could not read the definition of {}: {}",
+                    table, error
+                ))
+            }
+        }
+        self
+    }
+
+    pub fn scroll_details(mut self, lines: i16) -> Self {
+        self.mode.scroll = self.mode.scroll.saturating_add_signed(lines);
+        self
+    }
+
+    pub fn finish_inspecting(self) -> App<ExploreConnection> {
+        let mut app = self;
+        let explorer = std::mem::take(&mut app.mode.explorer);
+        app.copy_app_with_new_mode(explorer)
+    }
+}
+
+impl App<ConfirmQuit> {
+    /// Quit, closing the transaction's connection so the database rolls it
+    /// back.
+    pub fn rollback_and_quit(mut self) -> App<Quit> {
+        self.running = None;
+        self.transaction = None;
+        self.quit()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(text: &str) -> CellValue {
+        CellValue::Text(text.to_string())
+    }
+
+    fn headers(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn to_line_puts_line_breaks_and_tabs_on_one_line() {
+        assert_eq!(text("one\ntwo").to_line(), "one two");
+        assert_eq!(text("one\r\ntwo").to_line(), "one two");
+        assert_eq!(text("one\rtwo\tthree").to_line(), "one two three");
+        assert_eq!(text("a\n\nb\t").to_line(), "a  b ");
+        assert_eq!(text("plain").to_line(), "plain");
+        assert_eq!(CellValue::Integer(5).to_line(), "5");
+    }
+
+    #[test]
+    fn column_widths_stay_between_null_and_the_widest() {
+        let data = vec![vec![
+            text("x"),
+            text(&"y".repeat(100)),
+            CellValue::Integer(12345),
+        ]];
+        assert_eq!(
+            column_widths(&headers(&["a", "b", "c"]), &data),
+            [4, MAX_COLUMN_WIDTH, 5]
+        );
+        assert_eq!(column_widths(&headers(&["a header"]), &[]), [8]);
+    }
+
+    #[test]
+    fn column_widths_only_look_at_the_first_rows() {
+        let mut data = vec![vec![text("short")]; WIDTH_SAMPLE];
+        data.push(vec![text("a good deal longer")]);
+        assert_eq!(column_widths(&headers(&["a"]), &data), [5]);
+        data.swap(0, WIDTH_SAMPLE);
+        assert_eq!(column_widths(&headers(&["a"]), &data), [18]);
+    }
+
+    #[test]
+    fn column_widths_count_display_cells() {
+        let data = vec![vec![text("日本語"), text("é"), text("a\tb")]];
+        assert_eq!(
+            column_widths(&headers(&["漢字漢字漢字", "b", "c"]), &data),
+            [12, 4, 4]
+        );
+    }
+
+    #[test]
+    fn pages_widen_the_columns_while_they_are_sampled() {
+        let mut results = Results::new(headers(&["a"]), vec![vec![text("x")]]);
+        results.append(Results::new(headers(&["a"]), vec![vec![text("wider")]]));
+        let Results::Some { widths, .. } = &results else {
+            panic!("no results");
+        };
+        assert_eq!(widths, &[5]);
+
+        let full = vec![vec![text("x")]; WIDTH_SAMPLE];
+        let mut results = Results::new(headers(&["a"]), full);
+        results.append(Results::new(headers(&["a"]), vec![vec![text("wider")]]));
+        let Results::Some { widths, .. } = &results else {
+            panic!("no results");
+        };
+        assert_eq!(widths, &[4]);
+    }
+}
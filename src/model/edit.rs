@@ -0,0 +1,505 @@
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::widgets::TableState;
+
+use super::{
+    App, CellValue, DeleteRows, EditCell, ExploreResults, Grid, InsertRow, Outcome, Results,
+    RowAction, StatementResult,
+};
+use crate::database::BoundStatement;
+use crate::edit::{change_rows, input_text, input_value, ChangedRows, RowChange, RowSource};
+use crate::MainEvent;
+
+impl<T> App<T> {
+    /// Run statements that change rows of the tab being shown in the
+    /// background, in the open transaction if it is on the same connection.
+    /// What the last of them gives back comes as a `MainEvent::RowsChanged`.
+    fn change_rows(
+        &mut self,
+        source: RowSource,
+        change: RowChange,
+        statements: Vec<BoundStatement>,
+    ) {
+        let name = self.results_connection.clone().unwrap_or_default();
+        let Some(open) = self
+            .connections
+            .open()
+            .iter()
+            .find(|open| open.name == name)
+        else {
+            self.status = Some(format!("{} is no longer open", name));
+            return;
+        };
+        let connection = open.connection.clone();
+        let in_transaction = self
+            .transaction
+            .as_ref()
+            .is_some_and(|transaction| transaction.connection == name);
+        if in_transaction && !self.stop_idle_query() {
+            return;
+        }
+        let transaction = match &mut self.transaction {
+            Some(open) if in_transaction => {
+                open.pending += 1;
+                Some(open.transaction.clone())
+            }
+            _ => None,
+        };
+        self.status = Some(format!("Changing {}...", source.table));
+        let changed = ChangedRows {
+            source,
+            statement: self.results[self.selected_result].statement.clone(),
+            tab: self.selected_result,
+            change,
+        };
+        let events = self.events.clone();
+        tokio::spawn(async move {
+            let result = change_rows(&connection, transaction.as_ref(), &statements)
+                .await
+                .map_err(|error| error.to_string());
+            let _ = events.send(MainEvent::RowsChanged(changed, result));
+        });
+    }
+
+    /// Bring the tab whose rows were changed up to date with what came
+    /// back, unless the results have been replaced since.
+    pub fn rows_changed(
+        mut self,
+        changed: ChangedRows,
+        result: Result<Vec<Vec<CellValue>>, String>,
+    ) -> Self {
+        let ChangedRows {
+            source,
+            statement,
+            tab,
+            change,
+        } = changed;
+        let table = &source.table;
+        let rows = match result {
+            Ok(rows) => rows,
+            Err(error) => {
+                self.status = Some(format!("Could not change {}: {}", table, error));
+                return self;
+            }
+        };
+        let data = match self.results.get_mut(tab) {
+            Some(StatementResult {
+                statement: shown,
+                outcome: Outcome::Rows(Results::Some { data, .. }),
+                ..
+            }) if *shown == statement => Some(data),
+            _ => None,
+        };
+        self.status = Some(match change {
+            RowChange::Updated(_) if rows.is_empty() => {
+                format!("No row of {} has that key any more", table)
+            }
+            RowChange::Updated(row) => {
+                if let Some(old) = data.and_then(|data| data.get_mut(row)) {
+                    let fetched = rows.into_iter().next().unwrap_or_default();
+                    *old = source.refreshed(old, fetched);
+                }
+                format!("Updated a row of {}", table)
+            }
+            // MySQL has no RETURNING to read the row back with.
+            RowChange::Inserted(_) if rows.is_empty() => {
+                format!(
+                    "Inserted a row into {}, run the query again to see it",
+                    table
+                )
+            }
+            RowChange::Inserted(position) => {
+                if let Some(data) = data {
+                    let empty = vec![CellValue::Null; source.columns.len()];
+                    let position = position.min(data.len());
+                    let inserted = rows.into_iter().map(|row| source.refreshed(&empty, row));
+                    data.splice(position..position, inserted);
+                }
+                format!("Inserted a row into {}", table)
+            }
+            RowChange::Deleted(deleted) => {
+                if let Some(data) = data {
+                    for &row in deleted.iter().rev() {
+                        if row < data.len() {
+                            data.remove(row);
+                        }
+                    }
+                }
+                match deleted.len() {
+                    1 => format!("Deleted a row of {}", table),
+                    count => format!("Deleted {} rows of {}", count, table),
+                }
+            }
+        });
+        if let Some(StatementResult {
+            outcome: Outcome::Rows(results),
+            ..
+        }) = self.results.get_mut(tab)
+        {
+            results.fit_widths();
+        }
+        self
+    }
+}
+
+impl App<ExploreResults> {
+    /// Mark the selected row to be deleted with the others marked, or
+    /// unmark it, and move on to the next.
+    pub fn mark_row(mut self) -> Self {
+        if let Some(row) = self.mode.table.selected() {
+            let marked = &mut self.mode.grid.marked;
+            if !marked.remove(&row) {
+                marked.insert(row);
+            }
+        }
+        self.scroll_results(1)
+    }
+
+    /// The marked rows, or the selected row if none are marked.
+    fn target_rows(&self) -> Vec<usize> {
+        match self.mode.grid.marked.is_empty() {
+            true => self.mode.table.selected().into_iter().collect(),
+            false => self.mode.grid.marked.iter().copied().collect(),
+        }
+    }
+
+    /// The table the rows being shown came from, or why they cannot be
+    /// changed.
+    fn row_source(&self) -> Result<RowSource, String> {
+        let Some(StatementResult {
+            statement,
+            outcome: Outcome::Rows(Results::Some { headers, .. }),
+            ..
+        }) = self.results.get(self.selected_result)
+        else {
+            return Err("There are no rows to change".to_string());
+        };
+        let name = self.results_connection.as_deref().unwrap_or_default();
+        let Some(open) = self
+            .connections
+            .open()
+            .iter()
+            .find(|open| open.name == name)
+        else {
+            return Err(format!("{} is no longer open", name));
+        };
+        let Some(Some(schema)) = self.schemas.get(name) else {
+            return Err(format!(
+                "Reading the schema of {}, try again in a moment",
+                name
+            ));
+        };
+        RowSource::find(statement, headers, schema, open.connection.dialect())
+    }
+
+    /// The table the rows being shown came from, if the action can be done
+    /// to it with the row and column selected, or else why not.
+    fn check_action(&self, action: RowAction) -> Result<RowSource, String> {
+        let source = self.row_source()?;
+        let Results::Some { headers, data, .. } = self.results() else {
+            return Err("There are no rows to change".to_string());
+        };
+        let row = self.mode.table.selected().and_then(|row| data.get(row));
+        match (action, row) {
+            (RowAction::Insert, _) => {}
+            (_, None) => return Err("No row is selected".to_string()),
+            (RowAction::Edit, Some(row)) => {
+                let column = self.mode.grid.column.min(headers.len().saturating_sub(1));
+                if source.columns[column].is_none() {
+                    return Err(format!(
+                        "{} is not a column of {}",
+                        headers[column], source.table
+                    ));
+                }
+                if matches!(row[column], CellValue::Blob(_)) {
+                    return Err("Blobs cannot be changed here".to_string());
+                }
+            }
+            (RowAction::Duplicate | RowAction::Delete, Some(_)) => {}
+        }
+        Ok(source)
+    }
+
+    pub fn can_change(&self, action: RowAction) -> bool {
+        self.check_action(action).is_ok()
+    }
+
+    /// Say why the action cannot be done, reading the schema it needs if it
+    /// has not been read.
+    pub fn cannot_change(mut self, action: RowAction) -> Self {
+        if let Err(error) = self.check_action(action) {
+            self.status = Some(error);
+        }
+        if let Some(name) = self.results_connection.clone() {
+            if self.connections.position(&name).is_some() && !self.schemas.contains_key(&name) {
+                self.schemas.insert(name.clone(), None);
+                self.read_schema(&name);
+            }
+        }
+        self
+    }
+
+    pub fn edit_cell(mut self) -> App<EditCell> {
+        let source = self
+            .check_action(RowAction::Edit)
+            .expect("checked by can_change");
+        let row = self.mode.table.selected().unwrap_or_default();
+        let column = self.mode.grid.column.min(source.columns.len() - 1);
+        let input = match self.results() {
+            Results::Some { data, .. } => input_text(&data[row][column], source.field(column)),
+            Results::None => String::new(),
+        };
+        let results = std::mem::take(&mut self.mode);
+        self.copy_app_with_new_mode(EditCell {
+            source,
+            row,
+            column,
+            input,
+            update: None,
+            results,
+        })
+    }
+
+    /// Fill in a new row, starting from the selected one when duplicating
+    /// it. Its key is left for the database to give it where it can.
+    pub fn insert_row(mut self, duplicate: bool) -> App<InsertRow> {
+        let source = self
+            .check_action(RowAction::Insert)
+            .expect("checked by can_change");
+        let selected = self.mode.table.selected();
+        let row = match (self.results(), selected) {
+            (Results::Some { data, .. }, Some(row)) if duplicate => data.get(row),
+            _ => None,
+        };
+        let inputs = source
+            .fields
+            .iter()
+            .map(|field| {
+                let value = source
+                    .columns
+                    .iter()
+                    .position(|column| column.as_deref() == Some(field.name.as_str()))
+                    .and_then(|index| row?.get(index));
+                match value {
+                    Some(CellValue::Blob(_)) | None => String::new(),
+                    Some(_) if field.primary_key => String::new(),
+                    Some(value) => input_text(value, Some(field)),
+                }
+            })
+            .collect();
+        let position = selected.map_or(self.results().len(), |row| row + 1);
+        let results = std::mem::take(&mut self.mode);
+        self.copy_app_with_new_mode(InsertRow {
+            source,
+            inputs,
+            selected: 0,
+            insert: None,
+            position,
+            results,
+        })
+    }
+
+    /// Show the statement that deletes the marked rows, or the selected
+    /// one, to be confirmed.
+    pub fn delete_rows(mut self) -> App<DeleteRows> {
+        let source = self
+            .check_action(RowAction::Delete)
+            .expect("checked by can_change");
+        let rows = self.target_rows();
+        let delete = match self.results() {
+            Results::Some { data, .. } => {
+                let values = rows
+                    .iter()
+                    .filter_map(|&row| data.get(row).map(Vec::as_slice))
+                    .collect::<Vec<_>>();
+                source.delete(&values)
+            }
+            Results::None => source.delete(&[]),
+        };
+        let results = std::mem::take(&mut self.mode);
+        self.copy_app_with_new_mode(DeleteRows {
+            source,
+            rows,
+            delete,
+            results,
+        })
+    }
+}
+
+impl App<EditCell> {
+    pub fn results_table(&mut self) -> (&[StatementResult], usize, &mut TableState, &mut Grid) {
+        let results = &mut self.mode.results;
+        (
+            &self.results,
+            self.selected_result,
+            &mut results.table,
+            &mut results.grid,
+        )
+    }
+
+    pub fn cell_input(mut self, key: KeyEvent) -> Self {
+        match key.code {
+            KeyCode::Char(c) => self.mode.input.push(c),
+            KeyCode::Backspace => {
+                self.mode.input.pop();
+            }
+            _ => {}
+        }
+        self
+    }
+
+    /// The row being changed, as it was read.
+    pub fn edited_row(&self) -> &[CellValue] {
+        match self.results() {
+            Results::Some { data, .. } => data.get(self.mode.row).map_or(&[], Vec::as_slice),
+            Results::None => &[],
+        }
+    }
+
+    /// The value typed in, read as the type of the column it goes into.
+    pub fn edited_value(&self) -> CellValue {
+        input_value(&self.mode.input, self.mode.source.field(self.mode.column))
+    }
+
+    /// Write the statement that makes the change, to be confirmed.
+    pub fn review_change(mut self) -> Self {
+        let value = self.edited_value();
+        let update = self
+            .mode
+            .source
+            .update(self.edited_row(), self.mode.column, value);
+        self.mode.update = Some(update);
+        self
+    }
+
+    pub fn keep_editing(mut self) -> Self {
+        self.mode.update = None;
+        self
+    }
+
+    /// Make the change and read the row back, by its new key if the change
+    /// was to the key.
+    pub fn save_cell(mut self) -> App<ExploreResults> {
+        let Some((update, _)) = self.mode.update.take() else {
+            return self.back_to_results();
+        };
+        let mut row = self.edited_row().to_vec();
+        if let Some(value) = row.get_mut(self.mode.column) {
+            *value = self.edited_value();
+        }
+        let select = self.mode.source.select(&row);
+        let source = self.mode.source.clone();
+        let change = RowChange::Updated(self.mode.row);
+        self.change_rows(source, change, vec![update, select]);
+        self.back_to_results()
+    }
+
+    pub fn back_to_results(mut self) -> App<ExploreResults> {
+        let results = std::mem::take(&mut self.mode.results);
+        self.copy_app_with_new_mode(results)
+    }
+}
+
+impl App<InsertRow> {
+    pub fn results_table(&mut self) -> (&[StatementResult], usize, &mut TableState, &mut Grid) {
+        let results = &mut self.mode.results;
+        (
+            &self.results,
+            self.selected_result,
+            &mut results.table,
+            &mut results.grid,
+        )
+    }
+
+    pub fn select_field(mut self, forward: bool) -> Self {
+        let count = self.mode.inputs.len();
+        if count > 0 {
+            self.mode.selected = match forward {
+                true => (self.mode.selected + 1) % count,
+                false => (self.mode.selected + count - 1) % count,
+            };
+        }
+        self
+    }
+
+    pub fn field_input(mut self, key: KeyEvent) -> Self {
+        if let Some(input) = self.mode.inputs.get_mut(self.mode.selected) {
+            match key.code {
+                KeyCode::Char(c) => input.push(c),
+                KeyCode::Backspace => {
+                    input.pop();
+                }
+                _ => {}
+            }
+        }
+        self
+    }
+
+    /// Write the statement that inserts the row, to be confirmed.
+    pub fn review_insert(mut self) -> Self {
+        let values = self
+            .mode
+            .source
+            .fields
+            .iter()
+            .zip(&self.mode.inputs)
+            .filter(|(_, input)| !input.is_empty())
+            .map(|(field, input)| (field.name.clone(), input_value(input, Some(field))))
+            .collect();
+        self.mode.insert = Some(self.mode.source.insert(values));
+        self
+    }
+
+    pub fn keep_editing(mut self) -> Self {
+        self.mode.insert = None;
+        self
+    }
+
+    /// Insert the row, putting it in the results after the selected row
+    /// once it comes back.
+    pub fn insert(mut self) -> App<ExploreResults> {
+        let Some((insert, _)) = self.mode.insert.take() else {
+            return self.back_to_results();
+        };
+        let source = self.mode.source.clone();
+        let change = RowChange::Inserted(self.mode.position);
+        self.change_rows(source, change, vec![insert]);
+        self.back_to_results()
+    }
+
+    pub fn back_to_results(mut self) -> App<ExploreResults> {
+        let results = std::mem::take(&mut self.mode.results);
+        self.copy_app_with_new_mode(results)
+    }
+}
+
+impl App<DeleteRows> {
+    pub fn results_table(&mut self) -> (&[StatementResult], usize, &mut TableState, &mut Grid) {
+        let results = &mut self.mode.results;
+        (
+            &self.results,
+            self.selected_result,
+            &mut results.table,
+            &mut results.grid,
+        )
+    }
+
+    /// Delete the rows, selecting the row that will take the place of the
+    /// first of them.
+    pub fn delete(mut self) -> App<ExploreResults> {
+        let rows = std::mem::take(&mut self.mode.rows);
+        let left = self.results().len().saturating_sub(rows.len());
+        let selected = rows.first().map(|&first| first.min(left.saturating_sub(1)));
+        let results = &mut self.mode.results;
+        results.grid.marked.clear();
+        results.table.select(selected.filter(|_| left > 0));
+        let source = self.mode.source.clone();
+        let delete = self.mode.delete.0.clone();
+        self.change_rows(source, RowChange::Deleted(rows), vec![delete]);
+        self.back_to_results()
+    }
+
+    pub fn back_to_results(mut self) -> App<ExploreResults> {
+        let results = std::mem::take(&mut self.mode.results);
+        self.copy_app_with_new_mode(results)
+    }
+}
@@ -0,0 +1,88 @@
+use super::{App, CellValue, EditQuery, ExplainQuery};
+use crate::database::BoundStatement;
+use crate::plan::{explain, PlanNode};
+use crate::script::bind_placeholders;
+use crate::MainEvent;
+
+impl App<EditQuery> {
+    /// Whether there is a statement to explain and a connection to explain
+    /// it on.
+    pub fn can_explain(&self) -> bool {
+        self.connections.active().is_some() && self.current_statement().is_some()
+    }
+
+    pub fn cannot_explain(mut self) -> Self {
+        self.status = Some(match self.connections.active() {
+            Some(_) => "Nothing to explain".to_string(),
+            None => "Not connected to a database".to_string(),
+        });
+        self
+    }
+
+    /// Ask the database how it would run the statement under the cursor,
+    /// in the background and with the values last given for its
+    /// parameters. The plan comes back as a `MainEvent::Plan`.
+    pub fn explain_statement(mut self) -> App<ExplainQuery> {
+        self.save_query();
+        let statement = self.current_statement().expect("no statement to explain");
+        let open = self.connections.active().expect("not connected");
+        let (name, connection) = (open.name.clone(), open.connection.clone());
+        let transaction = self
+            .transaction
+            .as_ref()
+            .filter(|transaction| transaction.connection == name)
+            .map(|transaction| transaction.transaction.clone());
+        let (sql, names) = bind_placeholders(&statement, connection.dialect());
+        let parameters = names
+            .iter()
+            .map(|name| {
+                self.queries
+                    .parameters
+                    .get(name)
+                    .map_or(CellValue::Null, |value| CellValue::from_input(value))
+            })
+            .collect();
+        let events = self.events.clone();
+        tokio::spawn({
+            let name = name.clone();
+            async move {
+                let bound = BoundStatement { sql, parameters };
+                let result = explain(&connection, transaction.as_ref(), bound)
+                    .await
+                    .map_err(|error| error.to_string());
+                let _ = events.send(MainEvent::Plan(name, result));
+            }
+        });
+        let query = std::mem::take(&mut self.mode);
+        self.copy_app_with_new_mode(ExplainQuery {
+            connection: name,
+            statement,
+            plan: None,
+            scroll: 0,
+            query,
+        })
+    }
+}
+
+impl App<ExplainQuery> {
+    pub fn plan_loaded(
+        mut self,
+        connection: String,
+        result: Result<Vec<PlanNode>, String>,
+    ) -> Self {
+        if connection == self.mode.connection {
+            self.mode.plan = Some(result);
+        }
+        self
+    }
+
+    pub fn scroll_plan(mut self, lines: i16) -> Self {
+        self.mode.scroll = self.mode.scroll.saturating_add_signed(lines);
+        self
+    }
+
+    pub fn finish_explaining(mut self) -> App<EditQuery> {
+        let query = std::mem::take(&mut self.mode.query);
+        self.copy_app_with_new_mode(query)
+    }
+}
@@ -0,0 +1,136 @@
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::widgets::TableState;
+use std::path::Path;
+use tokio::sync::mpsc;
+
+use super::{App, ExploreResults, Export, Results, SaveResults, StatementResult};
+use crate::database::PAGE_SIZE;
+use crate::export::{export, ExportFormat, ExportPage};
+use crate::MainEvent;
+
+impl<T> App<T> {
+    /// Report how an export written in the background went.
+    pub fn exported(mut self, path: String, result: Result<u64, String>) -> Self {
+        self.status = Some(match result {
+            Ok(rows) => format!("Wrote {} rows to {}", rows, path),
+            Err(error) => format!("Could not export to {}: {}", path, error),
+        });
+        self
+    }
+}
+
+impl App<ExploreResults> {
+    /// Whether the tab being shown has rows to export and is not already
+    /// being exported.
+    pub fn can_export(&self) -> bool {
+        !matches!(self.results(), Results::None) && !self.exporting()
+    }
+
+    pub fn cannot_export(mut self) -> Self {
+        self.status = Some(match self.exporting() {
+            true => "An export is still being written".to_string(),
+            false => "There are no rows to export".to_string(),
+        });
+        self
+    }
+
+    fn exporting(&self) -> bool {
+        self.running
+            .as_ref()
+            .is_some_and(|running| running.export.is_some())
+    }
+
+    pub fn save_results(mut self) -> App<SaveResults> {
+        let results = std::mem::take(&mut self.mode);
+        self.copy_app_with_new_mode(SaveResults {
+            path: "results.csv".to_string(),
+            format: ExportFormat::Csv,
+            results,
+        })
+    }
+}
+
+impl App<SaveResults> {
+    pub fn results_table(&mut self) -> (&[StatementResult], usize, &mut TableState) {
+        (
+            &self.results,
+            self.selected_result,
+            &mut self.mode.results.table,
+        )
+    }
+
+    /// Pick the next or previous format, changing the extension of the path
+    /// to match if it has the extension of another.
+    pub fn switch_format(mut self, forward: bool) -> Self {
+        let formats = ExportFormat::ALL;
+        let index = formats
+            .iter()
+            .position(|format| *format == self.mode.format)
+            .unwrap_or(0);
+        self.mode.format = match forward {
+            true => formats[(index + 1) % formats.len()],
+            false => formats[(index + formats.len() - 1) % formats.len()],
+        };
+        if ExportFormat::from_path(&self.mode.path).is_some() {
+            let path = Path::new(&self.mode.path).with_extension(self.mode.format.extension());
+            self.mode.path = path.to_string_lossy().into_owned();
+        }
+        self
+    }
+
+    /// Edit the path, following its extension when it names a format.
+    pub fn path_input(mut self, key: KeyEvent) -> Self {
+        match key.code {
+            KeyCode::Char(c) => self.mode.path.push(c),
+            KeyCode::Backspace => {
+                self.mode.path.pop();
+            }
+            _ => {}
+        }
+        if let Some(format) = ExportFormat::from_path(&self.mode.path) {
+            self.mode.format = format;
+        }
+        self
+    }
+
+    /// Write the rows of the tab being shown in the background, following
+    /// the running query for the rest of them if it has more. How it went
+    /// comes back as a `MainEvent::Exported`.
+    pub fn save(mut self) -> App<ExploreResults> {
+        let path = self.mode.path.trim().to_string();
+        if path.is_empty() {
+            self.status = Some("No file to export to".to_string());
+            return self.back_to_results();
+        }
+        let Results::Some { headers, data, .. } = self.results() else {
+            return self.back_to_results();
+        };
+        let (headers, rows) = (headers.clone(), data.len());
+        let (pages, received) = mpsc::unbounded_channel();
+        for page in data.chunks(PAGE_SIZE) {
+            let _ = pages.send(ExportPage::Rows(page.to_vec()));
+        }
+        let (format, events) = (self.mode.format, self.events.clone());
+        tokio::task::spawn_blocking({
+            let path = path.clone();
+            move || {
+                let result = export(Path::new(&path), format, headers, received);
+                let _ = events.send(MainEvent::Exported(path, result));
+            }
+        });
+        self.status = Some(format!("Exporting to {}...", path));
+        if self.more_rows() {
+            if let Some(running) = &mut self.running {
+                running.export = Some(Export { path, pages, rows });
+            }
+            return self.fetch_more().back_to_results();
+        }
+        let _ = pages.send(ExportPage::End);
+        self.back_to_results()
+    }
+
+    pub fn back_to_results(mut self) -> App<ExploreResults> {
+        let results = std::mem::take(&mut self.mode.results);
+        self.copy_app_with_new_mode(results)
+    }
+}
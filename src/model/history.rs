@@ -0,0 +1,129 @@
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::widgets::TableState;
+
+use super::{App, BrowseHistory, EditQuery, Home, Outcome};
+use crate::history::HistoryEntry;
+use crate::MainEvent;
+
+impl<T> App<T> {
+    /// Write the statements of the running script to the history in the
+    /// background, once they have all come back or one has failed.
+    pub(super) fn record_history(&mut self, connection: &str) {
+        let (Some(running), Some(history)) = (&mut self.running, &self.queries.history) else {
+            return;
+        };
+        if std::mem::replace(&mut running.recorded, true) {
+            return;
+        }
+        let mut executed_at = running.executed_at;
+        let entries = self
+            .results
+            .iter()
+            .zip(&running.durations)
+            .map(|(result, &duration)| {
+                let (rows, error) = match &result.outcome {
+                    Outcome::Rows(results) => (Some(results.len() as u64), None),
+                    Outcome::Affected(rows) => (Some(*rows), None),
+                    Outcome::Failed(error) => (None, Some(error.clone())),
+                };
+                let entry = HistoryEntry {
+                    executed_at,
+                    connection: connection.to_string(),
+                    statement: result.statement.clone(),
+                    duration,
+                    rows,
+                    error,
+                };
+                executed_at += duration;
+                entry
+            })
+            .collect::<Vec<_>>();
+        let history = history.clone();
+        tokio::spawn(async move {
+            let _ = history.record(&entries).await;
+        });
+    }
+}
+
+impl App<BrowseHistory> {
+    /// Look for the entries matching the search in the background. They come
+    /// back as a `MainEvent::HistoryLoaded`.
+    pub(super) fn search_history(&mut self) {
+        let Some(history) = self.queries.history.clone() else {
+            self.status = Some("Query history is off".to_string());
+            return;
+        };
+        let (search, events) = (self.mode.search.clone(), self.events.clone());
+        tokio::spawn(async move {
+            let result = history
+                .search(&search)
+                .await
+                .map_err(|error| error.to_string());
+            let _ = events.send(MainEvent::HistoryLoaded(search, result));
+        });
+    }
+
+    pub fn history_loaded(
+        mut self,
+        search: String,
+        result: Result<Vec<HistoryEntry>, String>,
+    ) -> Self {
+        // Dropped if the search changed while it was running.
+        if search != self.mode.search {
+            return self;
+        }
+        match result {
+            Ok(entries) => {
+                let first = (!entries.is_empty()).then_some(0);
+                self.mode.entries = entries;
+                self.mode.table = TableState::default().with_selected(first);
+            }
+            Err(error) => self.status = Some(format!("Could not read the history: {}", error)),
+        }
+        self
+    }
+
+    pub fn scroll_history(mut self, rows: isize) -> Self {
+        let count = self.mode.entries.len();
+        if count > 0 {
+            let selected = self.mode.table.selected().unwrap_or(0);
+            let selected = selected.saturating_add_signed(rows).min(count - 1);
+            self.mode.table.select(Some(selected));
+        }
+        self
+    }
+
+    pub fn selected_entry(&self) -> Option<&HistoryEntry> {
+        self.mode.entries.get(self.mode.table.selected()?)
+    }
+
+    pub fn start_search(mut self) -> Self {
+        self.mode.searching = true;
+        self
+    }
+
+    pub fn finish_search(mut self) -> Self {
+        self.mode.searching = false;
+        self
+    }
+
+    pub fn search_input(mut self, key: KeyEvent) -> Self {
+        match key.code {
+            KeyCode::Char(c) => self.mode.search.push(c),
+            KeyCode::Backspace => {
+                self.mode.search.pop();
+            }
+            _ => return self,
+        }
+        self.search_history();
+        self
+    }
+
+    /// Put the selected statement in the editor.
+    pub fn load_entry(mut self) -> App<EditQuery> {
+        if let Some(entry) = self.selected_entry() {
+            self.queries.current_query = entry.statement.clone();
+        }
+        self.copy_app_with_new_mode(Home).edit_query()
+    }
+}
@@ -0,0 +1,313 @@
+use crossterm::event::{KeyCode, KeyEvent};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use super::{App, BrowseSqliteDBFiles, ImportCsv, ImportState};
+use crate::import::{import, table_name, CsvPreview, ImportPlan, ImportProgress, ImportReport};
+use crate::schema::{quote_name, Relation};
+use crate::MainEvent;
+
+impl App<BrowseSqliteDBFiles> {
+    /// Whether the file selected looks like a delimited file rather than a
+    /// database.
+    pub fn selected_csv(&self) -> bool {
+        self.selected_file().is_some_and(|file| {
+            let extension = file.path().extension().unwrap_or_default();
+            ["csv", "tsv", "txt"]
+                .iter()
+                .any(|csv| extension.eq_ignore_ascii_case(csv))
+        })
+    }
+
+    /// Whether there is a file selected and a connection to import it into.
+    pub fn can_import(&self) -> bool {
+        self.selected_file().is_some() && self.connections.active().is_some()
+    }
+
+    pub fn cannot_import(mut self) -> Self {
+        self.status = Some(match self.connections.active() {
+            Some(_) => "Pick a file to import".to_string(),
+            None => "Open a database to import into first".to_string(),
+        });
+        self
+    }
+
+    /// Read the start of the file selected to import it into the active
+    /// connection, into a new table named after it unless one of that name
+    /// exists.
+    pub fn import_csv(mut self) -> App<ImportCsv> {
+        let path = self
+            .selected_file()
+            .expect("no file to import")
+            .path()
+            .clone();
+        let connection = self
+            .connections
+            .active()
+            .expect("not connected")
+            .name
+            .clone();
+        self.load_schema(false);
+        let files = std::mem::take(&mut self.mode);
+        let mut app = self.copy_app_with_new_mode(ImportCsv {
+            connection,
+            preview: CsvPreview::read(&path).map_err(|error| error.to_string()),
+            table: table_name(&path),
+            mapping: Vec::new(),
+            column: 0,
+            state: ImportState::Mapping,
+            scroll: 0,
+            files,
+        });
+        app.map_columns();
+        app
+    }
+}
+
+impl App<ImportCsv> {
+    /// The existing table being imported into, if there is one by the name
+    /// given.
+    pub fn target_table(&self) -> Option<&Relation> {
+        let schema = self.schemas.get(&self.mode.connection)?.as_ref()?;
+        let table = self.mode.table.trim();
+        schema
+            .tables
+            .iter()
+            .find(|relation| relation.qualified_name().eq_ignore_ascii_case(table))
+    }
+
+    /// Send each of the file's columns to the table's column of the same
+    /// name, if there is one.
+    fn map_columns(&mut self) {
+        let (Ok(preview), Some(table)) = (&self.mode.preview, self.target_table()) else {
+            self.mode.mapping = Vec::new();
+            return;
+        };
+        self.mode.mapping = preview
+            .headers
+            .iter()
+            .map(|header| {
+                table
+                    .columns
+                    .iter()
+                    .position(|column| column.name.eq_ignore_ascii_case(header))
+            })
+            .collect();
+    }
+
+    pub fn table_input(mut self, key: KeyEvent) -> Self {
+        match key.code {
+            KeyCode::Char(c) => self.mode.table.push(c),
+            KeyCode::Backspace => {
+                self.mode.table.pop();
+            }
+            _ => {}
+        }
+        self.map_columns();
+        self
+    }
+
+    /// Import into the next or previous existing table, in the order they
+    /// are listed.
+    pub fn switch_table(mut self, forward: bool) -> Self {
+        let Some(Some(schema)) = self.schemas.get(&self.mode.connection) else {
+            return self;
+        };
+        let tables = &schema.tables;
+        if tables.is_empty() {
+            return self;
+        }
+        let current = tables.iter().position(|relation| {
+            relation
+                .qualified_name()
+                .eq_ignore_ascii_case(self.mode.table.trim())
+        });
+        let next = match (current, forward) {
+            (None, true) => 0,
+            (None, false) => tables.len() - 1,
+            (Some(index), true) => (index + 1) % tables.len(),
+            (Some(index), false) => (index + tables.len() - 1) % tables.len(),
+        };
+        self.mode.table = tables[next].qualified_name();
+        self.map_columns();
+        self
+    }
+
+    pub fn select_column(mut self, forward: bool) -> Self {
+        if let Ok(preview) = &self.mode.preview {
+            let count = preview.headers.len();
+            self.mode.column = match forward {
+                true => (self.mode.column + 1).min(count.saturating_sub(1)),
+                false => self.mode.column.saturating_sub(1),
+            };
+        }
+        self
+    }
+
+    /// Send the selected column of the file to the next or previous column
+    /// of the existing table, going through leaving it out.
+    pub fn switch_mapping(mut self, forward: bool) -> Self {
+        let Some(columns) = self.target_table().map(|table| table.columns.len()) else {
+            return self;
+        };
+        if let Some(mapped) = self.mode.mapping.get_mut(self.mode.column) {
+            // Leaving the column out comes after the table's last column.
+            let position = mapped.unwrap_or(columns);
+            let position = match forward {
+                true => (position + 1) % (columns + 1),
+                false => (position + columns) % (columns + 1),
+            };
+            *mapped = (position < columns).then_some(position);
+        }
+        self
+    }
+
+    /// Import the file in the background, reporting progress as
+    /// `MainEvent::ImportProgress` and how it went as `MainEvent::Imported`.
+    pub fn import(mut self) -> Self {
+        let Ok(preview) = &self.mode.preview else {
+            return self;
+        };
+        let table = self.mode.table.trim().to_string();
+        if table.is_empty() {
+            self.status = Some("Name a table to import into".to_string());
+            return self;
+        }
+        if self
+            .transaction
+            .as_ref()
+            .is_some_and(|transaction| transaction.connection == self.mode.connection)
+        {
+            self.status = Some(format!(
+                "Commit or roll back the transaction on {} first",
+                self.mode.connection
+            ));
+            return self;
+        }
+        let Some(open) = self
+            .connections
+            .open()
+            .iter()
+            .find(|open| open.name == self.mode.connection)
+        else {
+            self.status = Some(format!("{} is no longer open", self.mode.connection));
+            return self;
+        };
+        let connection = open.connection.clone();
+        let (create, columns) = match self.target_table() {
+            Some(relation) => {
+                let columns = self
+                    .mode
+                    .mapping
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(index, mapped)| {
+                        let column = &relation.columns[(*mapped)?];
+                        Some((index, preview.kinds[index], column.name.clone()))
+                    })
+                    .collect::<Vec<_>>();
+                (None, columns)
+            }
+            None => {
+                let create = preview.create_table(&table, connection.dialect());
+                let columns = preview
+                    .headers
+                    .iter()
+                    .zip(&preview.kinds)
+                    .enumerate()
+                    .map(|(index, (header, kind))| (index, *kind, header.clone()))
+                    .collect();
+                (Some(create), columns)
+            }
+        };
+        if columns.is_empty() {
+            self.status = Some("None of the file's columns go into the table".to_string());
+            return self;
+        }
+        let table = self.target_table().map_or_else(
+            || quote_name(&table, connection.dialect()),
+            |relation| relation.quoted_name(connection.dialect()),
+        );
+        let plan = ImportPlan {
+            path: preview.path.clone(),
+            delimiter: preview.delimiter,
+            table,
+            create,
+            width: preview.headers.len(),
+            columns,
+        };
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let events = self.events.clone();
+        tokio::spawn({
+            let cancelled = cancelled.clone();
+            async move {
+                let progress = {
+                    let events = events.clone();
+                    move |progress| {
+                        let _ = events.send(MainEvent::ImportProgress(progress));
+                    }
+                };
+                let result = import(connection, plan, cancelled, progress).await;
+                let _ = events.send(MainEvent::Imported(result));
+            }
+        });
+        self.mode.state = ImportState::Running {
+            progress: ImportProgress::default(),
+            cancelled,
+        };
+        self.status = None;
+        self
+    }
+
+    pub fn import_progress(mut self, progress: ImportProgress) -> Self {
+        if let ImportState::Running {
+            progress: shown, ..
+        } = &mut self.mode.state
+        {
+            *shown = progress;
+        }
+        self
+    }
+
+    /// Stop the import, which rolls back what it has inserted.
+    pub fn cancel_import(mut self) -> Self {
+        if let ImportState::Running { cancelled, .. } = &self.mode.state {
+            cancelled.store(true, Ordering::Relaxed);
+            self.status = Some("Cancelling the import...".to_string());
+        }
+        self
+    }
+
+    pub fn imported(mut self, result: Result<ImportReport, String>) -> Self {
+        self.status = Some(match &result {
+            Ok(report) if report.failed > 0 => format!(
+                "Imported {} rows into {}, {} rows failed",
+                report.inserted,
+                self.mode.table.trim(),
+                report.failed
+            ),
+            Ok(report) => format!(
+                "Imported {} rows into {}",
+                report.inserted,
+                self.mode.table.trim()
+            ),
+            Err(error) => format!("Import failed and was rolled back: {}", error),
+        });
+        self.mode.state = ImportState::Finished(result);
+        let connection = self.mode.connection.clone();
+        if self.schemas.contains_key(&connection) {
+            self.read_schema(&connection);
+        }
+        self
+    }
+
+    pub fn scroll_report(mut self, lines: i16) -> Self {
+        self.mode.scroll = self.mode.scroll.saturating_add_signed(lines);
+        self
+    }
+
+    pub fn back_to_files(mut self) -> App<BrowseSqliteDBFiles> {
+        let files = std::mem::take(&mut self.mode.files);
+        self.copy_app_with_new_mode(files)
+    }
+}
@@ -0,0 +1,60 @@
+use super::{App, BrowseLibrary, EditQuery, Home};
+use crate::library::{load_library, SavedQuery};
+
+impl<T> App<T> {
+    /// Read the saved queries from the library directory again.
+    pub fn load_library(&mut self) {
+        let Some(dir) = &self.config.library else {
+            return;
+        };
+        match load_library(dir) {
+            Ok(library) => self.queries.library = library,
+            Err(error) => self.status = Some(format!("Could not load the library: {}", error)),
+        }
+    }
+
+    pub fn library(&self) -> &[SavedQuery] {
+        &self.queries.library
+    }
+}
+
+impl App<BrowseLibrary> {
+    pub fn selected_query(&self) -> Option<&SavedQuery> {
+        self.queries.library.get(self.mode.list.selected()?)
+    }
+
+    pub fn select_query(mut self, forward: bool) -> Self {
+        let count = self.queries.library.len();
+        if count > 0 {
+            let selected = self.mode.list.selected().unwrap_or(0);
+            self.mode.list.select(Some(match forward {
+                true => (selected + 1) % count,
+                false => (selected + count - 1) % count,
+            }));
+        }
+        self
+    }
+
+    pub fn reload_library(mut self) -> Self {
+        self.load_library();
+        let count = self.queries.library.len();
+        let selected = self.mode.list.selected().unwrap_or(0);
+        self.mode
+            .list
+            .select((count > 0).then(|| selected.min(count - 1)));
+        self
+    }
+
+    /// Open the selected query in the editor, switching to its profile if
+    /// it names one.
+    pub fn open_selected(mut self) -> App<EditQuery> {
+        if let Some(query) = self.selected_query().cloned() {
+            self.queries.current_query = query.sql;
+            self.status = Some(format!("Opened {}", query.name));
+            if let Some(profile) = &query.profile {
+                self.use_profile(profile);
+            }
+        }
+        self.copy_app_with_new_mode(Home).edit_query()
+    }
+}
@@ -0,0 +1,281 @@
+use sqlx::types::JsonValue;
+use std::error::Error;
+
+use crate::database::{BoundStatement, Connection, Transaction};
+use crate::model::CellValue;
+use crate::script::Dialect;
+
+/// One step of a query plan and the steps that feed it.
+#[derive(Clone, Debug)]
+pub struct PlanNode {
+    /// What the step does, such as `Seq Scan on users` or
+    /// `SEARCH users USING INDEX users_email (email=?)`.
+    pub operation: String,
+    pub kind: PlanKind,
+    pub cost: Option<PlanCost>,
+    /// Conditions, keys and anything else the backend says about the step.
+    pub details: Vec<String>,
+    pub children: Vec<PlanNode>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PlanKind {
+    /// Reads every row of a table.
+    FullScan,
+    /// Finds rows through an index.
+    IndexScan,
+    Other,
+}
+
+/// The planner's estimates for a step, in its own units.
+#[derive(Clone, Copy, Debug)]
+pub struct PlanCost {
+    /// The cost before the first row comes out, where the backend says.
+    pub startup: Option<f64>,
+    pub total: f64,
+    pub rows: f64,
+}
+
+/// Ask the database how it would run a statement, without running it:
+/// `EXPLAIN QUERY PLAN` on SQLite, `EXPLAIN (FORMAT JSON)` on Postgres and
+/// `EXPLAIN FORMAT=TREE` on MySQL. DuckDB's plan is already drawn, so it is
+/// kept as it is.
+pub async fn explain(
+    connection: &Connection,
+    transaction: Option<&Transaction>,
+    statement: BoundStatement,
+) -> Result<Vec<PlanNode>, Box<dyn Error>> {
+    let dialect = connection.dialect();
+    let explain = match dialect {
+        Dialect::Sqlite => "EXPLAIN QUERY PLAN",
+        Dialect::Postgres => "EXPLAIN (FORMAT JSON)",
+        Dialect::MySql => "EXPLAIN FORMAT=TREE",
+        Dialect::DuckDb => "EXPLAIN",
+    };
+    let statement = BoundStatement {
+        sql: format!("{} {}", explain, statement.sql),
+        ..statement
+    };
+    let rows = connection.fetch_bound(transaction, &statement).await?;
+    // The columns each plan is read from: SQLite's id, parent and detail,
+    // and DuckDB's name and plan.
+    let columns = match dialect {
+        Dialect::Sqlite => 4,
+        Dialect::Postgres | Dialect::MySql => 1,
+        Dialect::DuckDb => 2,
+    };
+    if let Some(row) = rows.iter().find(|row| row.len() < columns) {
+        return Err(format!(
+            "the plan came back with {} columns rather than {}",
+            row.len(),
+            columns
+        )
+        .into());
+    }
+    Ok(match dialect {
+        Dialect::Sqlite => sqlite_plan(&rows),
+        Dialect::Postgres => match rows.first().and_then(|row| row.first()) {
+            Some(CellValue::Json(json)) => json
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter_map(|query| query.get("Plan"))
+                .map(postgres_node)
+                .collect(),
+            _ => return Err("Postgres did not return a plan".into()),
+        },
+        Dialect::MySql => mysql_plan(&rows),
+        Dialect::DuckDb => rows
+            .iter()
+            .map(|row| PlanNode {
+                operation: row[0].text(),
+                kind: PlanKind::Other,
+                cost: None,
+                details: row[1].text().lines().map(str::to_string).collect(),
+                children: Vec::new(),
+            })
+            .collect(),
+    })
+}
+
+/// Each row of `EXPLAIN QUERY PLAN` is a step with its own id, the id of
+/// the step it belongs to, and a description.
+fn sqlite_plan(rows: &[Vec<CellValue>]) -> Vec<PlanNode> {
+    let mut depths: Vec<(i64, usize)> = Vec::new();
+    let mut nodes = Vec::new();
+    for row in rows {
+        let id = integer(&row[0]);
+        let parent = integer(&row[1]);
+        let depth = depths
+            .iter()
+            .find(|(step, _)| *step == parent)
+            .map_or(0, |(_, depth)| depth + 1);
+        depths.push((id, depth));
+        let operation = row[3].text();
+        let kind = if operation.starts_with("SEARCH") || operation.contains(" INDEX ") {
+            PlanKind::IndexScan
+        } else if operation.starts_with("SCAN") && !operation.contains("CONSTANT ROW") {
+            PlanKind::FullScan
+        } else {
+            PlanKind::Other
+        };
+        nodes.push((depth, leaf(operation, kind)));
+    }
+    into_tree(nodes)
+}
+
+fn postgres_node(plan: &JsonValue) -> PlanNode {
+    let field = |name: &str| plan.get(name).and_then(JsonValue::as_str);
+    let node_type = field("Node Type").unwrap_or("?");
+    let mut operation = node_type.to_string();
+    if let Some(join) = field("Join Type").filter(|join| *join != "Inner") {
+        operation.push_str(&format!(" ({})", join));
+    }
+    if let Some(index) = field("Index Name") {
+        operation.push_str(&format!(" using {}", index));
+    }
+    if let Some(relation) = field("Relation Name") {
+        operation.push_str(&format!(" on {}", relation));
+        if let Some(alias) = field("Alias").filter(|alias| *alias != relation) {
+            operation.push_str(&format!(" {}", alias));
+        }
+    }
+    let kind = if node_type == "Seq Scan" {
+        PlanKind::FullScan
+    } else if node_type.contains("Index") {
+        PlanKind::IndexScan
+    } else {
+        PlanKind::Other
+    };
+    let number = |name: &str| plan.get(name).and_then(JsonValue::as_f64);
+    let cost = number("Total Cost").map(|total| PlanCost {
+        startup: number("Startup Cost"),
+        total,
+        rows: number("Plan Rows").unwrap_or_default(),
+    });
+    let mut details = Vec::new();
+    for name in [
+        "Index Cond",
+        "Recheck Cond",
+        "Hash Cond",
+        "Merge Cond",
+        "Join Filter",
+        "Filter",
+        "One-Time Filter",
+    ] {
+        if let Some(condition) = field(name) {
+            details.push(format!("{}: {}", name, condition));
+        }
+    }
+    for name in ["Sort Key", "Group Key"] {
+        if let Some(keys) = plan.get(name).and_then(JsonValue::as_array) {
+            let keys = keys
+                .iter()
+                .filter_map(JsonValue::as_str)
+                .collect::<Vec<_>>();
+            details.push(format!("{}: {}", name, keys.join(", ")));
+        }
+    }
+    let children = plan
+        .get("Plans")
+        .and_then(JsonValue::as_array)
+        .into_iter()
+        .flatten()
+        .map(postgres_node)
+        .collect();
+    PlanNode {
+        operation,
+        kind,
+        cost,
+        details,
+        children,
+    }
+}
+
+/// `EXPLAIN FORMAT=TREE` gives one line per step, starting `->` and indented
+/// four spaces under the step it feeds, with its estimates at the end.
+fn mysql_plan(rows: &[Vec<CellValue>]) -> Vec<PlanNode> {
+    let plan = rows.first().map(|row| row[0].text()).unwrap_or_default();
+    let mut nodes: Vec<(usize, PlanNode)> = Vec::new();
+    for line in plan.lines() {
+        let trimmed = line.trim_start();
+        let depth = (line.len() - trimmed.len()) / 4;
+        let Some(step) = trimmed.strip_prefix("-> ") else {
+            // Lines that do not start a step carry on the one before.
+            if let Some((_, node)) = nodes.last_mut() {
+                node.details.push(trimmed.to_string());
+            }
+            continue;
+        };
+        let (operation, cost) = match step.rfind("(cost=") {
+            Some(start) => (step[..start].trim_end(), mysql_cost(&step[start..])),
+            None => (step, None),
+        };
+        let kind = if operation.starts_with("Table scan") {
+            PlanKind::FullScan
+        } else if operation.contains("ndex") {
+            PlanKind::IndexScan
+        } else {
+            PlanKind::Other
+        };
+        let mut node = leaf(operation.to_string(), kind);
+        node.cost = cost;
+        nodes.push((depth, node));
+    }
+    into_tree(nodes)
+}
+
+/// Read `(cost=0.25..1.50 rows=3)` or `(cost=1.50 rows=3)`.
+fn mysql_cost(estimates: &str) -> Option<PlanCost> {
+    let estimates = estimates.strip_prefix("(cost=")?.split(')').next()?;
+    let (cost, rows) = estimates.split_once(" rows=")?;
+    let (startup, total) = match cost.split_once("..") {
+        Some((startup, total)) => (startup.parse().ok(), total),
+        None => (None, cost),
+    };
+    Some(PlanCost {
+        startup,
+        total: total.parse().ok()?,
+        rows: rows.parse().ok()?,
+    })
+}
+
+fn leaf(operation: String, kind: PlanKind) -> PlanNode {
+    PlanNode {
+        operation,
+        kind,
+        cost: None,
+        details: Vec::new(),
+        children: Vec::new(),
+    }
+}
+
+/// Nest steps listed in order with how deep each one is.
+fn into_tree(nodes: Vec<(usize, PlanNode)>) -> Vec<PlanNode> {
+    let mut stack: Vec<(usize, PlanNode)> = Vec::new();
+    let mut roots = Vec::new();
+    let attach = |stack: &mut Vec<(usize, PlanNode)>, roots: &mut Vec<PlanNode>, node| match stack
+        .last_mut()
+    {
+        Some((_, parent)) => parent.children.push(node),
+        None => roots.push(node),
+    };
+    for (depth, node) in nodes {
+        while stack.last().is_some_and(|(open, _)| *open >= depth) {
+            let (_, finished) = stack.pop().expect("stack is not empty");
+            attach(&mut stack, &mut roots, finished);
+        }
+        stack.push((depth, node));
+    }
+    while let Some((_, finished)) = stack.pop() {
+        attach(&mut stack, &mut roots, finished);
+    }
+    roots
+}
+
+fn integer(value: &CellValue) -> i64 {
+    match value {
+        CellValue::Integer(value) => *value,
+        value => value.text().parse().unwrap_or_default(),
+    }
+}
@@ -0,0 +1,616 @@
+use std::error::Error;
+
+use crate::database::Connection;
+use crate::model::CellValue;
+use crate::script::Dialect;
+
+/// The objects in a connection's database, as shown by the schema browser.
+#[derive(Clone, Debug, Default)]
+pub struct Schema {
+    pub tables: Vec<Relation>,
+    pub views: Vec<Relation>,
+    pub indexes: Vec<Dependent>,
+    pub triggers: Vec<Dependent>,
+}
+
+/// A table or view and its columns.
+#[derive(Clone, Debug)]
+pub struct Relation {
+    /// The schema the relation is in, unless it is the default one.
+    pub schema: Option<String>,
+    pub name: String,
+    pub columns: Vec<ColumnInfo>,
+}
+
+#[derive(Clone, Debug)]
+pub struct ColumnInfo {
+    pub name: String,
+    pub data_type: String,
+    pub not_null: bool,
+    pub primary_key: bool,
+    /// The expression the column takes when an insert leaves it out.
+    pub default: Option<String>,
+    /// The `table(column)` a foreign key on this column points at.
+    pub references: Option<String>,
+}
+
+/// An index or trigger, which belongs to a table.
+#[derive(Clone, Debug)]
+pub struct Dependent {
+    pub name: String,
+    pub table: String,
+    /// The indexed columns, or the definition of a trigger.
+    pub detail: String,
+}
+
+/// The catalog queries for one backend. Each returns its columns in the
+/// order given, so every backend is read the same way.
+struct CatalogQueries {
+    /// schema, name, 'table' or 'view', with no schema for the default one
+    relations: &'static str,
+    /// schema, table, column, type, not null, primary key, default
+    columns: &'static str,
+    /// schema, table, column, for backends whose column query has no
+    /// primary keys
+    primary_keys: Option<&'static str>,
+    /// schema, table, column, referenced table, referenced column
+    foreign_keys: Option<&'static str>,
+    /// name, table, detail
+    indexes: &'static str,
+    /// name, table, detail
+    triggers: Option<&'static str>,
+}
+
+const SQLITE: CatalogQueries = CatalogQueries {
+    relations: "SELECT NULL, name, type FROM sqlite_master \
+        WHERE type IN ('table', 'view') AND name NOT LIKE 'sqlite_%' ORDER BY name",
+    columns: "SELECT NULL, m.name, p.name, p.type, p.\"notnull\", p.pk, p.dflt_value \
+        FROM sqlite_master m JOIN pragma_table_info(m.name) p \
+        WHERE m.type IN ('table', 'view') ORDER BY m.name, p.cid",
+    primary_keys: None,
+    foreign_keys: Some(
+        "SELECT NULL, m.name, f.\"from\", f.\"table\", f.\"to\" \
+        FROM sqlite_master m JOIN pragma_foreign_key_list(m.name) f WHERE m.type = 'table'",
+    ),
+    indexes: "SELECT m.name, m.tbl_name, group_concat(i.name, ', ') \
+        FROM sqlite_master m JOIN pragma_index_info(m.name) i \
+        WHERE m.type = 'index' GROUP BY m.name, m.tbl_name ORDER BY m.name",
+    triggers: Some(
+        "SELECT name, tbl_name, sql FROM sqlite_master WHERE type = 'trigger' ORDER BY name",
+    ),
+};
+
+const POSTGRES: CatalogQueries = CatalogQueries {
+    relations: "SELECT NULLIF(table_schema, 'public')::text, table_name::text, \
+        CASE WHEN table_type = 'VIEW' THEN 'view' ELSE 'table' END \
+        FROM information_schema.tables \
+        WHERE table_schema NOT IN ('pg_catalog', 'information_schema') \
+        ORDER BY table_schema <> 'public', table_schema, table_name",
+    columns: "SELECT NULLIF(table_schema, 'public')::text, table_name::text, \
+        column_name::text, data_type::text, is_nullable = 'NO', false, \
+        column_default::text \
+        FROM information_schema.columns \
+        WHERE table_schema NOT IN ('pg_catalog', 'information_schema') \
+        ORDER BY table_schema, table_name, ordinal_position",
+    primary_keys: Some(
+        "SELECT NULLIF(k.table_schema, 'public')::text, k.table_name::text, \
+        k.column_name::text \
+        FROM information_schema.table_constraints c \
+        JOIN information_schema.key_column_usage k \
+            ON k.constraint_schema = c.constraint_schema \
+            AND k.constraint_name = c.constraint_name \
+        WHERE c.constraint_type = 'PRIMARY KEY'",
+    ),
+    foreign_keys: Some(
+        "SELECT NULLIF(k.table_schema, 'public')::text, k.table_name::text, \
+        k.column_name::text, \
+        CASE WHEN u.table_schema = 'public' THEN u.table_name \
+            ELSE u.table_schema || '.' || u.table_name END::text, u.column_name::text \
+        FROM information_schema.referential_constraints r \
+        JOIN information_schema.key_column_usage k \
+            ON k.constraint_schema = r.constraint_schema \
+            AND k.constraint_name = r.constraint_name \
+        JOIN information_schema.key_column_usage u \
+            ON u.constraint_schema = r.unique_constraint_schema \
+            AND u.constraint_name = r.unique_constraint_name \
+            AND u.ordinal_position = k.position_in_unique_constraint",
+    ),
+    indexes: "SELECT CASE WHEN schemaname = 'public' THEN indexname \
+            ELSE schemaname || '.' || indexname END::text, \
+        CASE WHEN schemaname = 'public' THEN tablename \
+            ELSE schemaname || '.' || tablename END::text, indexdef \
+        FROM pg_indexes WHERE schemaname NOT IN ('pg_catalog', 'information_schema') \
+        ORDER BY 1",
+    triggers: Some(
+        "SELECT trigger_name::text, \
+        CASE WHEN event_object_schema = 'public' THEN event_object_table \
+            ELSE event_object_schema || '.' || event_object_table END::text, \
+        action_timing || ' ' || string_agg(event_manipulation::text, ' OR ') || ' ' \
+            || action_statement \
+        FROM information_schema.triggers \
+        GROUP BY 1, 2, action_timing, action_statement ORDER BY 1",
+    ),
+};
+
+const MYSQL: CatalogQueries = CatalogQueries {
+    relations: "SELECT NULL, table_name, IF(table_type = 'VIEW', 'view', 'table') \
+        FROM information_schema.tables WHERE table_schema = DATABASE() ORDER BY 1",
+    columns: "SELECT NULL, table_name, column_name, column_type, is_nullable = 'NO', \
+        column_key = 'PRI', column_default \
+        FROM information_schema.columns WHERE table_schema = DATABASE() \
+        ORDER BY table_name, ordinal_position",
+    primary_keys: None,
+    foreign_keys: Some(
+        "SELECT NULL, table_name, column_name, referenced_table_name, referenced_column_name \
+        FROM information_schema.key_column_usage \
+        WHERE table_schema = DATABASE() AND referenced_table_name IS NOT NULL",
+    ),
+    indexes: "SELECT index_name, table_name, \
+        GROUP_CONCAT(column_name ORDER BY seq_in_index SEPARATOR ', ') \
+        FROM information_schema.statistics WHERE table_schema = DATABASE() \
+        GROUP BY index_name, table_name ORDER BY 1",
+    triggers: Some(
+        "SELECT trigger_name, event_object_table, \
+        CONCAT(action_timing, ' ', event_manipulation, ' ', action_statement) \
+        FROM information_schema.triggers WHERE trigger_schema = DATABASE() ORDER BY 1",
+    ),
+};
+
+/// DuckDB has no triggers, and its foreign keys are left out as the
+/// catalog functions describing them vary between versions.
+const DUCKDB: CatalogQueries = CatalogQueries {
+    relations: "SELECT NULLIF(table_schema, 'main'), table_name, \
+        CASE WHEN table_type = 'VIEW' THEN 'view' ELSE 'table' END \
+        FROM information_schema.tables \
+        WHERE table_schema NOT IN ('information_schema', 'pg_catalog') \
+        ORDER BY table_schema <> 'main', table_schema, table_name",
+    columns: "SELECT NULLIF(table_schema, 'main'), table_name, \
+        column_name, data_type, is_nullable = 'NO', false, column_default \
+        FROM information_schema.columns \
+        WHERE table_schema NOT IN ('information_schema', 'pg_catalog') \
+        ORDER BY table_schema, table_name, ordinal_position",
+    primary_keys: Some(
+        "SELECT NULLIF(schema_name, 'main'), table_name, unnest(constraint_column_names) \
+        FROM duckdb_constraints() WHERE constraint_type = 'PRIMARY KEY'",
+    ),
+    foreign_keys: None,
+    indexes: "SELECT CASE WHEN schema_name = 'main' THEN index_name \
+            ELSE schema_name || '.' || index_name END, \
+        CASE WHEN schema_name = 'main' THEN table_name \
+            ELSE schema_name || '.' || table_name END, coalesce(sql, '') \
+        FROM duckdb_indexes() ORDER BY 1",
+    triggers: None,
+};
+
+/// Read the tables, views, indexes and triggers of a connection's database
+/// from its catalog.
+pub async fn load_schema(connection: &Connection) -> Result<Schema, Box<dyn Error>> {
+    let queries = match connection.dialect() {
+        Dialect::Sqlite => &SQLITE,
+        Dialect::Postgres => &POSTGRES,
+        Dialect::MySql => &MYSQL,
+        Dialect::DuckDb => &DUCKDB,
+    };
+    let fetch = |query: Option<&'static str>| async move {
+        match query {
+            Some(query) => connection.fetch_all(query).await,
+            None => Ok(Vec::new()),
+        }
+    };
+
+    let text = |value: &CellValue| (!value.is_null()).then(|| value.text());
+
+    let mut schema = Schema::default();
+    for row in fetch(Some(queries.relations)).await? {
+        let relation = Relation {
+            schema: text(&row[0]),
+            name: row[1].text(),
+            columns: Vec::new(),
+        };
+        match row[2].text().as_str() {
+            "view" => schema.views.push(relation),
+            _ => schema.tables.push(relation),
+        }
+    }
+    for row in fetch(Some(queries.columns)).await? {
+        if let Some(relation) = schema.relation_mut(text(&row[0]).as_deref(), &row[1].text()) {
+            relation.columns.push(ColumnInfo {
+                name: row[2].text(),
+                data_type: row[3].text(),
+                not_null: flag(&row[4]),
+                primary_key: flag(&row[5]),
+                default: text(&row[6]),
+                references: None,
+            });
+        }
+    }
+    for row in fetch(queries.primary_keys).await? {
+        let table = text(&row[0]);
+        if let Some(column) = schema.column_mut(table.as_deref(), &row[1].text(), &row[2].text()) {
+            column.primary_key = true;
+        }
+    }
+    for row in fetch(queries.foreign_keys).await? {
+        let table = text(&row[0]);
+        if let Some(column) = schema.column_mut(table.as_deref(), &row[1].text(), &row[2].text()) {
+            column.references = Some(format!("{}({})", row[3].text(), row[4].text()));
+        }
+    }
+    schema.indexes = dependents(fetch(Some(queries.indexes)).await?);
+    schema.triggers = dependents(fetch(queries.triggers).await?);
+    Ok(schema)
+}
+
+/// What the table inspector shows beyond the schema itself: the table's
+/// definition and roughly how many rows it has.
+#[derive(Clone, Debug)]
+pub struct TableDetails {
+    pub definition: String,
+    /// Whether the definition was put together from the columns, for
+    /// backends that cannot show a table's own `CREATE` statement.
+    pub reconstructed: bool,
+    pub row_count: Option<i64>,
+}
+
+/// Read a table or view's definition and an estimate of its row count.
+pub async fn load_table_details(
+    connection: &Connection,
+    relation: &Relation,
+    is_view: bool,
+) -> Result<TableDetails, Box<dyn Error>> {
+    let dialect = connection.dialect();
+    let schema = match (&relation.schema, dialect) {
+        (Some(schema), _) => schema,
+        (None, Dialect::Postgres) => "public",
+        (None, Dialect::DuckDb) => "main",
+        (None, _) => "",
+    };
+    let quoted = relation.quoted_name(dialect);
+    let (schema, name) = (quote_literal(schema), quote_literal(&relation.name));
+    let (definition, count) = match dialect {
+        Dialect::Sqlite => (
+            format!("SELECT sql FROM sqlite_master WHERE name = {}", name),
+            // Counting the rows could read the whole table, so the estimate
+            // is the one ANALYZE keeps, with the table's row first.
+            format!(
+                "SELECT stat FROM sqlite_stat1 WHERE tbl = {} ORDER BY idx IS NOT NULL LIMIT 1",
+                name
+            ),
+        ),
+        Dialect::Postgres => (
+            match is_view {
+                true => format!(
+                    "SELECT pg_get_viewdef({}::regclass, true)",
+                    quote_literal(&quoted)
+                ),
+                false => String::new(),
+            },
+            format!(
+                "SELECT reltuples::bigint FROM pg_class WHERE oid = {}::regclass",
+                quote_literal(&quoted)
+            ),
+        ),
+        Dialect::MySql => (
+            format!("SHOW CREATE TABLE {}", quoted),
+            format!(
+                "SELECT table_rows FROM information_schema.tables \
+                WHERE table_schema = DATABASE() AND table_name = {}",
+                name
+            ),
+        ),
+        Dialect::DuckDb => (
+            format!(
+                "SELECT sql FROM duckdb_tables() WHERE schema_name = {0} AND table_name = {1} \
+                UNION ALL SELECT sql FROM duckdb_views() \
+                WHERE schema_name = {0} AND view_name = {1}",
+                schema, name
+            ),
+            format!(
+                "SELECT estimated_size FROM duckdb_tables() \
+                WHERE schema_name = {} AND table_name = {}",
+                schema, name
+            ),
+        ),
+    };
+    let definition = match definition.is_empty() {
+        true => None,
+        false => connection.fetch_all(&definition).await?.into_iter().next(),
+    };
+    // SHOW CREATE TABLE gives the name first and the statement second.
+    let column = usize::from(dialect == Dialect::MySql);
+    let definition = definition.and_then(|row| row.get(column).map(CellValue::text));
+    let definition = match dialect {
+        Dialect::Postgres => {
+            definition.map(|query| format!("CREATE VIEW {} AS\n{}", quoted, query))
+        }
+        _ => definition,
+    };
+    // An estimate that cannot be read, or that Postgres or SQLite's ANALYZE
+    // has not made yet, is left out rather than failing the rest. SQLite's
+    // starts with the number of rows, followed by figures for the index.
+    let row_count = match is_view {
+        true => None,
+        false => connection.fetch_all(&count).await.ok().and_then(|rows| {
+            rows.first()
+                .and_then(|row| row.first())
+                .and_then(|value| value.text().split_whitespace().next()?.parse().ok())
+                .filter(|count: &i64| *count >= 0)
+        }),
+    };
+    Ok(TableDetails {
+        reconstructed: definition.is_none(),
+        definition: definition
+            .unwrap_or_else(|| reconstruct_definition(relation, &quoted, dialect)),
+        row_count,
+    })
+}
+
+/// A `CREATE TABLE` statement built from what the schema knows about the
+/// table's columns.
+fn reconstruct_definition(relation: &Relation, quoted: &str, dialect: Dialect) -> String {
+    let mut lines = relation
+        .columns
+        .iter()
+        .map(|column| {
+            let name = quote_name(&column.name, dialect);
+            let mut line = format!("    {} {}", name, column.data_type);
+            if column.not_null {
+                line.push_str(" NOT NULL");
+            }
+            if let Some(default) = &column.default {
+                line.push_str(&format!(" DEFAULT {}", default));
+            }
+            if let Some(references) = &column.references {
+                line.push_str(&format!(" REFERENCES {}", references));
+            }
+            line
+        })
+        .collect::<Vec<_>>();
+    let keys = relation
+        .columns
+        .iter()
+        .filter(|column| column.primary_key)
+        .map(|column| quote_name(&column.name, dialect))
+        .collect::<Vec<_>>();
+    if !keys.is_empty() {
+        lines.push(format!("    PRIMARY KEY ({})", keys.join(", ")));
+    }
+    format!("CREATE TABLE {} (\n{}\n);", quoted, lines.join(",\n"))
+}
+
+/// Split a name qualified with its schema, filling in the default schema of
+/// backends that have one.
+/// One name quoted as an identifier, dots and all.
+pub fn quote_name(name: &str, dialect: Dialect) -> String {
+    match dialect {
+        Dialect::MySql => format!("`{}`", name.replace('`', "``")),
+        _ => format!("\"{}\"", name.replace('"', "\"\"")),
+    }
+}
+
+fn quote_literal(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "''"))
+}
+
+impl Relation {
+    /// The name as it is shown, after its schema if it has one.
+    pub fn qualified_name(&self) -> String {
+        match &self.schema {
+            Some(schema) => format!("{}.{}", schema, self.name),
+            None => self.name.clone(),
+        }
+    }
+
+    /// The name as a statement refers to it, quoted.
+    pub fn quoted_name(&self, dialect: Dialect) -> String {
+        match &self.schema {
+            Some(schema) => format!(
+                "{}.{}",
+                quote_name(schema, dialect),
+                quote_name(&self.name, dialect)
+            ),
+            None => quote_name(&self.name, dialect),
+        }
+    }
+}
+
+impl Schema {
+    /// The foreign keys in other tables that point at `table`, as the
+    /// referencing `table.column` and the column referenced.
+    pub fn references_to(&self, table: &str) -> Vec<(String, String)> {
+        let prefix = format!("{}(", table);
+        self.tables
+            .iter()
+            .flat_map(|relation| {
+                relation.columns.iter().filter_map(|column| {
+                    let references = column.references.as_deref()?;
+                    let target = references.strip_prefix(&prefix)?.strip_suffix(')')?;
+                    Some((
+                        format!("{}.{}", relation.qualified_name(), column.name),
+                        target.to_string(),
+                    ))
+                })
+            })
+            .collect()
+    }
+
+    fn relation_mut(&mut self, schema: Option<&str>, name: &str) -> Option<&mut Relation> {
+        self.tables
+            .iter_mut()
+            .chain(self.views.iter_mut())
+            .find(|relation| relation.schema.as_deref() == schema && relation.name == name)
+    }
+
+    fn column_mut(
+        &mut self,
+        schema: Option<&str>,
+        table: &str,
+        column: &str,
+    ) -> Option<&mut ColumnInfo> {
+        let relation = self.relation_mut(schema, table)?;
+        relation.columns.iter_mut().find(|info| info.name == column)
+    }
+}
+
+fn dependents(rows: Vec<Vec<CellValue>>) -> Vec<Dependent> {
+    rows.iter()
+        .map(|row| Dependent {
+            name: row[0].text(),
+            table: row[1].text(),
+            detail: row[2]
+                .text()
+                .split_whitespace()
+                .collect::<Vec<_>>()
+                .join(" "),
+        })
+        .collect()
+}
+
+fn flag(value: &CellValue) -> bool {
+    match value {
+        CellValue::Bool(value) => *value,
+        CellValue::Integer(value) => *value != 0,
+        CellValue::Unsigned(value) => *value != 0,
+        value => matches!(value.text().as_str(), "1" | "true" | "YES"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const COMPOSITE_KEY: &[&str] = &[
+        "CREATE TABLE parent (x INTEGER, y INTEGER, PRIMARY KEY (x, y))",
+        "CREATE TABLE child (a INTEGER, b INTEGER, \
+            FOREIGN KEY (a, b) REFERENCES parent (x, y))",
+    ];
+
+    async fn references(connection: &Connection, table: &str) -> Vec<Option<String>> {
+        let schema = load_schema(connection).await.unwrap();
+        let relation = schema
+            .tables
+            .iter()
+            .find(|r| r.qualified_name() == table)
+            .unwrap();
+        relation
+            .columns
+            .iter()
+            .map(|column| column.references.clone())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn pairs_the_columns_of_a_sqlite_composite_key() {
+        let connection = Connection::connect("sqlite::memory:").await.unwrap();
+        for statement in COMPOSITE_KEY {
+            connection.fetch_all(statement).await.unwrap();
+        }
+        assert_eq!(
+            references(&connection, "child").await,
+            [Some("parent(x)".to_string()), Some("parent(y)".to_string())]
+        );
+    }
+
+    #[tokio::test]
+    async fn keeps_a_dot_in_a_sqlite_table_name() {
+        let connection = Connection::connect("sqlite::memory:").await.unwrap();
+        let create = "CREATE TABLE \"my.table\" (id INTEGER PRIMARY KEY)";
+        connection.fetch_all(create).await.unwrap();
+        let schema = load_schema(&connection).await.unwrap();
+        let relation = &schema.tables[0];
+        assert_eq!(
+            (relation.schema.as_deref(), relation.name.as_str()),
+            (None, "my.table")
+        );
+        assert_eq!(relation.quoted_name(Dialect::Sqlite), "\"my.table\"");
+        let details = load_table_details(&connection, relation, false)
+            .await
+            .unwrap();
+        assert_eq!(details.definition, create);
+    }
+
+    #[tokio::test]
+    async fn pairs_the_columns_of_a_postgres_composite_key() {
+        let Ok(url) = std::env::var("SQUEALER_TEST_POSTGRES_URL") else {
+            return;
+        };
+        let connection = Connection::connect(&url).await.unwrap();
+        let reset = "DROP SCHEMA IF EXISTS squealer_keys CASCADE";
+        connection.fetch_all(reset).await.unwrap();
+        connection
+            .fetch_all("CREATE SCHEMA squealer_keys")
+            .await
+            .unwrap();
+        for statement in COMPOSITE_KEY {
+            let statement = statement.replace("TABLE ", "TABLE squealer_keys.");
+            let statement = statement.replace("REFERENCES ", "REFERENCES squealer_keys.");
+            connection.fetch_all(&statement).await.unwrap();
+        }
+        let found = references(&connection, "squealer_keys.child").await;
+        connection.fetch_all(reset).await.unwrap();
+        assert_eq!(
+            found,
+            [
+                Some("squealer_keys.parent(x)".to_string()),
+                Some("squealer_keys.parent(y)".to_string())
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn keeps_a_dot_in_a_postgres_table_name() {
+        let Ok(url) = std::env::var("SQUEALER_TEST_POSTGRES_URL") else {
+            return;
+        };
+        let connection = Connection::connect(&url).await.unwrap();
+        let reset = "DROP SCHEMA IF EXISTS squealer_names CASCADE";
+        connection.fetch_all(reset).await.unwrap();
+        connection
+            .fetch_all("CREATE SCHEMA squealer_names")
+            .await
+            .unwrap();
+        connection
+            .fetch_all("CREATE VIEW squealer_names.\"my.view\" AS SELECT 1 AS one")
+            .await
+            .unwrap();
+        let schema = load_schema(&connection).await.unwrap();
+        let relation = schema
+            .views
+            .iter()
+            .find(|view| view.schema.as_deref() == Some("squealer_names"))
+            .unwrap()
+            .clone();
+        let details = load_table_details(&connection, &relation, true).await;
+        connection.fetch_all(reset).await.unwrap();
+        assert_eq!(relation.name, "my.view");
+        assert_eq!(
+            relation.quoted_name(Dialect::Postgres),
+            "\"squealer_names\".\"my.view\""
+        );
+        assert!(details
+            .unwrap()
+            .definition
+            .starts_with("CREATE VIEW \"squealer_names\".\"my.view\" AS"));
+    }
+
+    #[cfg(feature = "duckdb")]
+    #[tokio::test]
+    async fn keeps_a_dot_in_a_duckdb_table_name() {
+        let connection = Connection::connect("duckdb://:memory:").await.unwrap();
+        for statement in [
+            "CREATE SCHEMA s",
+            "CREATE TABLE s.\"my.table\" (id INTEGER PRIMARY KEY)",
+        ] {
+            connection.fetch_all(statement).await.unwrap();
+        }
+        let schema = load_schema(&connection).await.unwrap();
+        let relation = &schema.tables[0];
+        assert_eq!(relation.schema.as_deref(), Some("s"));
+        assert_eq!(relation.quoted_name(Dialect::DuckDb), "\"s\".\"my.table\"");
+        assert!(relation.columns[0].primary_key);
+        let details = load_table_details(&connection, relation, false)
+            .await
+            .unwrap();
+        assert!(details.definition.contains("my.table"));
+    }
+}
@@ -0,0 +1,605 @@
+use std::ops::Range;
+
+use crate::lexer::{
+    backslash_escapes, block_comment_end, dollar_quote_tag, find, is_word_byte, is_word_char,
+    line_end, quoted_end, word_end,
+};
+
+/// The SQL dialects a script can be split for, which differ in how strings
+/// are quoted and commented.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Dialect {
+    Sqlite,
+    Postgres,
+    MySql,
+    DuckDb,
+}
+
+/// Split a script on the semicolons between its statements, skipping those
+/// in strings, quoted identifiers, comments and the bodies of triggers and
+/// routines. Statements that are empty or only comments are dropped.
+pub fn split_statements(script: &str, dialect: Dialect) -> Vec<String> {
+    statement_ranges(script, dialect)
+        .into_iter()
+        .map(|range| script[range].trim().to_string())
+        .collect()
+}
+
+/// Where each statement of a script is, from its first code to the
+/// semicolon that ends it or the end of the script.
+pub fn statement_ranges(script: &str, dialect: Dialect) -> Vec<Range<usize>> {
+    let bytes = script.as_bytes();
+    let mut splitter = Splitter::default();
+    let mut statements = Vec::new();
+    let mut start = 0;
+    let mut i = 0;
+    while i < bytes.len() {
+        let had_code = splitter.has_code;
+        let next = match bytes[i] {
+            b'-' if bytes.get(i + 1) == Some(&b'-') => line_end(bytes, i),
+            b'#' if dialect == Dialect::MySql => line_end(bytes, i),
+            b'/' if bytes.get(i + 1) == Some(&b'*') => block_comment_end(bytes, i, 0, dialect).0,
+            quote @ (b'\'' | b'"' | b'`') => {
+                splitter.has_code = true;
+                let backslash_escapes = backslash_escapes(bytes, i, dialect);
+                quoted_end(bytes, i + 1, quote, backslash_escapes).unwrap_or(bytes.len())
+            }
+            b'[' if dialect == Dialect::Sqlite => {
+                splitter.has_code = true;
+                find(bytes, i + 1, b"]").map_or(bytes.len(), |end| end + 1)
+            }
+            b'$' if matches!(dialect, Dialect::Postgres | Dialect::DuckDb) => {
+                splitter.has_code = true;
+                match dollar_quote_tag(script, i) {
+                    Some(tag) => find(bytes, i + tag.len(), tag.as_bytes())
+                        .map_or(bytes.len(), |end| end + tag.len()),
+                    None => i + 1,
+                }
+            }
+            b';' => {
+                if splitter.end_of_statement() {
+                    if splitter.has_code {
+                        statements.push(start..i);
+                    }
+                    splitter = Splitter::default();
+                    start = i + 1;
+                }
+                i + 1
+            }
+            byte if byte.is_ascii_alphabetic() || byte == b'_' => {
+                let end = word_end(script, i);
+                splitter.word(&script[i..end].to_ascii_uppercase());
+                end
+            }
+            byte if byte.is_ascii_whitespace() => i + 1,
+            _ => {
+                splitter.has_code = true;
+                i + 1
+            }
+        };
+        // Comments before a statement are left out of it.
+        if !had_code && splitter.has_code {
+            start = i;
+        }
+        i = next;
+    }
+    if splitter.has_code {
+        statements.push(start..script.len());
+    }
+    statements
+}
+
+/// Whether a statement creates, alters or drops something, so that the
+/// schema read before it is out of date.
+pub fn changes_schema(statement: &str) -> bool {
+    let bytes = statement.as_bytes();
+    let start = bytes
+        .iter()
+        .position(|byte| byte.is_ascii_alphabetic())
+        .unwrap_or(bytes.len());
+    let word = &statement[start..word_end(statement, start)];
+    ["CREATE", "ALTER", "DROP", "RENAME"]
+        .iter()
+        .any(|keyword| keyword.eq_ignore_ascii_case(word))
+}
+
+/// A placeholder for a value in a statement, such as `?`, `?2`, `:name` or
+/// `$1`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Placeholder {
+    pub range: Range<usize>,
+    /// The name the value is asked for under. A bare `?` is numbered one
+    /// past the highest number used before it, counting each name as one,
+    /// as SQLite does.
+    pub name: String,
+}
+
+/// The placeholders in a statement, skipping anything that looks like one in
+/// strings, quoted identifiers and comments. Postgres has no `?`
+/// placeholders, so that JSON operators like `?|` are left alone, only
+/// SQLite names them with `@`, and DuckDB names them with `$` rather than
+/// `:`, which its struct literals and slices use. A `:` in a Postgres array
+/// subscript is a slice, as in `arr[1:n]`, rather than a name.
+pub fn placeholders(statement: &str, dialect: Dialect) -> Vec<Placeholder> {
+    let bytes = statement.as_bytes();
+    let mut placeholders = Vec::new();
+    let mut highest = 0;
+    // Whether each bracket still open is a subscript rather than an array.
+    let mut subscripts = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        let next = bytes.get(i + 1).copied();
+        let starts_name = next.is_some_and(|byte| byte.is_ascii_alphabetic() || byte == b'_');
+        let starts_number = next.is_some_and(|byte| byte.is_ascii_digit());
+        let end = match bytes[i] {
+            b'-' if next == Some(b'-') => {
+                i = line_end(bytes, i);
+                continue;
+            }
+            b'#' if dialect == Dialect::MySql => {
+                i = line_end(bytes, i);
+                continue;
+            }
+            b'/' if next == Some(b'*') => {
+                i = block_comment_end(bytes, i, 0, dialect).0;
+                continue;
+            }
+            quote @ (b'\'' | b'"' | b'`') => {
+                let backslash_escapes = backslash_escapes(bytes, i, dialect);
+                i = quoted_end(bytes, i + 1, quote, backslash_escapes).unwrap_or(bytes.len());
+                continue;
+            }
+            b'[' if dialect == Dialect::Sqlite => {
+                i = find(bytes, i + 1, b"]").map_or(bytes.len(), |end| end + 1);
+                continue;
+            }
+            b'$' if matches!(dialect, Dialect::Postgres | Dialect::DuckDb)
+                && dollar_quote_tag(statement, i).is_some() =>
+            {
+                let tag = dollar_quote_tag(statement, i).unwrap_or_default();
+                i = find(bytes, i + tag.len(), tag.as_bytes())
+                    .map_or(bytes.len(), |end| end + tag.len());
+                continue;
+            }
+            b'[' => {
+                subscripts.push(is_subscript(&statement[..i]));
+                i += 1;
+                continue;
+            }
+            b']' => {
+                subscripts.pop();
+                i += 1;
+                continue;
+            }
+            b':' if next == Some(b':') => {
+                i += 2;
+                continue;
+            }
+            b'?' if dialect != Dialect::Postgres => word_end(statement, i + 1),
+            b'$' if starts_number && dialect != Dialect::MySql => word_end(statement, i + 1),
+            b'$' if starts_name && matches!(dialect, Dialect::Sqlite | Dialect::DuckDb) => {
+                word_end(statement, i + 1)
+            }
+            b':' if starts_name
+                && dialect != Dialect::DuckDb
+                && subscripts.last() != Some(&true)
+                && !(i > 0 && is_word_byte(bytes[i - 1])) =>
+            {
+                word_end(statement, i + 1)
+            }
+            b'@' if dialect == Dialect::Sqlite && starts_name => word_end(statement, i + 1),
+            byte if is_word_byte(byte) => {
+                i = word_end(statement, i);
+                continue;
+            }
+            _ => {
+                i += 1;
+                continue;
+            }
+        };
+        let name = match statement[i + 1..end].parse::<usize>() {
+            Ok(number) => {
+                highest = highest.max(number);
+                statement[i..end].to_string()
+            }
+            Err(_) if end == i + 1 => {
+                highest += 1;
+                format!("?{}", highest)
+            }
+            // A name takes the next number the first time it is used.
+            Err(_) => {
+                let name = statement[i..end].to_string();
+                if !placeholders
+                    .iter()
+                    .any(|placeholder: &Placeholder| placeholder.name == name)
+                {
+                    highest += 1;
+                }
+                name
+            }
+        };
+        placeholders.push(Placeholder {
+            range: i..end,
+            name,
+        });
+        i = end;
+    }
+    placeholders
+}
+
+/// Whether a bracket after this text indexes the value before it, rather
+/// than starting an array like `ARRAY[...]` or `[1, 2]`.
+fn is_subscript(before: &str) -> bool {
+    let before = before.trim_end();
+    match before.chars().last() {
+        Some(')' | ']' | '"' | '\'') => true,
+        Some(c) if is_word_char(c) => {
+            let word = before.rsplit(|c: char| !is_word_char(c)).next();
+            !word.is_some_and(|word| word.eq_ignore_ascii_case("ARRAY"))
+        }
+        _ => false,
+    }
+}
+
+/// Rewrite a statement's placeholders into the form its backend binds, and
+/// list the parameter each value bound is for, in order. Postgres and SQLite
+/// number each distinct parameter, while MySQL and DuckDB take a `?` and a
+/// value for every use of one.
+pub fn bind_placeholders(statement: &str, dialect: Dialect) -> (String, Vec<String>) {
+    let mut sql = String::with_capacity(statement.len());
+    let mut names: Vec<String> = Vec::new();
+    let mut copied = 0;
+    for placeholder in placeholders(statement, dialect) {
+        sql.push_str(&statement[copied..placeholder.range.start]);
+        copied = placeholder.range.end;
+        match dialect {
+            Dialect::Postgres | Dialect::Sqlite => {
+                let position = match names.iter().position(|name| *name == placeholder.name) {
+                    Some(position) => position,
+                    None => {
+                        names.push(placeholder.name);
+                        names.len() - 1
+                    }
+                };
+                let marker = if dialect == Dialect::Postgres {
+                    '$'
+                } else {
+                    '?'
+                };
+                sql.push_str(&format!("{}{}", marker, position + 1));
+            }
+            Dialect::MySql | Dialect::DuckDb => {
+                names.push(placeholder.name);
+                sql.push('?');
+            }
+        }
+    }
+    sql.push_str(&statement[copied..]);
+    (sql, names)
+}
+
+/// The state of the statement being split.
+#[derive(Default)]
+struct Splitter {
+    /// Whether the statement has anything other than whitespace and comments.
+    has_code: bool,
+    /// The first few words, enough to tell what the statement creates.
+    leading_words: Vec<String>,
+    /// How many `BEGIN` and `CASE` blocks of a trigger or routine are open.
+    depth: usize,
+    /// Whether the last word was an `END`, which closes a block unless it is
+    /// part of `END IF`, `END LOOP` and so on.
+    pending_end: bool,
+}
+
+impl Splitter {
+    fn word(&mut self, word: &str) {
+        self.has_code = true;
+        if self.leading_words.len() < 8 {
+            self.leading_words.push(word.to_string());
+        }
+        if std::mem::take(&mut self.pending_end) {
+            match word {
+                "IF" | "LOOP" | "WHILE" | "REPEAT" => return,
+                "CASE" => {
+                    self.depth = self.depth.saturating_sub(1);
+                    return;
+                }
+                _ => self.depth = self.depth.saturating_sub(1),
+            }
+        }
+        if self.defines_routine() {
+            match word {
+                "BEGIN" | "CASE" => self.depth += 1,
+                "END" => self.pending_end = true,
+                _ => {}
+            }
+        }
+    }
+
+    /// Whether a semicolon here ends the statement, rather than a statement
+    /// inside a trigger or routine body.
+    fn end_of_statement(&mut self) -> bool {
+        if std::mem::take(&mut self.pending_end) {
+            self.depth = self.depth.saturating_sub(1);
+        }
+        self.depth == 0
+    }
+
+    /// Whether the statement creates a trigger, routine or event, going by
+    /// the kind of thing named after `CREATE` and its modifiers, so that a
+    /// column named `function` does not count.
+    fn defines_routine(&self) -> bool {
+        let mut words = self.leading_words.iter().map(String::as_str);
+        if words.next() != Some("CREATE") {
+            return false;
+        }
+        // MySQL's `DEFINER = user@host` comes before the kind.
+        let mut in_definer = false;
+        for word in words {
+            match word {
+                "TRIGGER" | "PROCEDURE" | "FUNCTION" | "EVENT" => return true,
+                "OR" | "REPLACE" | "TEMP" | "TEMPORARY" | "CONSTRAINT" => {}
+                "DEFINER" => in_definer = true,
+                _ if in_definer => {}
+                _ => return false,
+            }
+        }
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn split(script: &str, dialect: Dialect) -> Vec<String> {
+        split_statements(script, dialect)
+    }
+
+    #[test]
+    fn splits_on_semicolons() {
+        assert_eq!(
+            split("select 1; select 2;", Dialect::Sqlite),
+            ["select 1", "select 2"]
+        );
+        assert_eq!(split(";; select 1 ;;", Dialect::Sqlite), ["select 1"]);
+        assert!(split("  \n ", Dialect::Sqlite).is_empty());
+    }
+
+    #[test]
+    fn keeps_a_last_statement_without_a_semicolon() {
+        assert_eq!(
+            split("select 1;\nselect 2", Dialect::Postgres),
+            ["select 1", "select 2"]
+        );
+        assert_eq!(
+            split("select 1; select 2 -- the last", Dialect::Postgres),
+            ["select 1", "select 2 -- the last"]
+        );
+    }
+
+    #[test]
+    fn skips_semicolons_in_quotes() {
+        assert_eq!(
+            split("select 'a;b', \"c;d\"; select 'it''s;'", Dialect::Sqlite),
+            ["select 'a;b', \"c;d\"", "select 'it''s;'"]
+        );
+        assert_eq!(
+            split("select `a;b` from [c;d]; select 2", Dialect::Sqlite),
+            ["select `a;b` from [c;d]", "select 2"]
+        );
+        assert_eq!(
+            split("select 'a\\';'; select 2", Dialect::MySql),
+            ["select 'a\\';'", "select 2"]
+        );
+    }
+
+    #[test]
+    fn escapes_backslashes_only_where_the_dialect_does() {
+        assert_eq!(
+            split("select E'a\\';'; select 2", Dialect::Postgres),
+            ["select E'a\\';'", "select 2"]
+        );
+        assert_eq!(
+            split("select 'a\\'; select 2", Dialect::Postgres),
+            ["select 'a\\'", "select 2"]
+        );
+        assert_eq!(
+            split("select e'a\\'; select 2", Dialect::Sqlite),
+            ["select e'a\\'", "select 2"]
+        );
+        assert_eq!(
+            split("select some'a\\'; select 2", Dialect::Postgres),
+            ["select some'a\\'", "select 2"]
+        );
+    }
+
+    #[test]
+    fn skips_semicolons_in_comments() {
+        assert_eq!(
+            split(
+                "-- first; still a comment\nselect 1; /* a; b */ select 2; -- done;",
+                Dialect::Sqlite
+            ),
+            ["select 1", "select 2"]
+        );
+        assert_eq!(
+            split("# a; b\nselect 1; select 2", Dialect::MySql),
+            ["select 1", "select 2"]
+        );
+        assert_eq!(
+            split("select 1 # a; b", Dialect::Sqlite),
+            ["select 1 # a", "b"]
+        );
+    }
+
+    #[test]
+    fn nests_block_comments_only_on_postgres() {
+        assert_eq!(
+            split("/* a /* b; */ c; */ select 1", Dialect::Postgres),
+            ["select 1"]
+        );
+        assert_eq!(
+            split("/* a /* b */ select 1; select 2", Dialect::Sqlite),
+            ["select 1", "select 2"]
+        );
+    }
+
+    #[test]
+    fn skips_dollar_quoted_bodies() {
+        let script = "create function f() returns int as $$ begin return 1; end; $$ \
+                      language plpgsql; select 2";
+        assert_eq!(
+            split(script, Dialect::Postgres),
+            [
+                "create function f() returns int as $$ begin return 1; end; $$ \
+                 language plpgsql",
+                "select 2"
+            ]
+        );
+        assert_eq!(
+            split("select $body$ a; $$ b; $body$; select 2", Dialect::DuckDb),
+            ["select $body$ a; $$ b; $body$", "select 2"]
+        );
+        assert_eq!(
+            split("select $1; select $2", Dialect::Postgres),
+            ["select $1", "select $2"]
+        );
+    }
+
+    #[test]
+    fn keeps_trigger_bodies_whole() {
+        let trigger = "create trigger t after insert on a begin \
+                       insert into b values (1); \
+                       update c set x = case when 1 then 2 end; \
+                       end";
+        assert_eq!(
+            split(&format!("{}; select 1", trigger), Dialect::Sqlite),
+            [trigger, "select 1"]
+        );
+        let temporary = "CREATE TEMP TRIGGER t BEFORE DELETE ON a BEGIN SELECT 1; END";
+        assert_eq!(
+            split(&format!("{};SELECT 2;", temporary), Dialect::Sqlite),
+            [temporary, "SELECT 2"]
+        );
+    }
+
+    #[test]
+    fn keeps_routine_bodies_whole() {
+        let procedure = "create definer = admin@localhost procedure p() begin \
+                         if x then select 1; end if; \
+                         while y do set y = y - 1; end while; \
+                         end";
+        assert_eq!(
+            split(&format!("{}; select 2", procedure), Dialect::MySql),
+            [procedure, "select 2"]
+        );
+        let function = "create or replace function f() returns int begin atomic \
+                        select 1; end";
+        assert_eq!(
+            split(&format!("{}; select 2", function), Dialect::Postgres),
+            [function, "select 2"]
+        );
+    }
+
+    #[test]
+    fn does_not_take_a_column_named_function_for_a_routine() {
+        assert_eq!(
+            split(
+                "create view v as select function, begin from t; select 1",
+                Dialect::Postgres
+            ),
+            ["create view v as select function, begin from t", "select 1"]
+        );
+        assert_eq!(
+            split(
+                "create table t (function text, begin int); select 1",
+                Dialect::Sqlite
+            ),
+            ["create table t (function text, begin int)", "select 1"]
+        );
+    }
+
+    #[test]
+    fn ranges_start_after_leading_comments() {
+        let script = "-- note\nselect 1; select 2";
+        let ranges = statement_ranges(script, Dialect::Sqlite);
+        assert_eq!(ranges, [8..16, 18..26]);
+        assert_eq!(&script[ranges[0].clone()], "select 1");
+    }
+
+    #[test]
+    fn tells_statements_that_change_the_schema() {
+        assert!(changes_schema("  CREATE TABLE t (a int)"));
+        assert!(changes_schema("drop view v"));
+        assert!(!changes_schema("select * from created"));
+        assert!(!changes_schema(""));
+    }
+
+    fn names(statement: &str, dialect: Dialect) -> Vec<String> {
+        placeholders(statement, dialect)
+            .into_iter()
+            .map(|placeholder| placeholder.name)
+            .collect()
+    }
+
+    #[test]
+    fn numbers_bare_question_marks_after_the_highest_so_far() {
+        assert_eq!(
+            names("select ?, ?5, ?", Dialect::Sqlite),
+            ["?1", "?5", "?6"]
+        );
+        assert_eq!(names("select ?, ?", Dialect::MySql), ["?1", "?2"]);
+        assert!(names("select data ?| array['a']", Dialect::Postgres).is_empty());
+    }
+
+    #[test]
+    fn names_placeholders_the_way_each_dialect_does() {
+        let statement = "select :a, @b, $c, $1";
+        assert_eq!(names(statement, Dialect::Sqlite), [":a", "@b", "$c", "$1"]);
+        assert_eq!(names(statement, Dialect::Postgres), [":a", "$1"]);
+        assert_eq!(names(statement, Dialect::MySql), [":a"]);
+        assert_eq!(names(statement, Dialect::DuckDb), ["$c", "$1"]);
+    }
+
+    #[test]
+    fn skips_placeholders_in_strings_comments_and_names() {
+        let statement = "select ':a', \"?\" -- :b\n, /* $1 */ :c";
+        assert_eq!(names(statement, Dialect::Sqlite), [":c"]);
+        assert_eq!(names("select $$ :a $$, :b", Dialect::Postgres), [":b"]);
+        assert_eq!(names("select [:a], :b", Dialect::Sqlite), [":b"]);
+    }
+
+    #[test]
+    fn leaves_casts_and_slices_alone() {
+        assert!(names("select x::text, '1'::int", Dialect::Postgres).is_empty());
+        assert_eq!(names("select $1::int", Dialect::Postgres), ["$1"]);
+        assert!(names("select arr[1:n], f(x)[:n], a.b[2:3]", Dialect::Postgres).is_empty());
+        assert_eq!(
+            names("select array[:a, :b]", Dialect::Postgres),
+            [":a", ":b"]
+        );
+        assert!(names("select arr[:n][1]", Dialect::Postgres).is_empty());
+        assert!(names("select l[1:n], {'k': v}", Dialect::DuckDb).is_empty());
+    }
+
+    #[test]
+    fn binds_in_the_form_each_backend_takes() {
+        let statement = "select :a, :b, :a";
+        assert_eq!(
+            bind_placeholders(statement, Dialect::Postgres),
+            (
+                "select $1, $2, $1".to_string(),
+                vec![":a".into(), ":b".into()]
+            )
+        );
+        assert_eq!(
+            bind_placeholders(statement, Dialect::MySql),
+            (
+                "select ?, ?, ?".to_string(),
+                vec![":a".into(), ":b".into(), ":a".into()]
+            )
+        );
+    }
+}
@@ -1,97 +1,1461 @@
-use crate::app::UiState;
-use crate::viewstate::ViewStateBox;
-use crate::{viewstate, App};
-use ratatui::layout::{Constraint, Direction, Layout, Rect};
-use ratatui::widgets::{Block, Borders, Cell, Row as TableRow, Table, TableState};
+use crate::database::redact_url;
+use crate::edit::input_value;
+use crate::export::ExportFormat;
+use crate::highlight::TokenKind;
+use crate::import::CsvPreview;
+use crate::model::{
+    self, BindParameters, BrowseHistory, BrowseLibrary, BrowseSqliteDBFiles, CellValue,
+    ConfirmQuit, ConnectionEntry, EditCell, EditQuery, ExplainQuery, ExploreConnection, Grid,
+    ImportCsv, ImportState, InsertRow, InspectTable, Outcome, Results, SaveResults, SchemaItem,
+    StatementResult,
+};
+use crate::plan::{PlanKind, PlanNode};
+use ratatui::layout::{Constraint, Direction, Layout, Margin, Rect};
+use ratatui::style::{Color, Modifier, Style, Stylize};
+use ratatui::text::{Line, Span, Text};
+use ratatui::widgets::{
+    Block, Borders, Cell, Clear, Gauge, List, ListItem, Paragraph, Row as TableRow, Table,
+    TableState, Tabs, Wrap,
+};
 use ratatui::Frame;
-use ratatui_explorer::{FileExplorer, Theme};
-use sqlx::Column;
-use sqlx::Row;
-use std::error::Error;
+use sqlx::types::chrono::Local;
+use std::str::FromStr;
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
 
-fn render_explorer(f: &mut Frame, app: &mut App) -> Result<(), Box<dyn Error>> {
-    match &app.explorer {
-        None => {
-            let theme = Theme::default().add_default_title();
-            let file_explorer = FileExplorer::with_theme(theme)?;
-            f.render_widget(&file_explorer.widget(), f.size());
-            app.explorer = Some(file_explorer);
-        }
-        Some(file_explorer) => {
-            f.render_widget(&file_explorer.widget(), f.size());
+/// A table of result rows, with the cell and rows picked out in the grid.
+fn render_table(
+    frame: &mut Frame,
+    area: Rect,
+    results: &Results,
+    block: Block,
+    state: &mut TableState,
+    mut grid: Option<&mut Grid>,
+) {
+    let Results::Some {
+        headers,
+        data,
+        widths,
+    } = results
+    else {
+        return;
+    };
+    let selected = state.selected();
+    let widths = widths
+        .iter()
+        .enumerate()
+        .map(
+            |(i, &width)| match grid.as_ref().and_then(|grid| grid.widths.get(&i)) {
+                Some(&width) => width,
+                None => width,
+            },
+        )
+        .collect::<Vec<_>>();
+    // Leave room for the highlight symbol.
+    let space = block.inner(area).width.saturating_sub(2);
+    let columns = match grid.as_mut() {
+        Some(grid) => visible_columns(grid, &widths, space),
+        None => widths.iter().copied().enumerate().collect(),
+    };
+    let grid = grid.as_deref();
+    let column = grid.map(|grid| grid.column);
+    let frozen = grid.map_or(0, |grid| grid.frozen);
+    let rows = data.iter().enumerate().map(|(index, row)| {
+        let marked = grid.is_some_and(|grid| grid.marked.contains(&index));
+        let row = TableRow::new(
+            columns
+                .iter()
+                .filter_map(|&(i, width)| {
+                    let cell = match row.get(i)? {
+                        CellValue::Null => Cell::from(fit("NULL".to_string(), width))
+                            .style(Style::new().dark_gray().italic()),
+                        value => Cell::from(fit(value.to_line(), width)),
+                    };
+                    match selected == Some(index) && column == Some(i) {
+                        true => Some(cell.reversed()),
+                        false => Some(cell),
+                    }
+                })
+                .collect::<Vec<_>>(),
+        );
+        match marked {
+            true => row.red(),
+            false => row,
         }
+    });
+
+    let constraints = columns.iter().map(|&(_, width)| Constraint::Length(width));
+    let table = Table::new(rows, constraints)
+        .header(TableRow::new(
+            columns
+                .iter()
+                .map(|&(i, width)| {
+                    let cell = Cell::from(fit(headers[i].clone(), width));
+                    let cell = match i < frozen {
+                        true => cell.bold(),
+                        false => cell,
+                    };
+                    match column == Some(i) {
+                        true => cell.underlined(),
+                        false => cell,
+                    }
+                })
+                .collect::<Vec<_>>(),
+        ))
+        .block(block)
+        .highlight_symbol(">>");
+
+    frame.render_stateful_widget(table, area, state);
+}
+
+/// The columns that fit across a table, with the width each is shown at:
+/// the frozen columns, then the others from the grid's offset, which is
+/// moved to keep the picked column in view. A column that only partly fits
+/// is cut short.
+fn visible_columns(grid: &mut Grid, widths: &[u16], space: u16) -> Vec<(usize, u16)> {
+    let count = widths.len();
+    let space = space as usize;
+    let frozen = grid.frozen.min(count);
+    // The width of a run of columns, each followed by a space.
+    let span = |from: usize, to: usize| -> usize {
+        widths[from..to]
+            .iter()
+            .map(|&width| width as usize + 1)
+            .sum()
     };
-    Ok(())
+    grid.offset = grid.offset.clamp(frozen, count);
+    // A column past the last, from wider results before, stands for the last.
+    let column = grid.column.min(count.saturating_sub(1));
+    if (frozen..count).contains(&column) {
+        let free = space.saturating_sub(span(0, frozen)) + 1;
+        grid.offset = grid.offset.min(column);
+        while grid.offset < column && span(grid.offset, column + 1) > free {
+            grid.offset += 1;
+        }
+        // Bring back columns on the left rather than leave room on the right.
+        while grid.offset > frozen && span(grid.offset - 1, count) <= free {
+            grid.offset -= 1;
+        }
+    }
+    let mut columns = Vec::new();
+    let mut used = 0;
+    for i in (0..frozen).chain(grid.offset..count) {
+        let left = space.saturating_sub(used);
+        if left < 2 {
+            break;
+        }
+        columns.push((i, widths[i].min(left as u16)));
+        used += widths[i] as usize + 1;
+    }
+    columns
 }
 
-fn render_query(f: &mut Frame, app: &mut App) -> Result<(), Box<dyn Error>> {
-    let (headers, data) = extract_row_data(&app).unwrap();
-    let size = f.size();
+/// Text cut down to a display width, ending in an ellipsis if it was cut.
+fn fit(text: String, width: u16) -> String {
+    let width = width as usize;
+    if text.width() <= width {
+        return text;
+    }
+    if width == 0 {
+        return String::new();
+    }
+    let mut fitted = String::new();
+    let mut used = 0;
+    for c in text.chars() {
+        let char_width = c.width().unwrap_or(0);
+        if used + char_width + 1 > width {
+            break;
+        }
+        used += char_width;
+        fitted.push(c);
+    }
+    fitted.push('…');
+    fitted
+}
+
+/// A bordered block, highlighted when it has focus.
+pub fn panel<'a>(title: impl Into<Line<'a>>, focused: bool) -> Block<'a> {
+    let block = Block::default().title(title).borders(Borders::ALL);
+    match focused {
+        true => block.border_style(Style::default().fg(Color::Yellow)),
+        false => block,
+    }
+}
+
+/// Split off a one line status bar at the bottom of an area.
+pub fn split_status_line(area: Rect) -> (Rect, Rect) {
     let chunks = Layout::default()
         .direction(Direction::Vertical)
-        .margin(1)
-        .constraints([Constraint::Length(12), Constraint::Min(5)].as_ref())
-        .split(size);
+        .constraints([Constraint::Min(1), Constraint::Length(1)])
+        .split(area);
+    (chunks[0], chunks[1])
+}
 
-    // Render input field
-    f.render_widget(app.query_input.widget(), chunks[0]);
+const SPINNER: [char; 10] = ['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'];
 
-    // Render table
-    render_table(f, chunks[1], &headers, &data, &mut app.table_state);
-    Ok(())
+/// The status bar, or a spinner with the elapsed time while a query runs.
+pub fn render_status<Mode>(frame: &mut Frame, area: Rect, app: &model::App<Mode>) {
+    let fetching = app
+        .running()
+        .and_then(|running| Some((running, running.fetching?)));
+    let status = match fetching {
+        Some((running, started)) => {
+            let elapsed = started.elapsed();
+            let frame_index = (elapsed.as_millis() / 100) as usize % SPINNER.len();
+            format!(
+                "{} Running on {} for {:.1}s (Ctrl+C to cancel)",
+                SPINNER[frame_index],
+                running.connection,
+                elapsed.as_secs_f32()
+            )
+        }
+        None => app.status().unwrap_or_default().to_string(),
+    };
+    frame.render_widget(Paragraph::new(status), area);
 }
 
-/// Execute a query from the input field and update the results.
-fn render_table(
+/// The title of the results panel, saying where the results came from and
+/// whether there are more rows to fetch or left behind.
+pub fn results_title<Mode>(app: &model::App<Mode>) -> String {
+    let title = match app.results_connection() {
+        Some(name) => format!("SQL Results ({})", name),
+        None => "SQL Results".to_string(),
+    };
+    let truncated = app
+        .statement_results()
+        .get(app.selected_result())
+        .is_some_and(|result| result.truncated);
+    match (app.more_rows(), truncated) {
+        (true, _) => format!(
+            "{} - {} rows loaded, more available",
+            title,
+            app.results().len()
+        ),
+        (false, true) => format!("{} - first {} rows only", title, app.results().len()),
+        (false, false) => title,
+    }
+}
+
+/// The results of each statement of the last script, with a tab per
+/// statement when there was more than one.
+pub fn render_results(
     frame: &mut Frame,
     area: Rect,
-    headers: &[String],
-    data: &[Vec<String>],
+    results: &[StatementResult],
+    selected: usize,
+    block: Block,
     state: &mut TableState,
+    grid: Option<&mut Grid>,
 ) {
-    let rows = data.iter().map(|row| {
-        TableRow::new(
-            row.iter()
-                .map(|cell| Cell::from(cell.as_str()))
-                .collect::<Vec<_>>(),
-        )
+    let (area, block) = match results.len() > 1 {
+        true => {
+            let inner = block.inner(area);
+            frame.render_widget(block, area);
+            let [tabs, body] = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([Constraint::Length(1), Constraint::Min(0)])
+                .areas(inner);
+            let titles = results.iter().enumerate().map(|(index, result)| {
+                let verb = result
+                    .statement
+                    .split_whitespace()
+                    .next()
+                    .unwrap_or_default();
+                let title = format!("{} {}", index + 1, verb.to_uppercase());
+                match result.outcome {
+                    Outcome::Failed(_) => Line::from(title).red(),
+                    _ => Line::from(title),
+                }
+            });
+            frame.render_widget(Tabs::new(titles).select(selected).bold(), tabs);
+            (body, Block::default())
+        }
+        false => (area, block),
+    };
+    let message = match results.get(selected).map(|result| &result.outcome) {
+        Some(Outcome::Rows(results @ Results::Some { .. })) => {
+            return render_table(frame, area, results, block, state, grid);
+        }
+        Some(Outcome::Affected(rows)) => Line::from(format!("{} rows affected", rows)),
+        Some(Outcome::Failed(error)) => Line::from(error.as_str()).red(),
+        Some(Outcome::Rows(Results::None)) | None => Line::from("No results"),
+    };
+    let paragraph = Paragraph::new(message).wrap(Wrap { trim: false });
+    frame.render_widget(paragraph.block(block), area);
+}
+
+pub fn render_query_summary(frame: &mut Frame, area: Rect, query: &str, focused: bool) {
+    let block = panel("Query (h history)", focused);
+    frame.render_widget(Paragraph::new(query).block(block), area);
+}
+
+/// The colour configured for a profile, if it has one ratatui understands.
+fn profile_colour(app: &model::App<impl Sized>, name: &str) -> Option<Color> {
+    let colour = app.config().profiles.get(name)?.colour.as_deref()?;
+    Color::from_str(colour).ok()
+}
+
+fn connection_name_style(app: &model::App<impl Sized>, name: &str) -> Style {
+    Style::default().fg(profile_colour(app, name).unwrap_or(Color::Reset))
+}
+
+pub fn render_connection_summary<Mode>(
+    frame: &mut Frame,
+    area: Rect,
+    app: &model::App<Mode>,
+    focused: bool,
+) {
+    let connections = app.connections();
+    let line = match connections.active() {
+        Some(active) => {
+            let mut spans = vec![
+                Span::raw("Connected to "),
+                Span::styled(
+                    active.name.as_str(),
+                    connection_name_style(app, &active.name),
+                )
+                .bold(),
+            ];
+            if connections.open().len() > 1 {
+                spans.push(Span::raw(format!(" ({} open)", connections.open().len())));
+            }
+            spans.extend(transaction_indicator(app));
+            Line::from(spans)
+        }
+        None => Line::from("Not connected"),
+    };
+    frame.render_widget(
+        Paragraph::new(line).block(panel("Connection", focused)),
+        area,
+    );
+}
+
+/// A badge showing that a transaction is open and how many statements have
+/// run in it.
+pub fn transaction_indicator<Mode>(app: &model::App<Mode>) -> Option<Span<'static>> {
+    let transaction = app.transaction()?;
+    let badge = format!(
+        " TXN OPEN on {} / {} pending statements ",
+        transaction.connection, transaction.pending
+    );
+    Some(Span::raw(badge).black().on_yellow().bold())
+}
+
+pub fn render_confirm_quit(frame: &mut Frame, area: Rect, app: &model::App<ConfirmQuit>) {
+    let (connection, pending) = app
+        .transaction()
+        .map(|transaction| (transaction.connection.as_str(), transaction.pending))
+        .unwrap_or_default();
+    let text = vec![
+        Line::from(format!(
+            "A transaction is still open on {} with {} pending statements.",
+            connection, pending
+        )),
+        Line::from(""),
+        Line::from("Quitting rolls it back. Quit anyway? (y/n)").bold(),
+    ];
+    frame.render_widget(
+        Paragraph::new(text)
+            .wrap(Wrap { trim: false })
+            .block(panel("Quit", true)),
+        area,
+    );
+}
+
+/// The completion popup, under the word being completed in the editor
+/// drawn in `area`, or over it when there is no room below.
+pub fn render_completion(frame: &mut Frame, area: Rect, app: &mut model::App<EditQuery>) {
+    let (row, column) = app.mode.editor.cursor();
+    let Some(popup) = &mut app.mode.completion else {
+        return;
+    };
+    let name_width = popup
+        .completions
+        .iter()
+        .map(|completion| completion.name.chars().count())
+        .max()
+        .unwrap_or(0);
+    let width = (name_width + 15).min(frame.size().width as usize) as u16;
+    let height = (popup.completions.len().min(8) + 2) as u16;
+    // The editor scrolls to keep the cursor in view, so a cursor past the
+    // bottom is on its last line.
+    let inner_height = area.height.saturating_sub(2);
+    let cursor_y = area.y + 1 + (row as u16).min(inner_height.saturating_sub(1));
+    let word_x = area.x + 1 + column.saturating_sub(popup.replaces) as u16;
+    let screen = frame.size();
+    let y = if cursor_y + 1 + height <= screen.bottom() {
+        cursor_y + 1
+    } else {
+        cursor_y.saturating_sub(height)
+    };
+    let x = word_x.min(screen.right().saturating_sub(width));
+    let items = popup
+        .completions
+        .iter()
+        .map(|completion| {
+            ListItem::new(Line::from(vec![
+                Span::raw(format!("{:width$} ", completion.name, width = name_width)),
+                Span::raw(completion.kind.label()).dim(),
+            ]))
+        })
+        .collect::<Vec<_>>();
+    let list = List::new(items)
+        .block(Block::default().borders(Borders::ALL))
+        .highlight_style(Style::default().reversed());
+    let area = Rect::new(x, y, width, height.min(screen.height));
+    frame.render_widget(Clear, area);
+    frame.render_stateful_widget(list, area, &mut popup.list);
+}
+
+/// Colour the SQL in the query editor drawn in `area`, over what the editor
+/// drew, and the bracket at the cursor with the one matching it. Only the
+/// lines on screen are lexed.
+pub fn highlight_query(frame: &mut Frame, area: Rect, app: &mut model::App<EditQuery>) {
+    let theme = &app.config().theme;
+    let colour = |setting: &Option<String>, default: Color| {
+        setting
+            .as_deref()
+            .and_then(|colour| Color::from_str(colour).ok())
+            .unwrap_or(default)
+    };
+    let style = |kind: TokenKind| {
+        Style::default().fg(match kind {
+            TokenKind::Keyword => colour(&theme.keyword, Color::Blue),
+            TokenKind::Identifier => colour(&theme.identifier, Color::Cyan),
+            TokenKind::String => colour(&theme.string, Color::Green),
+            TokenKind::Number => colour(&theme.number, Color::Yellow),
+            TokenKind::Comment => colour(&theme.comment, Color::DarkGray),
+            TokenKind::Parameter => colour(&theme.parameter, Color::Magenta),
+            TokenKind::Bracket => Color::Reset,
+        })
+    };
+    let bracket_style = Style::default()
+        .fg(colour(&theme.matching_bracket, Color::LightRed))
+        .add_modifier(Modifier::BOLD);
+    let styles = [
+        TokenKind::Keyword,
+        TokenKind::Identifier,
+        TokenKind::String,
+        TokenKind::Number,
+        TokenKind::Comment,
+        TokenKind::Parameter,
+        TokenKind::Bracket,
+    ]
+    .map(|kind| (kind, style(kind)));
+    let dialect = app.dialect();
+
+    let mode = &mut app.mode;
+    let inner = area.inner(&Margin::new(1, 1));
+    let (row, column) = mode.editor.cursor();
+    // The same as the editor scrolls to keep the cursor in view.
+    let next_top = |top: u16, cursor: usize, length: u16| {
+        let cursor = cursor as u16;
+        if cursor < top {
+            cursor
+        } else if top + length <= cursor {
+            cursor + 1 - length
+        } else {
+            top
+        }
+    };
+    mode.scroll_top = (
+        next_top(mode.scroll_top.0, row, inner.height),
+        next_top(mode.scroll_top.1, column, inner.width),
+    );
+    let (top_row, top_column) = mode.scroll_top;
+    let lines = mode.editor.lines();
+    let brackets = mode
+        .highlighter
+        .matching_brackets(lines, (row, column), dialect);
+    let tab_length = mode.editor.tab_length() as usize;
+    let buffer = frame.buffer_mut();
+    let bottom = lines.len().min(top_row as usize + inner.height as usize);
+    for (y, current) in (inner.y..).zip(top_row as usize..bottom) {
+        let line = &lines[current];
+        let tokens = mode.highlighter.line(lines, current, dialect);
+        let mut tokens = tokens.iter().peekable();
+        let mut x = 0;
+        for (offset, c) in line.char_indices() {
+            let width = match c {
+                '\t' if tab_length > 0 => tab_length - x % tab_length,
+                c => c.width().unwrap_or(0),
+            };
+            while tokens.next_if(|token| token.range.end <= offset).is_some() {}
+            let token_style = tokens
+                .peek()
+                .filter(|token| token.range.start <= offset)
+                .and_then(|token| styles.iter().find(|(kind, _)| *kind == token.kind))
+                .map(|(_, style)| *style);
+            let matched = brackets.is_some_and(|pair| pair.contains(&(current, offset)));
+            let cell_style = if matched {
+                Some(bracket_style)
+            } else {
+                token_style
+            };
+            if let Some(cell_style) = cell_style {
+                for column in x..x + width {
+                    let Some(screen_x) = column.checked_sub(top_column as usize) else {
+                        continue;
+                    };
+                    if screen_x < inner.width as usize {
+                        buffer
+                            .get_mut(inner.x + screen_x as u16, y)
+                            .set_style(cell_style);
+                    }
+                }
+            }
+            x += width;
+        }
+    }
+}
+
+/// The query about to run above a form asking for its parameters, each
+/// with what kind of value it will be bound as.
+pub fn render_parameters(frame: &mut Frame, area: Rect, app: &model::App<BindParameters>) {
+    let inputs = &app.mode.inputs;
+    let [query, form] = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Min(3),
+            Constraint::Length(inputs.len() as u16 + 2),
+        ])
+        .areas(area);
+    let title = match app.connections().active() {
+        Some(open) => format!("Query on {}", open.name),
+        None => "Query (not connected)".to_string(),
+    };
+    frame.render_widget(
+        Paragraph::new(app.current_query()).block(panel(title, false)),
+        query,
+    );
+    let name_width = inputs
+        .iter()
+        .map(|input| input.name.chars().count())
+        .max()
+        .unwrap_or(0);
+    let lines = inputs
+        .iter()
+        .enumerate()
+        .map(|(index, input)| {
+            let selected = index == app.mode.selected;
+            let name = format!(
+                "{} {:width$} ",
+                if selected { ">>" } else { "  " },
+                input.name,
+                width = name_width
+            );
+            let hint = CellValue::from_input(&input.value).type_name();
+            Line::from(vec![
+                Span::from(name).magenta(),
+                Span::from(input.value.clone()),
+                Span::from(format!("  {}", hint)).dim(),
+            ])
+        })
+        .collect::<Vec<_>>();
+    frame.render_widget(
+        Paragraph::new(lines).block(panel(
+            "Parameters (Enter run, Tab next, Esc back to the editor)",
+            true,
+        )),
+        form,
+    );
+    if let Some(input) = inputs.get(app.mode.selected) {
+        let x = form.x + 1 + name_width as u16 + 4 + input.value.chars().count() as u16;
+        let y = form.y + 1 + app.mode.selected as u16;
+        frame.set_cursor(x.min(form.right().saturating_sub(2)), y);
+    }
+}
+
+/// Where to write the results being shown and in which format, with the
+/// formats to choose from.
+pub fn render_save_results(frame: &mut Frame, area: Rect, app: &model::App<SaveResults>) {
+    let mut formats = vec![Span::from("Format: ").magenta()];
+    for format in ExportFormat::ALL {
+        let name = Span::from(format!(" {} ", format.name()));
+        formats.push(match format == app.mode.format {
+            true => name.reversed(),
+            false => name,
+        });
+    }
+    let lines = vec![
+        Line::from(vec![
+            Span::from("File:   ").magenta(),
+            Span::from(app.mode.path.clone()),
+        ]),
+        Line::from(formats),
+    ];
+    frame.render_widget(
+        Paragraph::new(lines).block(panel(
+            "Export (Enter save, Tab format, Esc back to the results)",
+            true,
+        )),
+        area,
+    );
+    let x = area.x + 9 + app.mode.path.chars().count() as u16;
+    frame.set_cursor(x.min(area.right().saturating_sub(2)), area.y + 1);
+}
+
+/// The new value for a cell of the results, then the statement that sets
+/// it, to be confirmed.
+pub fn render_edit_cell(frame: &mut Frame, area: Rect, app: &model::App<EditCell>) {
+    let edit = &app.mode;
+    if let Some((_, shown)) = &edit.update {
+        return render_confirm_change(frame, area, app, shown);
+    }
+    let column = edit.source.columns[edit.column]
+        .as_deref()
+        .unwrap_or_default();
+    let was = app
+        .edited_row()
+        .get(edit.column)
+        .map(ToString::to_string)
+        .unwrap_or_default();
+    let hint = app.edited_value().type_name();
+    let mut name = vec![Span::from(format!("{}.{}", edit.source.table, column)).magenta()];
+    if edit.source.is_key(edit.column) {
+        name.push(Span::from("  part of the key").yellow());
+    }
+    let lines = vec![
+        Line::from(name),
+        Line::from(vec![Span::from("Was:   ").magenta(), Span::from(was).dim()]),
+        Line::from(vec![
+            Span::from("Value: ").magenta(),
+            Span::from(edit.input.clone()),
+            Span::from(format!("  {}", hint)).dim(),
+        ]),
+    ];
+    frame.render_widget(
+        Paragraph::new(lines).block(panel(
+            "Edit (Enter review the change, Esc back to the results)",
+            true,
+        )),
+        area,
+    );
+    let x = area.x + 8 + edit.input.chars().count() as u16;
+    frame.set_cursor(x.min(area.right().saturating_sub(2)), area.y + 3);
+}
+
+/// A statement that changes rows of the results, waiting to be confirmed.
+pub fn render_confirm_change<Mode>(
+    frame: &mut Frame,
+    area: Rect,
+    app: &model::App<Mode>,
+    statement: &str,
+) {
+    let in_transaction = app.transaction().is_some_and(|transaction| {
+        Some(transaction.connection.as_str()) == app.results_connection()
     });
+    let title = match in_transaction {
+        true => "Run in the open transaction? (Enter or y run, Esc or n go back)",
+        false => "Run this? (Enter or y run, Esc or n go back)",
+    };
+    let paragraph = Paragraph::new(statement).wrap(Wrap { trim: false });
+    frame.render_widget(paragraph.block(panel(title, true)), area);
+}
 
-    let table = Table::new(rows, [15, 20, 30])
-        .header(TableRow::new(
-            headers
+/// A value for each of a table's columns, to insert a row, with what the
+/// schema says about the column beside it. Empty fields take the column's
+/// default.
+pub fn render_insert_row(frame: &mut Frame, area: Rect, app: &model::App<InsertRow>) {
+    let insert = &app.mode;
+    if let Some((_, shown)) = &insert.insert {
+        return render_confirm_change(frame, area, app, shown);
+    }
+    let fields = &insert.source.fields;
+    let name_width = fields
+        .iter()
+        .map(|field| field.name.chars().count())
+        .max()
+        .unwrap_or(0);
+    let lines = fields
+        .iter()
+        .zip(&insert.inputs)
+        .enumerate()
+        .map(|(index, (field, input))| {
+            let name = format!(
+                "{} {:width$} ",
+                if index == insert.selected { ">>" } else { "  " },
+                field.name,
+                width = name_width
+            );
+            let mut spans = vec![Span::from(name).magenta()];
+            match (input.is_empty(), &field.default) {
+                (false, _) => {
+                    spans.push(Span::from(input.clone()));
+                    let hint = input_value(input, Some(field)).type_name();
+                    spans.push(Span::from(format!("  {}", hint)).dim());
+                }
+                (true, Some(default)) => {
+                    spans.push(Span::from(format!("default {}", default)).dim())
+                }
+                (true, None) if field.primary_key => spans.push(Span::from("key").dim()),
+                (true, None) if field.not_null => spans.push(Span::from("required").red()),
+                (true, None) => spans.push(Span::from("NULL").dim()),
+            }
+            spans.push(Span::from(format!("  {}", field.data_type)).dim());
+            Line::from(spans)
+        })
+        .collect::<Vec<_>>();
+    let visible = area.height.saturating_sub(2) as usize;
+    let scroll = (insert.selected + 1).saturating_sub(visible) as u16;
+    let title = format!(
+        "Insert into {} (Enter review, Tab next, empty for the default, Esc back)",
+        insert.source.table
+    );
+    frame.render_widget(
+        Paragraph::new(lines)
+            .scroll((scroll, 0))
+            .block(panel(title, true)),
+        area,
+    );
+    if let Some(input) = insert.inputs.get(insert.selected) {
+        let x = area.x + 1 + name_width as u16 + 4 + input.chars().count() as u16;
+        let y = area.y + 1 + insert.selected as u16 - scroll;
+        frame.set_cursor(x.min(area.right().saturating_sub(2)), y);
+    }
+}
+
+/// The files of a directory, to open one as a database or import it.
+pub fn render_files(frame: &mut Frame, area: Rect, app: &model::App<BrowseSqliteDBFiles>) {
+    let [files, hint] = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(3), Constraint::Length(1)])
+        .areas(area);
+    match &app.mode.explorer {
+        Some(explorer) => frame.render_widget(&explorer.widget(), files),
+        None => frame.render_widget(panel("Files", true), files),
+    }
+    let keys =
+        "Enter open a database or import a CSV file, i import any file, h up a directory, Esc back";
+    frame.render_widget(Paragraph::new(Line::from(keys).dim()), hint);
+}
+
+/// Importing a file: its first rows under where each column goes while
+/// choosing the table, then the progress, then what went wrong, if anything.
+pub fn render_import(frame: &mut Frame, area: Rect, app: &model::App<ImportCsv>) {
+    let import = &app.mode;
+    let [header, body] = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(4), Constraint::Min(3)])
+        .areas(area);
+    let file = match &import.preview {
+        Ok(preview) => preview.path.display().to_string(),
+        Err(_) => "file".to_string(),
+    };
+    let target = match (&import.state, app.target_table()) {
+        (ImportState::Mapping, Some(_)) => Span::from("  existing table").green(),
+        (ImportState::Mapping, None) => Span::from("  new table").yellow(),
+        _ => Span::from(""),
+    };
+    let keys = match import.state {
+        ImportState::Mapping => {
+            "Enter import, Tab existing tables, Left/Right column, Up/Down where it goes, Esc back"
+        }
+        ImportState::Running { .. } => "Esc cancel and roll back",
+        ImportState::Finished(_) => "j/k scroll, Esc done",
+    };
+    let lines = vec![
+        Line::from(vec![
+            Span::from("Table: ").magenta(),
+            Span::from(import.table.clone()),
+            target,
+        ]),
+        Line::from(keys).dim(),
+    ];
+    let title = format!("Import {} into {}", file, import.connection);
+    frame.render_widget(Paragraph::new(lines).block(panel(title, true)), header);
+    if let ImportState::Mapping = import.state {
+        frame.set_cursor(
+            (header.x + 8 + import.table.chars().count() as u16).min(header.right() - 2),
+            header.y + 1,
+        );
+    }
+    match (&import.preview, &import.state) {
+        (Err(error), _) => frame.render_widget(
+            Paragraph::new(Line::from(format!("Could not read the file: {}", error)).red())
+                .block(panel("Preview", false)),
+            body,
+        ),
+        (Ok(preview), ImportState::Mapping) => render_import_preview(frame, body, app, preview),
+        (
+            Ok(preview),
+            ImportState::Running {
+                progress,
+                cancelled,
+            },
+        ) => {
+            let [gauge, _] = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([Constraint::Length(3), Constraint::Min(0)])
+                .areas(body);
+            let ratio = match preview.size {
+                0 => 0.0,
+                size => (progress.bytes as f64 / size as f64).min(1.0),
+            };
+            let label = match cancelled.load(std::sync::atomic::Ordering::Relaxed) {
+                true => "Cancelling...".to_string(),
+                false => format!(
+                    "{} rows inserted, {} failed",
+                    progress.inserted, progress.failed
+                ),
+            };
+            frame.render_widget(
+                Gauge::default()
+                    .block(panel("Importing", false))
+                    .gauge_style(Style::new().green())
+                    .ratio(ratio)
+                    .label(label),
+                gauge,
+            );
+        }
+        (Ok(_), ImportState::Finished(Err(error))) => frame.render_widget(
+            Paragraph::new(vec![
+                Line::from("Nothing was imported.").red(),
+                Line::from(error.as_str()),
+            ])
+            .wrap(Wrap { trim: false })
+            .block(panel("Import failed", false)),
+            body,
+        ),
+        (Ok(_), ImportState::Finished(Ok(report))) => {
+            let mut lines = vec![Line::from(format!(
+                "{} rows inserted, {} failed",
+                report.inserted, report.failed
+            ))];
+            if report.failed > report.errors.len() as u64 {
+                lines.push(
+                    Line::from(format!(
+                        "Showing the first {} failures",
+                        report.errors.len()
+                    ))
+                    .dim(),
+                );
+            }
+            for failure in &report.errors {
+                lines.push(Line::from(vec![
+                    Span::from(format!("line {}: ", failure.line)).yellow(),
+                    Span::from(failure.error.clone()),
+                ]));
+            }
+            frame.render_widget(
+                Paragraph::new(lines)
+                    .wrap(Wrap { trim: false })
+                    .scroll((import.scroll, 0))
+                    .block(panel("Import finished", false)),
+                body,
+            );
+        }
+    }
+}
+
+/// The first rows of the file with each column's name and, for a new table,
+/// the type it looks like or, for an existing one, the column it goes into.
+fn render_import_preview(
+    frame: &mut Frame,
+    area: Rect,
+    app: &model::App<ImportCsv>,
+    preview: &CsvPreview,
+) {
+    let import = &app.mode;
+    let table = app.target_table();
+    let destinations = preview
+        .kinds
+        .iter()
+        .enumerate()
+        .map(|(index, kind)| match table {
+            Some(table) => match import.mapping.get(index).copied().flatten() {
+                Some(column) => Span::from(format!("-> {}", table.columns[column].name)).green(),
+                None => Span::from("left out").dim(),
+            },
+            None => Span::from(kind.name()).dim(),
+        })
+        .collect::<Vec<_>>();
+    // Keep the selected column in view by starting a couple before it.
+    let first = import.column.saturating_sub(2);
+    let widths = (first..preview.headers.len())
+        .map(|index| {
+            let values = preview
+                .rows
                 .iter()
-                .map(|h| Cell::from(h.as_str()))
-                .collect::<Vec<_>>(),
-        ))
-        .block(Block::default().title("SQL Results").borders(Borders::ALL))
-        .highlight_symbol(">>");
+                .filter_map(|row| row.get(index)?.as_deref())
+                .map(|value| value.chars().count());
+            let width = values
+                .chain([
+                    preview.headers[index].chars().count(),
+                    destinations[index].width(),
+                ])
+                .max()
+                .unwrap_or(0);
+            Constraint::Length(width.clamp(4, 30) as u16)
+        })
+        .collect::<Vec<_>>();
+    let header = (first..preview.headers.len()).map(|index| {
+        let name = Line::from(preview.headers[index].as_str()).bold();
+        let name = match index == import.column && table.is_some() {
+            true => name.reversed(),
+            false => name,
+        };
+        Cell::from(Text::from(vec![
+            name,
+            Line::from(destinations[index].clone()),
+        ]))
+    });
+    let rows = preview.rows.iter().map(|row| {
+        TableRow::new((first..preview.headers.len()).map(|index| {
+            match row.get(index).cloned().flatten() {
+                Some(value) => Cell::from(value),
+                None => Cell::from("NULL").style(Style::new().dark_gray().italic()),
+            }
+        }))
+    });
+    let title = format!(
+        "Preview - first {} rows, {} columns",
+        preview.rows.len(),
+        preview.headers.len()
+    );
+    frame.render_widget(
+        Table::new(rows, widths)
+            .header(TableRow::new(header).height(2))
+            .block(panel(title, false)),
+        area,
+    );
+}
 
-    frame.render_stateful_widget(table, area, state);
+/// The statement being explained above its plan, drawn as a tree with full
+/// scans, index lookups and the planner's estimates picked out.
+pub fn render_plan(frame: &mut Frame, area: Rect, app: &model::App<ExplainQuery>) {
+    let statement_height = app.mode.statement.lines().count().min(6) as u16 + 2;
+    let [statement, plan] = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(statement_height), Constraint::Min(3)])
+        .areas(area);
+    frame.render_widget(
+        Paragraph::new(app.mode.statement.as_str()).block(panel("Statement", false)),
+        statement,
+    );
+    let mut lines = Vec::new();
+    match &app.mode.plan {
+        None => lines.push(Line::from("Working out the plan...").dim()),
+        Some(Err(error)) => lines.push(Line::from(error.clone()).red()),
+        Some(Ok(nodes)) => {
+            let (mut full_scans, mut index_scans) = (0, 0);
+            count_scans(nodes, &mut full_scans, &mut index_scans);
+            let mut summary = vec![
+                Span::raw(format!("Full scans: {}", full_scans)).yellow(),
+                Span::raw(", "),
+                Span::raw(format!("index lookups: {}", index_scans)).green(),
+            ];
+            if let Some(cost) = nodes.first().and_then(|node| node.cost) {
+                summary.push(Span::raw(format!(
+                    ", estimated cost: {:.2} for {:.0} rows",
+                    cost.total, cost.rows
+                )));
+            }
+            lines.push(Line::from(summary));
+            lines.push(Line::default());
+            plan_lines(nodes, "", &mut lines);
+        }
+    }
+    let title = format!("Plan on {} (j/k scroll, Esc back)", app.mode.connection);
+    frame.render_widget(
+        Paragraph::new(lines)
+            .scroll((app.mode.scroll, 0))
+            .block(panel(title, true)),
+        plan,
+    );
 }
 
-fn extract_row_data(app: &App) -> Result<(Vec<String>, Vec<Vec<String>>), Box<dyn Error>> {
-    match app.results {
-        None => return Err("No results to display".into()),
-        Some(ref rows) => {
-            let headers = rows
-                .first()
-                .map(|row| {
-                    row.columns()
-                        .iter()
-                        .map(|col| col.name().to_string())
-                        .collect::<Vec<_>>()
-                })
+fn count_scans(nodes: &[PlanNode], full_scans: &mut usize, index_scans: &mut usize) {
+    for node in nodes {
+        match node.kind {
+            PlanKind::FullScan => *full_scans += 1,
+            PlanKind::IndexScan => *index_scans += 1,
+            PlanKind::Other => {}
+        }
+        count_scans(&node.children, full_scans, index_scans);
+    }
+}
+
+/// Draw each step under the one it feeds, with tree lines down the left.
+fn plan_lines(nodes: &[PlanNode], prefix: &str, lines: &mut Vec<Line<'static>>) {
+    for (index, node) in nodes.iter().enumerate() {
+        let (branch, continuation) = match index + 1 == nodes.len() {
+            true => ("└─ ", "   "),
+            false => ("├─ ", "│  "),
+        };
+        let style = match node.kind {
+            PlanKind::FullScan => Style::new().yellow().bold(),
+            PlanKind::IndexScan => Style::new().green(),
+            PlanKind::Other => Style::new(),
+        };
+        let mut spans = vec![
+            Span::raw(format!("{}{}", prefix, branch)).dim(),
+            Span::styled(node.operation.clone(), style),
+        ];
+        if let Some(cost) = node.cost {
+            let startup = cost
+                .startup
+                .map(|startup| format!("{:.2}..", startup))
                 .unwrap_or_default();
-            let data = rows
+            spans.push(
+                Span::raw(format!(
+                    "  cost {}{:.2}, {:.0} rows",
+                    startup, cost.total, cost.rows
+                ))
+                .magenta(),
+            );
+        }
+        lines.push(Line::from(spans));
+        let prefix = format!("{}{}", prefix, continuation);
+        let guide = if node.children.is_empty() {
+            "  "
+        } else {
+            "│ "
+        };
+        for detail in &node.details {
+            lines.push(Line::from(format!("{}{}{}", prefix, guide, detail)).dim());
+        }
+        plan_lines(&node.children, &prefix, lines);
+    }
+}
+
+/// The names of the saved queries, on the home view.
+pub fn render_library_summary<Mode>(
+    frame: &mut Frame,
+    area: Rect,
+    app: &model::App<Mode>,
+    focused: bool,
+) {
+    let library = app.library();
+    let lines = match library.is_empty() {
+        true => vec![Line::from("No saved queries").dim()],
+        false => library
+            .iter()
+            .map(|query| Line::from(query.name.clone()))
+            .collect(),
+    };
+    let title = format!("Library ({})", library.len());
+    frame.render_widget(Paragraph::new(lines).block(panel(title, focused)), area);
+}
+
+/// The saved queries, with the description and SQL of the selected one.
+pub fn render_library(frame: &mut Frame, area: Rect, app: &mut model::App<BrowseLibrary>) {
+    let columns = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(35), Constraint::Percentage(65)])
+        .split(area);
+    let preview = match app.selected_query() {
+        Some(query) => {
+            let mut lines = vec![Line::from(query.path.display().to_string()).dim()];
+            if let Some(description) = &query.description {
+                lines.push(Line::from(description.clone()));
+            }
+            if let Some(profile) = &query.profile {
+                lines.push(Line::from(format!("Runs on {}", profile)).cyan());
+            }
+            if !query.parameters.is_empty() {
+                lines.push(
+                    Line::from(format!("Parameters: {}", query.parameters.join(", "))).magenta(),
+                );
+            }
+            lines.push(Line::default());
+            lines.extend(query.sql.lines().map(|line| Line::from(line.to_string())));
+            lines
+        }
+        None => vec![],
+    };
+    frame.render_widget(
+        Paragraph::new(preview)
+            .wrap(Wrap { trim: false })
+            .block(panel("Query", false)),
+        columns[1],
+    );
+    let items = app
+        .library()
+        .iter()
+        .map(|query| ListItem::new(query.name.clone()))
+        .collect::<Vec<_>>();
+    let title = match &app.config().library {
+        Some(dir) => format!(
+            "Library in {} (Enter open, r reload, Esc back)",
+            dir.display()
+        ),
+        None => "Library".to_string(),
+    };
+    let list = List::new(items)
+        .block(panel(title, true))
+        .highlight_symbol(">> ");
+    frame.render_stateful_widget(list, columns[0], &mut app.mode.list);
+}
+
+/// The statements run before, with the search above them and the whole of
+/// the selected one below.
+pub fn render_history(frame: &mut Frame, area: Rect, app: &mut model::App<BrowseHistory>) {
+    let [search, list, preview] = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(3),
+            Constraint::Min(5),
+            Constraint::Length(8),
+        ])
+        .areas(area);
+    let searching = app.mode.searching;
+    let search_text = match (searching, app.mode.search.as_str()) {
+        (false, "") => Line::from("Press / to search").dim(),
+        (true, text) => Line::from(format!("{}█", text)),
+        (false, text) => Line::from(text.to_string()),
+    };
+    frame.render_widget(
+        Paragraph::new(search_text).block(panel("Search", searching)),
+        search,
+    );
+
+    let preview_text = match app.selected_entry() {
+        Some(entry) => {
+            let mut text = vec![Line::from(format!(
+                "{} on {}",
+                entry
+                    .executed_at
+                    .with_timezone(&Local)
+                    .format("%Y-%m-%d %H:%M:%S"),
+                entry.connection
+            ))
+            .dim()];
+            if let Some(error) = &entry.error {
+                text.push(Line::from(error.clone()).red());
+            }
+            text.extend(
+                entry
+                    .statement
+                    .lines()
+                    .map(|line| Line::from(line.to_string())),
+            );
+            text
+        }
+        None => vec![],
+    };
+    frame.render_widget(
+        Paragraph::new(preview_text)
+            .wrap(Wrap { trim: false })
+            .block(panel("Statement", false)),
+        preview,
+    );
+
+    let rows = app.mode.entries.iter().map(|entry| {
+        let outcome = match (&entry.error, entry.rows) {
+            (Some(_), _) => "failed".to_string(),
+            (None, Some(rows)) => rows.to_string(),
+            (None, None) => String::new(),
+        };
+        let row = TableRow::new(vec![
+            Cell::from(
+                entry
+                    .executed_at
+                    .with_timezone(&Local)
+                    .format("%m-%d %H:%M")
+                    .to_string(),
+            ),
+            Cell::from(entry.connection.clone()),
+            Cell::from(format!("{:?}", entry.duration)),
+            Cell::from(outcome),
+            Cell::from(
+                entry
+                    .statement
+                    .lines()
+                    .next()
+                    .unwrap_or_default()
+                    .to_string(),
+            ),
+        ]);
+        match entry.error {
+            Some(_) => row.red(),
+            None => row,
+        }
+    });
+    let title = match app.mode.entries.len() {
+        0 => "History (empty)".to_string(),
+        count => format!(
+            "History - {} statements (Enter edit, r run again, / search, Esc back)",
+            count
+        ),
+    };
+    let table = Table::new(
+        rows,
+        [
+            Constraint::Length(11),
+            Constraint::Length(20),
+            Constraint::Length(10),
+            Constraint::Length(8),
+            Constraint::Min(20),
+        ],
+    )
+    .header(TableRow::new(vec!["When", "Connection", "Took", "Rows", "Statement"]).bold())
+    .block(panel(title, !searching))
+    .highlight_symbol(">>");
+    frame.render_stateful_widget(table, list, &mut app.mode.table);
+}
+
+pub fn render_connections(frame: &mut Frame, area: Rect, app: &mut model::App<ExploreConnection>) {
+    let active = app.connections().active_index();
+    let items = app
+        .entries()
+        .into_iter()
+        .map(|entry| match entry {
+            ConnectionEntry::Open(index, open) => {
+                let marker = if Some(index) == active { "* " } else { "+ " };
+                let style = connection_name_style(app, &open.name);
+                ListItem::new(Line::from(vec![
+                    Span::raw(marker),
+                    Span::styled(open.name.clone(), style).bold(),
+                ]))
+            }
+            ConnectionEntry::Profile(name, profile) => {
+                let mut spans = vec![
+                    Span::raw("  "),
+                    Span::styled(name.to_string(), connection_name_style(app, name)),
+                    Span::raw(format!(
+                        "  {}",
+                        redact_url(&profile.url().unwrap_or_default())
+                    ))
+                    .dim(),
+                ];
+                if profile.read_only {
+                    spans.push(Span::raw("  [read only]"));
+                }
+                ListItem::new(Line::from(spans))
+            }
+        })
+        .collect::<Vec<_>>();
+    let title = match items.is_empty() {
+        true => "Connections (no profiles found in config.toml)",
+        false => "Connections (* active, + open)",
+    };
+    let list = List::new(items)
+        .block(panel(title, !app.mode.schema_focused))
+        .highlight_symbol(">> ");
+    frame.render_stateful_widget(list, area, &mut app.mode.entries);
+}
+
+/// The tree of the active connection's tables, views, indexes and triggers.
+pub fn render_schema(frame: &mut Frame, area: Rect, app: &mut model::App<ExploreConnection>) {
+    let focused = app.mode.schema_focused;
+    let title = match app.connections().active() {
+        Some(open) => format!("Schema of {} (Tab focus, i inspect, r refresh)", open.name),
+        None => "Schema".to_string(),
+    };
+    let block = panel(title, focused);
+    let nodes = app.schema_nodes();
+    if nodes.is_empty() {
+        let message = match (app.connections().active(), app.schema()) {
+            (None, _) => "Not connected",
+            (Some(_), None) => "Reading schema...",
+            (Some(_), Some(_)) => "No tables",
+        };
+        frame.render_widget(Paragraph::new(message).block(block), area);
+        return;
+    }
+    let items = nodes
+        .iter()
+        .map(|node| {
+            let marker = match node.expanded {
+                Some(true) => "▾ ",
+                Some(false) => "▸ ",
+                None => "  ",
+            };
+            let mut spans = vec![Span::raw("  ".repeat(node.depth)), Span::raw(marker)];
+            match &node.item {
+                SchemaItem::Group(group, count) => {
+                    spans.push(Span::raw(format!("{} ({})", group, count)).bold())
+                }
+                SchemaItem::Relation(relation) => spans.push(Span::raw(relation.qualified_name())),
+                SchemaItem::Dependent(dependent) => spans.push(Span::raw(dependent.name.clone())),
+                SchemaItem::Column(column) => {
+                    spans.push(Span::raw(column.name.clone()));
+                    spans.push(Span::raw(format!(" {}", column.data_type)).dim());
+                    if column.primary_key {
+                        spans.push(Span::raw(" PK").yellow());
+                    }
+                    if let Some(references) = &column.references {
+                        spans.push(Span::raw(format!(" FK → {}", references)).cyan());
+                    }
+                    if column.not_null && !column.primary_key {
+                        spans.push(Span::raw(" NOT NULL").magenta());
+                    }
+                }
+                SchemaItem::Detail(detail) => spans.push(Span::raw(detail.clone()).dim()),
+            }
+            ListItem::new(Line::from(spans))
+        })
+        .collect::<Vec<_>>();
+    let list = List::new(items).block(block).highlight_symbol(">> ");
+    frame.render_stateful_widget(list, area, &mut app.mode.tree);
+}
+
+/// The definition of one table with its columns, keys, indexes and triggers.
+pub fn render_table_inspector(frame: &mut Frame, area: Rect, app: &model::App<InspectTable>) {
+    let relation = &app.mode.relation;
+    let name = relation.qualified_name();
+    let kind = if app.mode.is_view { "View" } else { "Table" };
+    let mut title = format!("{} {}", kind, name);
+    if let Some(count) = app
+        .mode
+        .details
+        .as_ref()
+        .and_then(|details| details.row_count)
+    {
+        title.push_str(&format!(" (~{} rows)", count));
+    }
+    let heading = |text: &str| Line::from(Span::raw(text.to_string()).bold());
+    let mut lines = Vec::new();
+
+    match &app.mode.details {
+        None => lines.push(Line::from("Reading definition...").dim()),
+        Some(details) => {
+            if details.reconstructed {
+                lines.push(Line::from("Reconstructed from the catalog").dim());
+            }
+            lines.extend(
+                details
+                    .definition
+                    .lines()
+                    .map(|line| Line::from(line.to_string())),
+            );
+        }
+    }
+
+    lines.push(Line::default());
+    lines.push(heading("Columns"));
+    for column in &relation.columns {
+        let mut spans = vec![
+            Span::raw(format!("  {}", column.name)),
+            Span::raw(format!(" {}", column.data_type)).dim(),
+        ];
+        if column.primary_key {
+            spans.push(Span::raw(" PK").yellow());
+        }
+        if column.not_null && !column.primary_key {
+            spans.push(Span::raw(" NOT NULL").magenta());
+        }
+        if let Some(default) = &column.default {
+            spans.push(Span::raw(format!(" DEFAULT {}", default)).dim());
+        }
+        lines.push(Line::from(spans));
+    }
+
+    let schema = app.schema();
+    let indexes = schema
+        .map(|schema| schema.indexes.iter().filter(|index| index.table == name))
+        .into_iter()
+        .flatten()
+        .map(|index| Line::from(format!("  {} {}", index.name, index.detail)))
+        .collect::<Vec<_>>();
+    let outgoing = relation.columns.iter().filter_map(|column| {
+        let references = column.references.as_ref()?;
+        Some(Line::from(format!("  {} → {}", column.name, references)).cyan())
+    });
+    let incoming = schema
+        .map(|schema| schema.references_to(&name))
+        .unwrap_or_default()
+        .into_iter()
+        .map(|(from, column)| Line::from(format!("  {} ← {}", column, from)).cyan());
+    let foreign_keys = outgoing.chain(incoming).collect::<Vec<_>>();
+    let triggers = schema
+        .map(|schema| {
+            schema
+                .triggers
                 .iter()
-                .map(|row| {
-                    (0..row.len())
-                        .map(|i| row.try_get::<&str, _>(i).unwrap_or_default().to_string())
-                        .collect::<Vec<_>>()
-                })
-                .collect::<Vec<_>>();
-            Ok((headers, data))
+                .filter(|trigger| trigger.table == name)
+        })
+        .into_iter()
+        .flatten()
+        .map(|trigger| Line::from(format!("  {} {}", trigger.name, trigger.detail)))
+        .collect::<Vec<_>>();
+
+    for (name, section) in [
+        ("Indexes", indexes),
+        ("Foreign keys", foreign_keys),
+        ("Triggers", triggers),
+    ] {
+        lines.push(Line::default());
+        lines.push(heading(name));
+        if section.is_empty() {
+            lines.push(Line::from("  none").dim());
         }
+        lines.extend(section);
+    }
+
+    let paragraph = Paragraph::new(lines)
+        .block(panel(format!("{} (j/k scroll, Esc back)", title), true))
+        .scroll((app.mode.scroll, 0));
+    frame.render_widget(paragraph, area);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn picking(column: usize, frozen: usize, offset: usize) -> Grid {
+        Grid {
+            column,
+            frozen,
+            offset,
+            ..Grid::default()
+        }
+    }
+
+    #[test]
+    fn keeps_the_picked_column_in_view() {
+        let mut grid = picking(5, 0, 0);
+        let columns = visible_columns(&mut grid, &[10; 6], 35);
+        assert_eq!(columns, [(3, 10), (4, 10), (5, 10)]);
+        assert_eq!(grid.offset, 3);
+    }
+
+    #[test]
+    fn frozen_columns_wider_than_the_table_are_cut_short() {
+        let mut grid = picking(2, 2, 0);
+        let columns = visible_columns(&mut grid, &[30, 30, 10], 20);
+        assert_eq!(columns, [(0, 20)]);
+        let columns = visible_columns(&mut grid, &[10, 30, 10], 20);
+        assert_eq!(columns, [(0, 10), (1, 9)]);
+    }
+
+    #[test]
+    fn an_offset_past_the_last_column_brings_columns_back() {
+        let mut grid = picking(1, 0, 10);
+        assert_eq!(
+            visible_columns(&mut grid, &[5, 5, 5], 40),
+            [(0, 5), (1, 5), (2, 5)]
+        );
+        assert_eq!(grid.offset, 0);
+        let mut grid = picking(7, 1, 10);
+        assert_eq!(
+            visible_columns(&mut grid, &[5, 20, 20], 30),
+            [(0, 5), (2, 20)]
+        );
+    }
+
+    #[test]
+    fn no_columns_fit_in_no_width() {
+        for space in [0, 1] {
+            assert!(visible_columns(&mut picking(0, 1, 0), &[5, 5], space).is_empty());
+        }
+        assert!(visible_columns(&mut picking(0, 0, 0), &[], 40).is_empty());
+    }
+
+    #[test]
+    fn fit_cuts_text_down_to_its_width() {
+        assert_eq!(fit("short".to_string(), 5), "short");
+        assert_eq!(fit("longer".to_string(), 5), "long…");
+        assert_eq!(fit("longer".to_string(), 1), "…");
+        assert_eq!(fit("longer".to_string(), 0), "");
+    }
+
+    #[test]
+    fn fit_does_not_split_double_width_characters() {
+        assert_eq!(fit("日本語".to_string(), 6), "日本語");
+        assert_eq!(fit("日本語".to_string(), 5), "日本…");
+        assert_eq!(fit("日本語".to_string(), 4), "日…");
+        assert_eq!(fit("日本語".to_string(), 2), "…");
+        assert_eq!(fit("a日本".to_string(), 4), "a日…");
     }
 }
@@ -1,81 +1,68 @@
+use crate::database::TransactionEnd;
 use crate::message::Message;
+use crate::model::{
+    self, BindParameters, BrowseHistory, BrowseLibrary, BrowseSqliteDBFiles, ConfirmQuit,
+    DeleteRows, EditCell, EditQuery, ExplainQuery, ExploreConnection, ExploreResults, Home,
+    ImportCsv, ImportState, InsertRow, InspectTable, Quit, RowAction, SaveResults,
+};
+use crate::ui;
 use crate::MainEvent;
-use crossterm::event::{Event, KeyCode};
+use crossterm::event::{Event, KeyCode, KeyModifiers};
+use ratatui::layout::{Constraint, Direction, Layout};
+use ratatui::text::Line;
+use ratatui::widgets::TableState;
 use ratatui::Frame;
 
+#[derive(Default, Clone, Copy, PartialEq)]
 enum Component {
+    #[default]
     None,
     Connections,
     Results,
     Queries,
+    Library,
 }
 
-impl Default for Component {
-    fn default() -> Self {
-        Component::None
+impl Component {
+    fn next(self) -> Self {
+        match self {
+            Component::None | Component::Results => Component::Connections,
+            Component::Connections => Component::Queries,
+            Component::Queries => Component::Library,
+            Component::Library => Component::Results,
+        }
     }
-}
-
-// Modes
-
-#[derive(Default)]
-pub struct Home;
-
-#[derive(Default)]
-pub struct EditQuery;
-
-#[derive(Default)]
-pub struct BrowseSqliteDBFiles;
-
-#[derive(Default)]
-pub struct ExploreResults;
 
-#[derive(Default)]
-pub struct SaveResults;
-
-#[derive(Default)]
-pub struct ExploreConnection;
-
-#[derive(Default)]
-pub struct ConfigEditor;
-
-#[derive(Default)]
-pub struct Quit;
+    fn previous(self) -> Self {
+        match self {
+            Component::None | Component::Queries => Component::Connections,
+            Component::Connections => Component::Results,
+            Component::Results => Component::Library,
+            Component::Library => Component::Queries,
+        }
+    }
+}
 
 pub struct ViewState<Mode> {
-    mode: Mode,
+    app: model::App<Mode>,
     selected: Component,
 }
 
 pub type ViewStateBox = Box<dyn ViewStateTrait>;
 
-impl<Mode: Default> Default for ViewState<Mode> {
-    fn default() -> Self {
-        Self {
-            mode: Mode::default(),
-            selected: Component::default(),
-        }
-    }
-}
-
 struct ViewStateBuilder<Mode> {
-    mode: Option<Mode>,
+    app: model::App<Mode>,
     selected: Option<Component>,
 }
 
-impl<Mode: Default> ViewStateBuilder<Mode> {
-    fn default() -> Self {
+impl<Mode> ViewStateBuilder<Mode> {
+    fn new(app: model::App<Mode>) -> Self {
         Self {
-            mode: None,
+            app,
             selected: None,
         }
     }
 
-    fn mode(mut self, mode: Mode) -> Self {
-        self.mode = Some(mode);
-        self
-    }
-
     fn selected(mut self, selected: Component) -> Self {
         self.selected = Some(selected);
         self
@@ -83,15 +70,47 @@ impl<Mode: Default> ViewStateBuilder<Mode> {
 
     fn build(self) -> ViewState<Mode> {
         ViewState {
-            mode: self.mode.unwrap_or_default(),
+            app: self.app,
             selected: self.selected.unwrap_or_default(),
         }
     }
 }
 
 impl ViewState<Home> {
-    pub fn new() -> Self {
-        Self::default()
+    pub fn new(app: model::App<Home>) -> Self {
+        ViewStateBuilder::new(app).build()
+    }
+}
+
+impl<Mode> ViewState<Mode>
+where
+    ViewState<Mode>: ViewStateTrait + 'static,
+{
+    /// Handle messages that update the model the same way whichever view is
+    /// active.
+    fn update_app(self, message: Message) -> (ViewStateBox, Option<Message>) {
+        let ViewState { app, selected } = self;
+        let app = match message {
+            Message::Connected(profile, result) => app.connected(profile, result),
+            Message::QueryOutput(connection, result) => app.query_output(connection, result),
+            Message::Cancel => app.cancel_query(),
+            Message::BeginTransaction => app.begin_transaction(),
+            Message::EndTransaction(end) => app.end_transaction(end),
+            Message::TransactionBegun(connection, result) => {
+                app.transaction_begun(connection, result)
+            }
+            Message::TransactionEnded(connection, result) => {
+                app.transaction_ended(connection, result)
+            }
+            Message::SchemaLoaded(connection, result) => app.schema_loaded(connection, result),
+            Message::Exported(path, result) => app.exported(path, result),
+            Message::RowsChanged(changed, result) => app.rows_changed(changed, result),
+            _ => app,
+        };
+        (
+            Box::new(ViewStateBuilder::new(app).selected(selected).build()),
+            None,
+        )
     }
 }
 
@@ -100,6 +119,26 @@ pub trait ViewStateTrait {
         match event {
             MainEvent::Input(event) => self.handle_input(event),
             MainEvent::Tick => Message::NoOp,
+            MainEvent::Connected(profile, result) => Message::Connected(profile, result),
+            MainEvent::QueryOutput(connection, result) => Message::QueryOutput(connection, result),
+            MainEvent::TransactionBegun(connection, result) => {
+                Message::TransactionBegun(connection, result)
+            }
+            MainEvent::TransactionEnded(connection, result) => {
+                Message::TransactionEnded(connection, result)
+            }
+            MainEvent::SchemaLoaded(connection, result) => {
+                Message::SchemaLoaded(connection, result)
+            }
+            MainEvent::TableDetails(connection, table, result) => {
+                Message::TableDetails(connection, table, result)
+            }
+            MainEvent::HistoryLoaded(search, result) => Message::HistoryLoaded(search, result),
+            MainEvent::Plan(connection, result) => Message::Plan(connection, result),
+            MainEvent::Exported(path, result) => Message::Exported(path, result),
+            MainEvent::ImportProgress(progress) => Message::ImportProgress(progress),
+            MainEvent::Imported(result) => Message::Imported(result),
+            MainEvent::RowsChanged(changed, result) => Message::RowsChanged(changed, result),
         }
     }
     fn handle_input(&self, event: Event) -> Message;
@@ -107,48 +146,126 @@ pub trait ViewStateTrait {
     fn should_quit(&self) -> bool {
         false
     }
-    fn render(&self, frame: &mut Frame);
+    fn render(&mut self, frame: &mut Frame);
 }
 
 impl ViewStateTrait for ViewState<Home> {
     fn handle_input(&self, event: Event) -> Message {
         match event {
+            Event::Key(key) if key.modifiers.contains(KeyModifiers::CONTROL) => match key.code {
+                KeyCode::Char('c') => Message::Cancel,
+                KeyCode::Char('t') => Message::BeginTransaction,
+                KeyCode::Char('s') => Message::EndTransaction(TransactionEnd::Commit),
+                KeyCode::Char('z') => Message::EndTransaction(TransactionEnd::Rollback),
+                _ => Message::NoOp,
+            },
             Event::Key(key) => match key.code {
                 KeyCode::Char('q') => Message::Quit,
+                KeyCode::Char('h') => Message::History,
+                KeyCode::Char('o') => Message::Files,
                 KeyCode::Enter => Message::SelectMode,
+                KeyCode::Tab | KeyCode::Down | KeyCode::Char('j') => Message::Next,
+                KeyCode::BackTab | KeyCode::Up | KeyCode::Char('k') => Message::Previous,
                 _ => Message::NoOp,
             },
             _ => Message::NoOp,
         }
     }
 
-    fn update(self: Box<Self>, message: Message) -> (ViewStateBox, Option<Message>) {
+    fn update(mut self: Box<Self>, message: Message) -> (ViewStateBox, Option<Message>) {
         match message {
+            Message::Quit if self.app.transaction().is_some() => (
+                Box::new(ViewStateBuilder::new(self.app.confirm_quit()).build()),
+                None,
+            ),
             Message::Quit => (
-                Box::new(ViewStateBuilder::default().mode(Quit).build()),
+                Box::new(ViewStateBuilder::new(self.app.quit()).build()),
                 None,
             ),
             Message::SelectMode => match self.selected {
                 Component::None => (self, None),
                 Component::Connections => (
-                    Box::new(ViewStateBuilder::default().mode(ExploreConnection).build()),
+                    Box::new(ViewStateBuilder::new(self.app.explore_connection()).build()),
                     None,
                 ),
                 Component::Results => (
-                    Box::new(ViewStateBuilder::default().mode(ExploreResults).build()),
+                    Box::new(ViewStateBuilder::new(self.app.explore_results()).build()),
                     None,
                 ),
                 Component::Queries => (
-                    Box::new(ViewStateBuilder::default().mode(EditQuery).build()),
+                    Box::new(ViewStateBuilder::new(self.app.edit_query()).build()),
+                    None,
+                ),
+                Component::Library => (
+                    Box::new(ViewStateBuilder::new(self.app.browse_library()).build()),
                     None,
                 ),
             },
+            Message::History => (
+                Box::new(ViewStateBuilder::new(self.app.browse_history()).build()),
+                None,
+            ),
+            Message::Files => (
+                Box::new(ViewStateBuilder::new(self.app.open_sqlite_db()).build()),
+                None,
+            ),
+            Message::Next => {
+                self.selected = self.selected.next();
+                (self, None)
+            }
+            Message::Previous => {
+                self.selected = self.selected.previous();
+                (self, None)
+            }
             Message::NoOp | Message::Escape => (self, None),
+            message => self.update_app(message),
         }
     }
 
-    fn render(&self, frame: &mut Frame) {
-        // render home view
+    fn render(&mut self, frame: &mut Frame) {
+        let (main, status) = ui::split_status_line(frame.size());
+        let rows = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(3),
+                Constraint::Length(8),
+                Constraint::Min(5),
+            ])
+            .split(main);
+
+        ui::render_connection_summary(
+            frame,
+            rows[0],
+            &self.app,
+            self.selected == Component::Connections,
+        );
+        let queries = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(60), Constraint::Percentage(40)])
+            .split(rows[1]);
+        ui::render_query_summary(
+            frame,
+            queries[0],
+            self.app.current_query(),
+            self.selected == Component::Queries,
+        );
+        ui::render_library_summary(
+            frame,
+            queries[1],
+            &self.app,
+            self.selected == Component::Library,
+        );
+        let results_title = ui::results_title(&self.app);
+        ui::render_results(
+            frame,
+            rows[2],
+            self.app.statement_results(),
+            self.app.selected_result(),
+            ui::panel(results_title.as_str(), self.selected == Component::Results),
+            &mut TableState::default(),
+            None,
+        );
+        ui::render_status(frame, status, &self.app);
     }
 }
 
@@ -165,16 +282,65 @@ impl ViewStateTrait for ViewState<Quit> {
         true
     }
 
-    fn render(&self, frame: &mut Frame) {
+    fn render(&mut self, _frame: &mut Frame) {
         // render quit view
     }
 }
 
+impl ViewStateTrait for ViewState<ConfirmQuit> {
+    fn handle_input(&self, event: Event) -> Message {
+        match event {
+            Event::Key(key) => match key.code {
+                KeyCode::Char('y') => Message::Quit,
+                KeyCode::Char('n') | KeyCode::Esc => Message::Escape,
+                _ => Message::NoOp,
+            },
+            _ => Message::NoOp,
+        }
+    }
+
+    fn update(self: Box<Self>, message: Message) -> (ViewStateBox, Option<Message>) {
+        match message {
+            Message::Quit => (
+                Box::new(ViewStateBuilder::new(self.app.rollback_and_quit()).build()),
+                None,
+            ),
+            Message::Escape => (
+                Box::new(ViewStateBuilder::new(self.app.cancel()).build()),
+                None,
+            ),
+            Message::NoOp => (self, None),
+            message => self.update_app(message),
+        }
+    }
+
+    fn render(&mut self, frame: &mut Frame) {
+        let (main, status) = ui::split_status_line(frame.size());
+        ui::render_confirm_quit(frame, main, &self.app);
+        ui::render_status(frame, status, &self.app);
+    }
+}
+
 impl ViewStateTrait for ViewState<ExploreConnection> {
     fn handle_input(&self, event: Event) -> Message {
         match event {
             Event::Key(key) => match key.code {
                 KeyCode::Char('q') | KeyCode::Esc => Message::Escape,
+                KeyCode::Tab | KeyCode::BackTab => Message::SwitchFocus,
+                KeyCode::Char('r') => Message::Refresh,
+                KeyCode::Down | KeyCode::Char('j') => Message::Next,
+                KeyCode::Up | KeyCode::Char('k') => Message::Previous,
+                KeyCode::Enter | KeyCode::Right | KeyCode::Char('l')
+                    if self.app.mode.schema_focused =>
+                {
+                    Message::Expand
+                }
+                KeyCode::Left | KeyCode::Char('h') if self.app.mode.schema_focused => {
+                    Message::Collapse
+                }
+                KeyCode::Char('i') if self.app.mode.schema_focused => Message::Inspect,
+                KeyCode::Enter => Message::SelectMode,
+                KeyCode::Char('d') => Message::Disconnect,
                 _ => Message::NoOp,
             },
             _ => Message::NoOp,
@@ -184,27 +350,470 @@ impl ViewStateTrait for ViewState<ExploreConnection> {
     fn update(self: Box<Self>, message: Message) -> (ViewStateBox, Option<Message>) {
         match message {
             Message::Quit => (
-                Box::new(ViewStateBuilder::default().mode(Quit).build()),
+                Box::new(ViewStateBuilder::new(self.app.quit()).build()),
+                None,
+            ),
+            Message::SelectMode => (
+                Box::new(ViewStateBuilder::new(self.app.connect_to_selected()).build()),
+                None,
+            ),
+            Message::Disconnect => (
+                Box::new(ViewStateBuilder::new(self.app.disconnect_selected()).build()),
+                None,
+            ),
+            Message::Next if self.app.mode.schema_focused => (
+                Box::new(ViewStateBuilder::new(self.app.select_next_node()).build()),
+                None,
+            ),
+            Message::Previous if self.app.mode.schema_focused => (
+                Box::new(ViewStateBuilder::new(self.app.select_previous_node()).build()),
+                None,
+            ),
+            Message::Next => (
+                Box::new(ViewStateBuilder::new(self.app.select_next_entry()).build()),
+                None,
+            ),
+            Message::Previous => (
+                Box::new(ViewStateBuilder::new(self.app.select_previous_entry()).build()),
+                None,
+            ),
+            Message::Expand => (
+                Box::new(ViewStateBuilder::new(self.app.expand_node()).build()),
+                None,
+            ),
+            Message::Collapse => (
+                Box::new(ViewStateBuilder::new(self.app.collapse_node()).build()),
+                None,
+            ),
+            Message::SwitchFocus => (
+                Box::new(ViewStateBuilder::new(self.app.toggle_schema_focus()).build()),
+                None,
+            ),
+            Message::Refresh => (
+                Box::new(ViewStateBuilder::new(self.app.refresh_schema()).build()),
+                None,
+            ),
+            Message::Inspect if self.app.can_inspect() => (
+                Box::new(ViewStateBuilder::new(self.app.inspect_selected()).build()),
+                None,
+            ),
+            Message::NoOp => (self, None),
+            Message::Escape => (
+                Box::new(
+                    ViewStateBuilder::new(self.app.cancel())
+                        .selected(Component::Connections)
+                        .build(),
+                ),
+                None,
+            ),
+            message => self.update_app(message),
+        }
+    }
+
+    fn render(&mut self, frame: &mut Frame) {
+        let (main, status) = ui::split_status_line(frame.size());
+        let columns = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(40), Constraint::Percentage(60)])
+            .split(main);
+        ui::render_connections(frame, columns[0], &mut self.app);
+        ui::render_schema(frame, columns[1], &mut self.app);
+        ui::render_status(frame, status, &self.app);
+    }
+}
+
+impl ViewStateTrait for ViewState<InspectTable> {
+    fn handle_input(&self, event: Event) -> Message {
+        match event {
+            Event::Key(key) => match key.code {
+                KeyCode::Char('q') | KeyCode::Esc => Message::Escape,
+                KeyCode::Down | KeyCode::Char('j') => Message::Next,
+                KeyCode::Up | KeyCode::Char('k') => Message::Previous,
+                KeyCode::PageDown => Message::PageDown,
+                KeyCode::PageUp => Message::PageUp,
+                KeyCode::Char('g') | KeyCode::Home => Message::First,
+                _ => Message::NoOp,
+            },
+            _ => Message::NoOp,
+        }
+    }
+
+    fn update(self: Box<Self>, message: Message) -> (ViewStateBox, Option<Message>) {
+        let scroll = |app: model::App<InspectTable>, lines: i16| -> (ViewStateBox, _) {
+            (
+                Box::new(ViewStateBuilder::new(app.scroll_details(lines)).build()),
+                None,
+            )
+        };
+        match message {
+            Message::Next => scroll(self.app, 1),
+            Message::Previous => scroll(self.app, -1),
+            Message::PageDown => scroll(self.app, RESULTS_PAGE as i16),
+            Message::PageUp => scroll(self.app, -RESULTS_PAGE as i16),
+            Message::First => scroll(self.app, i16::MIN),
+            Message::TableDetails(connection, table, result) => (
+                Box::new(
+                    ViewStateBuilder::new(self.app.table_details(connection, table, result))
+                        .build(),
+                ),
+                None,
+            ),
+            Message::NoOp => (self, None),
+            Message::Escape => (
+                Box::new(ViewStateBuilder::new(self.app.finish_inspecting()).build()),
+                None,
+            ),
+            message => self.update_app(message),
+        }
+    }
+
+    fn render(&mut self, frame: &mut Frame) {
+        let (main, status) = ui::split_status_line(frame.size());
+        ui::render_table_inspector(frame, main, &self.app);
+        ui::render_status(frame, status, &self.app);
+    }
+}
+
+impl ViewStateTrait for ViewState<ExplainQuery> {
+    fn handle_input(&self, event: Event) -> Message {
+        match event {
+            Event::Key(key) => match key.code {
+                KeyCode::Char('q') | KeyCode::Esc => Message::Escape,
+                KeyCode::Down | KeyCode::Char('j') => Message::Next,
+                KeyCode::Up | KeyCode::Char('k') => Message::Previous,
+                KeyCode::PageDown => Message::PageDown,
+                KeyCode::PageUp => Message::PageUp,
+                KeyCode::Char('g') | KeyCode::Home => Message::First,
+                _ => Message::NoOp,
+            },
+            _ => Message::NoOp,
+        }
+    }
+
+    fn update(self: Box<Self>, message: Message) -> (ViewStateBox, Option<Message>) {
+        let scroll = |app: model::App<ExplainQuery>, lines: i16| -> (ViewStateBox, _) {
+            (
+                Box::new(ViewStateBuilder::new(app.scroll_plan(lines)).build()),
+                None,
+            )
+        };
+        match message {
+            Message::Next => scroll(self.app, 1),
+            Message::Previous => scroll(self.app, -1),
+            Message::PageDown => scroll(self.app, RESULTS_PAGE as i16),
+            Message::PageUp => scroll(self.app, -RESULTS_PAGE as i16),
+            Message::First => scroll(self.app, i16::MIN),
+            Message::Plan(connection, result) => (
+                Box::new(ViewStateBuilder::new(self.app.plan_loaded(connection, result)).build()),
+                None,
+            ),
+            Message::NoOp => (self, None),
+            Message::Escape => (
+                Box::new(ViewStateBuilder::new(self.app.finish_explaining()).build()),
+                None,
+            ),
+            message => self.update_app(message),
+        }
+    }
+
+    fn render(&mut self, frame: &mut Frame) {
+        let (main, status) = ui::split_status_line(frame.size());
+        ui::render_plan(frame, main, &self.app);
+        ui::render_status(frame, status, &self.app);
+    }
+}
+
+impl ViewStateTrait for ViewState<BrowseSqliteDBFiles> {
+    fn handle_input(&self, event: Event) -> Message {
+        match event {
+            Event::Key(key) => match key.code {
+                KeyCode::Char('q') | KeyCode::Esc => Message::Escape,
+                KeyCode::Char('i') => Message::Import,
+                KeyCode::Enter if self.app.selected_csv() => Message::Import,
+                KeyCode::Enter if !self.app.selected_directory() => Message::SelectMode,
+                _ => Message::Input(key),
+            },
+            _ => Message::NoOp,
+        }
+    }
+
+    fn update(self: Box<Self>, message: Message) -> (ViewStateBox, Option<Message>) {
+        match message {
+            Message::Input(key) => (
+                Box::new(ViewStateBuilder::new(self.app.browse_files(key)).build()),
+                None,
+            ),
+            Message::SelectMode => (
+                Box::new(ViewStateBuilder::new(self.app.open_sqlite_db()).build()),
+                None,
+            ),
+            Message::Import if self.app.can_import() => (
+                Box::new(ViewStateBuilder::new(self.app.import_csv()).build()),
+                None,
+            ),
+            Message::Import => (
+                Box::new(ViewStateBuilder::new(self.app.cannot_import()).build()),
+                None,
+            ),
+            Message::Escape => (
+                Box::new(ViewStateBuilder::new(self.app.cancel()).build()),
+                None,
+            ),
+            Message::NoOp => (self, None),
+            message => self.update_app(message),
+        }
+    }
+
+    fn render(&mut self, frame: &mut Frame) {
+        let (main, status) = ui::split_status_line(frame.size());
+        ui::render_files(frame, main, &self.app);
+        ui::render_status(frame, status, &self.app);
+    }
+}
+
+impl ViewStateTrait for ViewState<ImportCsv> {
+    fn handle_input(&self, event: Event) -> Message {
+        let Event::Key(key) = event else {
+            return Message::NoOp;
+        };
+        match &self.app.mode.state {
+            ImportState::Mapping => match key.code {
+                KeyCode::Esc => Message::Escape,
+                KeyCode::Enter => Message::Import,
+                KeyCode::Tab => Message::NextTab,
+                KeyCode::BackTab => Message::PreviousTab,
+                KeyCode::Right => Message::Expand,
+                KeyCode::Left => Message::Collapse,
+                KeyCode::Down => Message::Next,
+                KeyCode::Up => Message::Previous,
+                _ => Message::Input(key),
+            },
+            ImportState::Running { .. } => match key.code {
+                KeyCode::Esc => Message::Cancel,
+                KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    Message::Cancel
+                }
+                _ => Message::NoOp,
+            },
+            ImportState::Finished(_) => match key.code {
+                KeyCode::Char('q') | KeyCode::Esc | KeyCode::Enter => Message::Escape,
+                KeyCode::Down | KeyCode::Char('j') => Message::Next,
+                KeyCode::Up | KeyCode::Char('k') => Message::Previous,
+                KeyCode::PageDown => Message::PageDown,
+                KeyCode::PageUp => Message::PageUp,
+                _ => Message::NoOp,
+            },
+        }
+    }
+
+    fn update(self: Box<Self>, message: Message) -> (ViewStateBox, Option<Message>) {
+        let mapping = matches!(self.app.mode.state, ImportState::Mapping);
+        let app = match message {
+            Message::Escape if mapping => {
+                return (
+                    Box::new(ViewStateBuilder::new(self.app.back_to_files()).build()),
+                    None,
+                );
+            }
+            Message::Escape => {
+                return (
+                    Box::new(ViewStateBuilder::new(self.app.cancel()).build()),
+                    None,
+                );
+            }
+            Message::Input(key) if mapping => self.app.table_input(key),
+            Message::NextTab if mapping => self.app.switch_table(true),
+            Message::PreviousTab if mapping => self.app.switch_table(false),
+            Message::Expand if mapping => self.app.select_column(true),
+            Message::Collapse if mapping => self.app.select_column(false),
+            Message::Next if mapping => self.app.switch_mapping(true),
+            Message::Previous if mapping => self.app.switch_mapping(false),
+            Message::Import if mapping => self.app.import(),
+            Message::Cancel => self.app.cancel_import(),
+            Message::ImportProgress(progress) => self.app.import_progress(progress),
+            Message::Imported(result) => self.app.imported(result),
+            Message::Next => self.app.scroll_report(1),
+            Message::Previous => self.app.scroll_report(-1),
+            Message::PageDown => self.app.scroll_report(RESULTS_PAGE as i16),
+            Message::PageUp => self.app.scroll_report(-RESULTS_PAGE as i16),
+            Message::NoOp => return (self, None),
+            message => return self.update_app(message),
+        };
+        (Box::new(ViewStateBuilder::new(app).build()), None)
+    }
+
+    fn render(&mut self, frame: &mut Frame) {
+        let (main, status) = ui::split_status_line(frame.size());
+        ui::render_import(frame, main, &self.app);
+        ui::render_status(frame, status, &self.app);
+    }
+}
+
+impl ViewStateTrait for ViewState<BrowseLibrary> {
+    fn handle_input(&self, event: Event) -> Message {
+        match event {
+            Event::Key(key) => match key.code {
+                KeyCode::Char('q') | KeyCode::Esc => Message::Escape,
+                KeyCode::Char('r') => Message::Refresh,
+                KeyCode::Enter => Message::SelectMode,
+                KeyCode::Down | KeyCode::Char('j') => Message::Next,
+                KeyCode::Up | KeyCode::Char('k') => Message::Previous,
+                _ => Message::NoOp,
+            },
+            _ => Message::NoOp,
+        }
+    }
+
+    fn update(self: Box<Self>, message: Message) -> (ViewStateBox, Option<Message>) {
+        match message {
+            Message::Next => (
+                Box::new(ViewStateBuilder::new(self.app.select_query(true)).build()),
+                None,
+            ),
+            Message::Previous => (
+                Box::new(ViewStateBuilder::new(self.app.select_query(false)).build()),
+                None,
+            ),
+            Message::Refresh => (
+                Box::new(ViewStateBuilder::new(self.app.reload_library()).build()),
+                None,
+            ),
+            Message::SelectMode if self.app.selected_query().is_some() => (
+                Box::new(ViewStateBuilder::new(self.app.open_selected()).build()),
                 None,
             ),
             Message::NoOp | Message::SelectMode => (self, None),
             Message::Escape => (
-                Box::new(ViewStateBuilder::default().mode(Home).build()),
+                Box::new(
+                    ViewStateBuilder::new(self.app.cancel())
+                        .selected(Component::Library)
+                        .build(),
+                ),
+                None,
+            ),
+            message => self.update_app(message),
+        }
+    }
+
+    fn render(&mut self, frame: &mut Frame) {
+        let (main, status) = ui::split_status_line(frame.size());
+        ui::render_library(frame, main, &mut self.app);
+        ui::render_status(frame, status, &self.app);
+    }
+}
+
+impl ViewStateTrait for ViewState<BrowseHistory> {
+    fn handle_input(&self, event: Event) -> Message {
+        match event {
+            Event::Key(key) if self.app.mode.searching => match key.code {
+                KeyCode::Esc | KeyCode::Enter => Message::Escape,
+                _ => Message::Input(key),
+            },
+            Event::Key(key) => match key.code {
+                KeyCode::Char('q') | KeyCode::Esc => Message::Escape,
+                KeyCode::Char('/') => Message::Search,
+                KeyCode::Char('r') => Message::Execute,
+                KeyCode::Enter => Message::SelectMode,
+                KeyCode::Down | KeyCode::Char('j') => Message::Next,
+                KeyCode::Up | KeyCode::Char('k') => Message::Previous,
+                KeyCode::PageDown => Message::PageDown,
+                KeyCode::PageUp => Message::PageUp,
+                KeyCode::Char('G') | KeyCode::End => Message::Last,
+                KeyCode::Char('g') | KeyCode::Home => Message::First,
+                _ => Message::NoOp,
+            },
+            _ => Message::NoOp,
+        }
+    }
+
+    fn update(self: Box<Self>, message: Message) -> (ViewStateBox, Option<Message>) {
+        let scroll = |app: model::App<BrowseHistory>, rows: isize| -> (ViewStateBox, _) {
+            (
+                Box::new(ViewStateBuilder::new(app.scroll_history(rows)).build()),
+                None,
+            )
+        };
+        match message {
+            Message::Next => scroll(self.app, 1),
+            Message::Previous => scroll(self.app, -1),
+            Message::PageDown => scroll(self.app, RESULTS_PAGE),
+            Message::PageUp => scroll(self.app, -RESULTS_PAGE),
+            Message::Last => scroll(self.app, isize::MAX),
+            Message::First => scroll(self.app, isize::MIN),
+            Message::Search => (
+                Box::new(ViewStateBuilder::new(self.app.start_search()).build()),
+                None,
+            ),
+            Message::Input(key) => (
+                Box::new(ViewStateBuilder::new(self.app.search_input(key)).build()),
+                None,
+            ),
+            Message::HistoryLoaded(search, result) => (
+                Box::new(ViewStateBuilder::new(self.app.history_loaded(search, result)).build()),
+                None,
+            ),
+            Message::SelectMode => (
+                Box::new(ViewStateBuilder::new(self.app.load_entry()).build()),
+                None,
+            ),
+            // The editor runs it, asking for any parameters first.
+            Message::Execute if self.app.selected_entry().is_some() => (
+                Box::new(ViewStateBuilder::new(self.app.load_entry()).build()),
+                Some(Message::Execute),
+            ),
+            Message::NoOp => (self, None),
+            Message::Escape if self.app.mode.searching => (
+                Box::new(ViewStateBuilder::new(self.app.finish_search()).build()),
+                None,
+            ),
+            Message::Escape => (
+                Box::new(
+                    ViewStateBuilder::new(self.app.cancel())
+                        .selected(Component::Queries)
+                        .build(),
+                ),
                 None,
             ),
+            message => self.update_app(message),
         }
     }
 
-    fn render(&self, frame: &mut Frame) {
-        // render explore connection view
+    fn render(&mut self, frame: &mut Frame) {
+        let (main, status) = ui::split_status_line(frame.size());
+        ui::render_history(frame, main, &mut self.app);
+        ui::render_status(frame, status, &self.app);
     }
 }
 
 impl ViewStateTrait for ViewState<ExploreResults> {
     fn handle_input(&self, event: Event) -> Message {
         match event {
+            Event::Key(key) if key.modifiers.contains(KeyModifiers::CONTROL) => match key.code {
+                KeyCode::Char('c') => Message::Cancel,
+                _ => Message::NoOp,
+            },
             Event::Key(key) => match key.code {
                 KeyCode::Char('q') | KeyCode::Esc => Message::Escape,
+                KeyCode::Down | KeyCode::Char('j') => Message::Next,
+                KeyCode::Up | KeyCode::Char('k') => Message::Previous,
+                KeyCode::PageDown => Message::PageDown,
+                KeyCode::PageUp => Message::PageUp,
+                KeyCode::Char('G') | KeyCode::End => Message::Last,
+                KeyCode::Char('g') | KeyCode::Home => Message::First,
+                KeyCode::Tab => Message::NextTab,
+                KeyCode::BackTab => Message::PreviousTab,
+                KeyCode::Right | KeyCode::Char('l') => Message::NextColumn,
+                KeyCode::Left | KeyCode::Char('h') => Message::PreviousColumn,
+                KeyCode::Char('>') => Message::ResizeColumn(2),
+                KeyCode::Char('<') => Message::ResizeColumn(-2),
+                KeyCode::Char('=') => Message::FitColumn,
+                KeyCode::Char('f') => Message::FreezeColumns,
+                KeyCode::Enter | KeyCode::Char('e') => Message::ChangeRows(RowAction::Edit),
+                KeyCode::Char('i') => Message::ChangeRows(RowAction::Insert),
+                KeyCode::Char('c') => Message::ChangeRows(RowAction::Duplicate),
+                KeyCode::Char('d') => Message::ChangeRows(RowAction::Delete),
+                KeyCode::Char(' ') => Message::Mark,
+                KeyCode::Char('s') => Message::Save,
                 _ => Message::NoOp,
             },
             _ => Message::NoOp,
@@ -212,50 +821,562 @@ impl ViewStateTrait for ViewState<ExploreResults> {
     }
 
     fn update(self: Box<Self>, message: Message) -> (ViewStateBox, Option<Message>) {
+        let scroll = |app: model::App<ExploreResults>, rows: isize| -> (ViewStateBox, _) {
+            (
+                Box::new(ViewStateBuilder::new(app.scroll_results(rows)).build()),
+                None,
+            )
+        };
         match message {
             Message::Quit => (
-                Box::new(ViewStateBuilder::default().mode(Quit).build()),
+                Box::new(ViewStateBuilder::new(self.app.quit()).build()),
+                None,
+            ),
+            Message::Next => scroll(self.app, 1),
+            Message::Previous => scroll(self.app, -1),
+            Message::PageDown => scroll(self.app, RESULTS_PAGE),
+            Message::PageUp => scroll(self.app, -RESULTS_PAGE),
+            Message::Last => scroll(self.app, isize::MAX),
+            Message::First => scroll(self.app, isize::MIN),
+            Message::NextTab => (
+                Box::new(ViewStateBuilder::new(self.app.switch_result(true)).build()),
+                None,
+            ),
+            Message::PreviousTab => (
+                Box::new(ViewStateBuilder::new(self.app.switch_result(false)).build()),
+                None,
+            ),
+            Message::NextColumn => (
+                Box::new(ViewStateBuilder::new(self.app.move_column(true)).build()),
+                None,
+            ),
+            Message::PreviousColumn => (
+                Box::new(ViewStateBuilder::new(self.app.move_column(false)).build()),
+                None,
+            ),
+            Message::ResizeColumn(change) => (
+                Box::new(ViewStateBuilder::new(self.app.resize_column(change)).build()),
+                None,
+            ),
+            Message::FitColumn => (
+                Box::new(ViewStateBuilder::new(self.app.fit_column()).build()),
+                None,
+            ),
+            Message::FreezeColumns => (
+                Box::new(ViewStateBuilder::new(self.app.freeze_columns()).build()),
+                None,
+            ),
+            Message::Save if self.app.can_export() => (
+                Box::new(ViewStateBuilder::new(self.app.save_results()).build()),
+                None,
+            ),
+            Message::Save => (
+                Box::new(ViewStateBuilder::new(self.app.cannot_export()).build()),
+                None,
+            ),
+            Message::Mark => (
+                Box::new(ViewStateBuilder::new(self.app.mark_row()).build()),
+                None,
+            ),
+            Message::ChangeRows(action) if self.app.can_change(action) => match action {
+                RowAction::Edit => (
+                    Box::new(ViewStateBuilder::new(self.app.edit_cell()).build()),
+                    None,
+                ),
+                RowAction::Insert => (
+                    Box::new(ViewStateBuilder::new(self.app.insert_row(false)).build()),
+                    None,
+                ),
+                RowAction::Duplicate => (
+                    Box::new(ViewStateBuilder::new(self.app.insert_row(true)).build()),
+                    None,
+                ),
+                RowAction::Delete => (
+                    Box::new(ViewStateBuilder::new(self.app.delete_rows()).build()),
+                    None,
+                ),
+            },
+            Message::ChangeRows(action) => (
+                Box::new(ViewStateBuilder::new(self.app.cannot_change(action)).build()),
                 None,
             ),
             Message::NoOp | Message::SelectMode => (self, None),
             Message::Escape => (
-                Box::new(ViewStateBuilder::default().mode(Home).build()),
+                Box::new(
+                    ViewStateBuilder::new(self.app.cancel())
+                        .selected(Component::Results)
+                        .build(),
+                ),
                 None,
             ),
+            message => self.update_app(message),
         }
     }
 
-    fn render(&self, frame: &mut Frame) {
-        // render explore results view
+    fn render(&mut self, frame: &mut Frame) {
+        let (main, status) = ui::split_status_line(frame.size());
+        let title = ui::results_title(&self.app);
+        let (results, selected, table, grid) = self.app.results_table();
+        ui::render_results(
+            frame,
+            main,
+            results,
+            selected,
+            ui::panel(title, true),
+            table,
+            Some(grid),
+        );
+        ui::render_status(frame, status, &self.app);
     }
 }
 
-impl ViewStateTrait for ViewState<EditQuery> {
+impl ViewStateTrait for ViewState<SaveResults> {
     fn handle_input(&self, event: Event) -> Message {
         match event {
             Event::Key(key) => match key.code {
-                KeyCode::Char('q') | KeyCode::Esc => Message::Escape,
+                KeyCode::Esc => Message::Escape,
+                KeyCode::Enter => Message::Save,
+                KeyCode::Tab | KeyCode::Down => Message::Next,
+                KeyCode::BackTab | KeyCode::Up => Message::Previous,
+                _ => Message::Input(key),
+            },
+            _ => Message::NoOp,
+        }
+    }
+
+    fn update(self: Box<Self>, message: Message) -> (ViewStateBox, Option<Message>) {
+        match message {
+            Message::Next => (
+                Box::new(ViewStateBuilder::new(self.app.switch_format(true)).build()),
+                None,
+            ),
+            Message::Previous => (
+                Box::new(ViewStateBuilder::new(self.app.switch_format(false)).build()),
+                None,
+            ),
+            Message::Input(key) => (
+                Box::new(ViewStateBuilder::new(self.app.path_input(key)).build()),
+                None,
+            ),
+            Message::Save => (
+                Box::new(ViewStateBuilder::new(self.app.save()).build()),
+                None,
+            ),
+            Message::Escape => (
+                Box::new(ViewStateBuilder::new(self.app.back_to_results()).build()),
+                None,
+            ),
+            Message::NoOp => (self, None),
+            message => self.update_app(message),
+        }
+    }
+
+    fn render(&mut self, frame: &mut Frame) {
+        let (main, status) = ui::split_status_line(frame.size());
+        let [results, form] = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(3), Constraint::Length(4)])
+            .areas(main);
+        let title = ui::results_title(&self.app);
+        let (tabs, selected, table) = self.app.results_table();
+        ui::render_results(
+            frame,
+            results,
+            tabs,
+            selected,
+            ui::panel(title, false),
+            table,
+            None,
+        );
+        ui::render_save_results(frame, form, &self.app);
+        ui::render_status(frame, status, &self.app);
+    }
+}
+
+impl ViewStateTrait for ViewState<EditCell> {
+    fn handle_input(&self, event: Event) -> Message {
+        let confirming = self.app.mode.update.is_some();
+        match event {
+            Event::Key(key) => match key.code {
+                KeyCode::Esc => Message::Escape,
+                KeyCode::Enter => Message::Execute,
+                KeyCode::Char('y') if confirming => Message::Execute,
+                KeyCode::Char('n') if confirming => Message::Escape,
+                _ if confirming => Message::NoOp,
+                _ => Message::Input(key),
+            },
+            _ => Message::NoOp,
+        }
+    }
+
+    fn update(self: Box<Self>, message: Message) -> (ViewStateBox, Option<Message>) {
+        let confirming = self.app.mode.update.is_some();
+        match message {
+            Message::Input(key) => (
+                Box::new(ViewStateBuilder::new(self.app.cell_input(key)).build()),
+                None,
+            ),
+            Message::Execute if confirming => (
+                Box::new(ViewStateBuilder::new(self.app.save_cell()).build()),
+                None,
+            ),
+            Message::Execute => (
+                Box::new(ViewStateBuilder::new(self.app.review_change()).build()),
+                None,
+            ),
+            Message::Escape if confirming => (
+                Box::new(ViewStateBuilder::new(self.app.keep_editing()).build()),
+                None,
+            ),
+            Message::Escape => (
+                Box::new(ViewStateBuilder::new(self.app.back_to_results()).build()),
+                None,
+            ),
+            Message::NoOp => (self, None),
+            message => self.update_app(message),
+        }
+    }
+
+    fn render(&mut self, frame: &mut Frame) {
+        let (main, status) = ui::split_status_line(frame.size());
+        let [results, form] = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(3), Constraint::Length(5)])
+            .areas(main);
+        let title = ui::results_title(&self.app);
+        let (tabs, selected, table, grid) = self.app.results_table();
+        ui::render_results(
+            frame,
+            results,
+            tabs,
+            selected,
+            ui::panel(title, false),
+            table,
+            Some(grid),
+        );
+        ui::render_edit_cell(frame, form, &self.app);
+        ui::render_status(frame, status, &self.app);
+    }
+}
+
+impl ViewStateTrait for ViewState<InsertRow> {
+    fn handle_input(&self, event: Event) -> Message {
+        let confirming = self.app.mode.insert.is_some();
+        match event {
+            Event::Key(key) => match key.code {
+                KeyCode::Esc => Message::Escape,
+                KeyCode::Enter => Message::Execute,
+                KeyCode::Char('y') if confirming => Message::Execute,
+                KeyCode::Char('n') if confirming => Message::Escape,
+                _ if confirming => Message::NoOp,
+                KeyCode::Tab | KeyCode::Down => Message::Next,
+                KeyCode::BackTab | KeyCode::Up => Message::Previous,
+                _ => Message::Input(key),
+            },
+            _ => Message::NoOp,
+        }
+    }
+
+    fn update(self: Box<Self>, message: Message) -> (ViewStateBox, Option<Message>) {
+        let confirming = self.app.mode.insert.is_some();
+        match message {
+            Message::Next => (
+                Box::new(ViewStateBuilder::new(self.app.select_field(true)).build()),
+                None,
+            ),
+            Message::Previous => (
+                Box::new(ViewStateBuilder::new(self.app.select_field(false)).build()),
+                None,
+            ),
+            Message::Input(key) => (
+                Box::new(ViewStateBuilder::new(self.app.field_input(key)).build()),
+                None,
+            ),
+            Message::Execute if confirming => (
+                Box::new(ViewStateBuilder::new(self.app.insert()).build()),
+                None,
+            ),
+            Message::Execute => (
+                Box::new(ViewStateBuilder::new(self.app.review_insert()).build()),
+                None,
+            ),
+            Message::Escape if confirming => (
+                Box::new(ViewStateBuilder::new(self.app.keep_editing()).build()),
+                None,
+            ),
+            Message::Escape => (
+                Box::new(ViewStateBuilder::new(self.app.back_to_results()).build()),
+                None,
+            ),
+            Message::NoOp => (self, None),
+            message => self.update_app(message),
+        }
+    }
+
+    fn render(&mut self, frame: &mut Frame) {
+        let (main, status) = ui::split_status_line(frame.size());
+        let height = self.app.mode.inputs.len().clamp(3, 12) as u16 + 2;
+        let [results, form] = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(3), Constraint::Length(height)])
+            .areas(main);
+        let title = ui::results_title(&self.app);
+        let (tabs, selected, table, grid) = self.app.results_table();
+        ui::render_results(
+            frame,
+            results,
+            tabs,
+            selected,
+            ui::panel(title, false),
+            table,
+            Some(grid),
+        );
+        ui::render_insert_row(frame, form, &self.app);
+        ui::render_status(frame, status, &self.app);
+    }
+}
+
+impl ViewStateTrait for ViewState<DeleteRows> {
+    fn handle_input(&self, event: Event) -> Message {
+        match event {
+            Event::Key(key) => match key.code {
+                KeyCode::Enter | KeyCode::Char('y') => Message::Execute,
+                KeyCode::Esc | KeyCode::Char('n') => Message::Escape,
                 _ => Message::NoOp,
             },
             _ => Message::NoOp,
         }
     }
 
+    fn update(self: Box<Self>, message: Message) -> (ViewStateBox, Option<Message>) {
+        match message {
+            Message::Execute => (
+                Box::new(ViewStateBuilder::new(self.app.delete()).build()),
+                None,
+            ),
+            Message::Escape => (
+                Box::new(ViewStateBuilder::new(self.app.back_to_results()).build()),
+                None,
+            ),
+            Message::NoOp => (self, None),
+            message => self.update_app(message),
+        }
+    }
+
+    fn render(&mut self, frame: &mut Frame) {
+        let (main, status) = ui::split_status_line(frame.size());
+        let [results, form] = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(3), Constraint::Length(5)])
+            .areas(main);
+        let title = ui::results_title(&self.app);
+        let (tabs, selected, table, grid) = self.app.results_table();
+        ui::render_results(
+            frame,
+            results,
+            tabs,
+            selected,
+            ui::panel(title, false),
+            table,
+            Some(grid),
+        );
+        let (_, shown) = &self.app.mode.delete;
+        ui::render_confirm_change(frame, form, &self.app, shown);
+        ui::render_status(frame, status, &self.app);
+    }
+}
+
+/// How many rows Page Up and Page Down move through the results.
+const RESULTS_PAGE: isize = 20;
+
+impl ViewState<EditQuery> {
+    /// Whether the cursor is just after a word or a `.`, where Tab completes
+    /// rather than indents.
+    fn after_word(&self) -> bool {
+        let (row, column) = self.app.mode.editor.cursor();
+        let line = &self.app.mode.editor.lines()[row];
+        column
+            .checked_sub(1)
+            .and_then(|before| line.chars().nth(before))
+            .is_some_and(|c| c.is_alphanumeric() || c == '_' || c == '.')
+    }
+}
+
+impl ViewStateTrait for ViewState<EditQuery> {
+    fn handle_input(&self, event: Event) -> Message {
+        match event {
+            Event::Key(key) if key.modifiers.contains(KeyModifiers::CONTROL) => match key.code {
+                KeyCode::Char('r') => Message::Execute,
+                KeyCode::Char('c') => Message::Cancel,
+                KeyCode::Char('n') => Message::NextConnection,
+                KeyCode::Char('t') => Message::BeginTransaction,
+                KeyCode::Char('s') => Message::EndTransaction(TransactionEnd::Commit),
+                KeyCode::Char('z') => Message::EndTransaction(TransactionEnd::Rollback),
+                KeyCode::Char(' ') => Message::Complete,
+                KeyCode::Char('e') => Message::Explain,
+                _ => Message::Input(key),
+            },
+            Event::Key(key) if self.app.mode.completion.is_some() => match key.code {
+                KeyCode::Esc => Message::Escape,
+                KeyCode::Down => Message::Next,
+                KeyCode::Up => Message::Previous,
+                KeyCode::Enter | KeyCode::Tab => Message::SelectMode,
+                _ => Message::Input(key),
+            },
+            Event::Key(key) => match key.code {
+                KeyCode::Esc => Message::Escape,
+                KeyCode::F(5) => Message::Execute,
+                KeyCode::Tab if self.after_word() => Message::Complete,
+                _ => Message::Input(key),
+            },
+            _ => Message::NoOp,
+        }
+    }
+
     fn update(self: Box<Self>, message: Message) -> (ViewStateBox, Option<Message>) {
         match message {
             Message::Quit => (
-                Box::new(ViewStateBuilder::default().mode(Quit).build()),
+                Box::new(ViewStateBuilder::new(self.app.quit()).build()),
+                None,
+            ),
+            Message::Input(key) => (
+                Box::new(ViewStateBuilder::new(self.app.input(key)).build()),
+                None,
+            ),
+            Message::Complete => (
+                Box::new(ViewStateBuilder::new(self.app.complete()).build()),
+                None,
+            ),
+            Message::Next => (
+                Box::new(ViewStateBuilder::new(self.app.select_completion(true)).build()),
+                None,
+            ),
+            Message::Previous => (
+                Box::new(ViewStateBuilder::new(self.app.select_completion(false)).build()),
+                None,
+            ),
+            Message::SelectMode if self.app.mode.completion.is_some() => (
+                Box::new(ViewStateBuilder::new(self.app.accept_completion()).build()),
+                None,
+            ),
+            Message::Escape if self.app.mode.completion.is_some() => (
+                Box::new(ViewStateBuilder::new(self.app.close_completion()).build()),
+                None,
+            ),
+            Message::Execute if !self.app.parameters().is_empty() => (
+                Box::new(ViewStateBuilder::new(self.app.bind_parameters()).build()),
+                None,
+            ),
+            Message::Execute => (
+                Box::new(ViewStateBuilder::new(self.app.execute_query()).build()),
+                None,
+            ),
+            Message::NextConnection => (
+                Box::new(ViewStateBuilder::new(self.app.next_connection()).build()),
+                None,
+            ),
+            Message::Explain if self.app.can_explain() => (
+                Box::new(ViewStateBuilder::new(self.app.explain_statement()).build()),
+                None,
+            ),
+            Message::Explain => (
+                Box::new(ViewStateBuilder::new(self.app.cannot_explain()).build()),
                 None,
             ),
             Message::NoOp | Message::SelectMode => (self, None),
             Message::Escape => (
-                Box::new(ViewStateBuilder::default().mode(Home).build()),
+                Box::new(
+                    ViewStateBuilder::new(self.app.finish_editing())
+                        .selected(Component::Queries)
+                        .build(),
+                ),
+                None,
+            ),
+            message => self.update_app(message),
+        }
+    }
+
+    fn render(&mut self, frame: &mut Frame) {
+        let (main, status) = ui::split_status_line(frame.size());
+        let rows = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Percentage(40), Constraint::Percentage(60)])
+            .split(main);
+
+        let mut title = Line::from(match self.app.connections().active() {
+            Some(open) => format!(
+                "Query on {} (Ctrl+R run, Ctrl+E explain, Ctrl+N next connection, Ctrl+T transaction, Tab complete, Esc done)",
+                open.name
+            ),
+            None => "Query (not connected, Esc done)".to_string(),
+        });
+        title.spans.extend(ui::transaction_indicator(&self.app));
+        self.app.mode.editor.set_block(ui::panel(title, true));
+        frame.render_widget(self.app.mode.editor.widget(), rows[0]);
+        ui::highlight_query(frame, rows[0], &mut self.app);
+        ui::render_completion(frame, rows[0], &mut self.app);
+        let results_title = ui::results_title(&self.app);
+        ui::render_results(
+            frame,
+            rows[1],
+            self.app.statement_results(),
+            self.app.selected_result(),
+            ui::panel(results_title.as_str(), false),
+            &mut TableState::default(),
+            None,
+        );
+        ui::render_status(frame, status, &self.app);
+    }
+}
+
+impl ViewStateTrait for ViewState<BindParameters> {
+    fn handle_input(&self, event: Event) -> Message {
+        match event {
+            Event::Key(key) if key.modifiers.contains(KeyModifiers::CONTROL) => match key.code {
+                KeyCode::Char('r') => Message::Execute,
+                _ => Message::NoOp,
+            },
+            Event::Key(key) => match key.code {
+                KeyCode::Esc => Message::Escape,
+                KeyCode::Enter => Message::Execute,
+                KeyCode::Down | KeyCode::Tab => Message::Next,
+                KeyCode::Up | KeyCode::BackTab => Message::Previous,
+                _ => Message::Input(key),
+            },
+            _ => Message::NoOp,
+        }
+    }
+
+    fn update(self: Box<Self>, message: Message) -> (ViewStateBox, Option<Message>) {
+        match message {
+            Message::Next => (
+                Box::new(ViewStateBuilder::new(self.app.select_parameter(true)).build()),
+                None,
+            ),
+            Message::Previous => (
+                Box::new(ViewStateBuilder::new(self.app.select_parameter(false)).build()),
+                None,
+            ),
+            Message::Input(key) => (
+                Box::new(ViewStateBuilder::new(self.app.parameter_input(key)).build()),
+                None,
+            ),
+            Message::Execute => (
+                Box::new(ViewStateBuilder::new(self.app.run()).build()),
+                None,
+            ),
+            Message::Escape => (
+                Box::new(ViewStateBuilder::new(self.app.back_to_editor()).build()),
                 None,
             ),
+            Message::NoOp => (self, None),
+            message => self.update_app(message),
         }
     }
 
-    fn render(&self, frame: &mut Frame) {
-        // render edit query view
+    fn render(&mut self, frame: &mut Frame) {
+        let (main, status) = ui::split_status_line(frame.size());
+        ui::render_parameters(frame, main, &self.app);
+        ui::render_status(frame, status, &self.app);
     }
 }